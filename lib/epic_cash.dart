@@ -3,6 +3,8 @@ import 'dart:io' as io;
 
 import 'package:ffi/ffi.dart';
 
+import 'utils/epic_errors.dart';
+
 final DynamicLibrary epicCashNative = io.Platform.isWindows
     ? DynamicLibrary.open("libepic_cash_wallet.dll")
     : io.Platform.environment.containsKey('FLUTTER_TEST')
@@ -98,14 +100,33 @@ typedef TransactionFeesFFI = Pointer<Utf8> Function(
 typedef DeleteWallet = Pointer<Utf8> Function(Pointer<Utf8>, Pointer<Utf8>);
 typedef DeleteWalletFFI = Pointer<Utf8> Function(Pointer<Utf8>, Pointer<Utf8>);
 
+typedef ChangePassword = Pointer<Utf8> Function(
+    Pointer<Utf8>, Pointer<Utf8>, Pointer<Utf8>);
+typedef ChangePasswordFFI = Pointer<Utf8> Function(
+    Pointer<Utf8>, Pointer<Utf8>, Pointer<Utf8>);
+
 typedef OpenWallet = Pointer<Utf8> Function(Pointer<Utf8>, Pointer<Utf8>);
 typedef OpenWalletFFI = Pointer<Utf8> Function(Pointer<Utf8>, Pointer<Utf8>);
 
+typedef ManagerCreateWallet = Pointer<Utf8> Function(Pointer<Utf8>,
+    Pointer<Utf8>, Pointer<Utf8>, Pointer<Utf8>, Pointer<Utf8>);
+typedef ManagerCreateWalletFFI = Pointer<Utf8> Function(Pointer<Utf8>,
+    Pointer<Utf8>, Pointer<Utf8>, Pointer<Utf8>, Pointer<Utf8>);
+
 typedef TxHttpSend = Pointer<Utf8> Function(Pointer<Utf8>, Pointer<Int8>,
     Pointer<Int8>, Pointer<Utf8>, Pointer<Int8>, Pointer<Utf8>);
 typedef TxHttpSendFFI = Pointer<Utf8> Function(Pointer<Utf8>, Pointer<Int8>,
     Pointer<Int8>, Pointer<Utf8>, Pointer<Int8>, Pointer<Utf8>);
 
+typedef StringFree = void Function(Pointer<Utf8>);
+typedef StringFreeFFI = Void Function(Pointer<Utf8>);
+
+/// Every string returned by the library is owned by the caller and must be
+/// released with `rust_string_free` (never `malloc.free`).
+final StringFree _stringFree = epicCashNative
+    .lookup<NativeFunction<StringFreeFFI>>("rust_string_free")
+    .asFunction();
+
 final WalletMnemonic _walletMnemonic = epicCashNative
     .lookup<NativeFunction<WalletMnemonicFFI>>("get_mnemonic")
     .asFunction();
@@ -114,12 +135,29 @@ String walletMnemonic() {
   Pointer<Utf8>? ptr;
   try {
     ptr = _walletMnemonic();
-    return ptr.toDartString();
+    return unwrapFfiResult(ptr.toDartString());
   } catch (_) {
     rethrow;
   } finally {
     if (ptr != null) {
-      malloc.free(ptr);
+      _stringFree(ptr);
+    }
+  }
+}
+
+final WalletMnemonic _libInfo = epicCashNative
+    .lookup<NativeFunction<WalletMnemonicFFI>>("rust_lib_info")
+    .asFunction();
+
+/// Describe the loaded native library, as JSON.
+String libInfo() {
+  Pointer<Utf8>? ptr;
+  try {
+    ptr = _libInfo();
+    return unwrapFfiResult(ptr.toDartString());
+  } finally {
+    if (ptr != null) {
+      _stringFree(ptr);
     }
   }
 }
@@ -142,7 +180,7 @@ String initWallet(
 
   try {
     ptr = _initWallet(configPtr, mnemonicPtr, passwordPtr, namePtr);
-    return ptr.toDartString();
+    return unwrapFfiResult(ptr.toDartString());
   } catch (_) {
     rethrow;
   } finally {
@@ -151,7 +189,7 @@ String initWallet(
     malloc.free(passwordPtr);
     malloc.free(namePtr);
     if (ptr != null) {
-      malloc.free(ptr);
+      _stringFree(ptr);
     }
   }
 }
@@ -177,7 +215,7 @@ Future<String> getWalletInfo(
       refreshFromNodePtr,
       minConfPtr,
     );
-    return ptr.toDartString();
+    return unwrapFfiResult(ptr.toDartString());
   } catch (_) {
     rethrow;
   } finally {
@@ -185,7 +223,7 @@ Future<String> getWalletInfo(
     malloc.free(refreshFromNodePtr);
     malloc.free(minConfPtr);
     if (ptr != null) {
-      malloc.free(ptr);
+      _stringFree(ptr);
     }
   }
 }
@@ -208,7 +246,7 @@ String recoverWallet(
 
   try {
     ptr = _recoverWallet(configPtr, passwordPtr, mnemonicPtr, namePtr);
-    return ptr.toDartString();
+    return unwrapFfiResult(ptr.toDartString());
   } catch (_) {
     rethrow;
   } finally {
@@ -217,7 +255,7 @@ String recoverWallet(
     malloc.free(mnemonicPtr);
     malloc.free(namePtr);
     if (ptr != null) {
-      malloc.free(ptr);
+      _stringFree(ptr);
     }
   }
 }
@@ -243,7 +281,7 @@ Future<String> scanOutPuts(
       startHeightPtr,
       numberOfBlocksPtr,
     );
-    return ptr.toDartString();
+    return unwrapFfiResult(ptr.toDartString());
   } catch (_) {
     rethrow;
   } finally {
@@ -251,7 +289,7 @@ Future<String> scanOutPuts(
     malloc.free(startHeightPtr);
     malloc.free(numberOfBlocksPtr);
     if (ptr != null) {
-      malloc.free(ptr);
+      _stringFree(ptr);
     }
   }
 }
@@ -284,12 +322,12 @@ bool epicboxListenerStop(Pointer<Void> handler) {
 
   try {
     ptr = _epicboxListenerStop(handler);
-    return ptr.toDartString() == "true";
+    return unwrapFfiResult(ptr.toDartString()) == "true";
   } catch (_) {
     return false;
   } finally {
     if (ptr != null) {
-      malloc.free(ptr);
+      _stringFree(ptr);
     }
   }
 }
@@ -309,12 +347,12 @@ bool epicboxListenerIsRunning(Pointer<Void>? handler) {
 
   try {
     ptr = _epicboxListenerIsRunning(handler);
-    return ptr.toDartString() == "true";
+    return unwrapFfiResult(ptr.toDartString()) == "true";
   } catch (_) {
     return false;
   } finally {
     if (ptr != null) {
-      malloc.free(ptr);
+      _stringFree(ptr);
     }
   }
 }
@@ -323,6 +361,8 @@ final CreateTransaction _createTransaction = epicCashNative
     .lookup<NativeFunction<CreateTransactionFFI>>("rust_create_tx")
     .asFunction();
 
+/// Create a transaction. With [sweep] set, [amount] is ignored and the whole
+/// spendable balance less the fee is sent.
 Future<String> createTransaction(
   String wallet,
   int amount,
@@ -332,10 +372,12 @@ Future<String> createTransaction(
   int minimumConfirmations,
   String note, {
   bool returnSlate = false,
+  bool sweep = false,
 }) async {
   Pointer<Utf8>? ptr;
   final walletPtr = wallet.toNativeUtf8();
-  final amountPtr = amount.toString().toNativeUtf8().cast<Int8>();
+  final amountPtr =
+      (sweep ? 'max' : amount.toString()).toNativeUtf8().cast<Int8>();
   final addressPtr = address.toNativeUtf8();
   final secretKeyPtr = secretKey.toString().toNativeUtf8().cast<Int8>();
   final epicboxConfigPtr = epicboxConfig.toNativeUtf8();
@@ -355,7 +397,7 @@ Future<String> createTransaction(
       notePtr,
       returnSlatePtr,
     );
-    return ptr.toDartString();
+    return unwrapFfiResult(ptr.toDartString());
   } catch (_) {
     rethrow;
   } finally {
@@ -368,7 +410,7 @@ Future<String> createTransaction(
     malloc.free(notePtr);
     malloc.free(returnSlatePtr);
     if (ptr != null) {
-      malloc.free(ptr);
+      _stringFree(ptr);
     }
   }
 }
@@ -385,14 +427,14 @@ Future<String> getTransactions(String wallet, int refreshFromNode) async {
 
   try {
     ptr = _getTransactions(walletPtr, refreshFromNodePtr);
-    return ptr.toDartString();
+    return unwrapFfiResult(ptr.toDartString());
   } catch (_) {
     rethrow;
   } finally {
     malloc.free(walletPtr);
     malloc.free(refreshFromNodePtr);
     if (ptr != null) {
-      malloc.free(ptr);
+      _stringFree(ptr);
     }
   }
 }
@@ -408,14 +450,14 @@ String cancelTransaction(String wallet, String transactionId) {
 
   try {
     ptr = _cancelTransaction(walletPtr, transactionIdPtr);
-    return ptr.toDartString();
+    return unwrapFfiResult(ptr.toDartString());
   } catch (_) {
     rethrow;
   } finally {
     malloc.free(walletPtr);
     malloc.free(transactionIdPtr);
     if (ptr != null) {
-      malloc.free(ptr);
+      _stringFree(ptr);
     }
   }
 }
@@ -430,14 +472,14 @@ int getChainHeight(String config) {
 
   try {
     ptr = _getChainHeight(configPtr);
-    final latestHeight = ptr.toDartString();
+    final latestHeight = unwrapFfiResult(ptr.toDartString());
     return int.parse(latestHeight);
   } catch (_) {
     rethrow;
   } finally {
     malloc.free(configPtr);
     if (ptr != null) {
-      malloc.free(ptr);
+      _stringFree(ptr);
     }
   }
 }
@@ -454,7 +496,7 @@ String getAddressInfo(String wallet, int index, String epicboxConfig) {
 
   try {
     ptr = _addressInfo(walletPtr, indexPtr, epicboxConfigPtr);
-    return ptr.toDartString();
+    return unwrapFfiResult(ptr.toDartString());
   } catch (_) {
     rethrow;
   } finally {
@@ -462,7 +504,7 @@ String getAddressInfo(String wallet, int index, String epicboxConfig) {
     malloc.free(indexPtr);
     malloc.free(epicboxConfigPtr);
     if (ptr != null) {
-      malloc.free(ptr);
+      _stringFree(ptr);
     }
   }
 }
@@ -477,13 +519,13 @@ String validateSendAddress(String address) {
 
   try {
     ptr = _validateSendAddress(addressPtr);
-    return ptr.toDartString();
+    return unwrapFfiResult(ptr.toDartString());
   } catch (_) {
     rethrow;
   } finally {
     malloc.free(addressPtr);
     if (ptr != null) {
-      malloc.free(ptr);
+      _stringFree(ptr);
     }
   }
 }
@@ -505,7 +547,7 @@ Future<String> getTransactionFees(
   try {
     ptr = _transactionFees(
         walletPtr, amountPtr.cast<Int8>(), minConfPtr.cast<Int8>());
-    return ptr.toDartString();
+    return unwrapFfiResult(ptr.toDartString());
   } catch (_) {
     rethrow;
   } finally {
@@ -513,29 +555,296 @@ Future<String> getTransactionFees(
     malloc.free(amountPtr);
     malloc.free(minConfPtr);
     if (ptr != null) {
-      malloc.free(ptr);
+      _stringFree(ptr);
     }
   }
 }
 
+final OpenWallet _maxSendable = epicCashNative
+    .lookup<NativeFunction<OpenWalletFFI>>("rust_max_sendable")
+    .asFunction();
+
+/// Get the largest amount a sweep can send, as JSON
+/// `{amount, fee, inputs, spendable}`.
+String maxSendable(String wallet, int minimumConfirmations) =>
+    _walletLabelCall(_maxSendable, wallet, minimumConfirmations.toString());
+
 final DeleteWallet _deleteWallet = epicCashNative
     .lookup<NativeFunction<DeleteWalletFFI>>("rust_delete_wallet")
     .asFunction();
 
+/// Delete the wallet in [config], closing the session that has it open.
+/// Returns `{removed, kept, closed_sessions}` as JSON.
 Future<String> deleteWallet(String wallet, String config) async {
   Pointer<Utf8>? ptr;
   final configPtr = config.toNativeUtf8();
   final walletPtr = wallet.toNativeUtf8();
   try {
     ptr = _deleteWallet(walletPtr, configPtr);
-    return ptr.toDartString();
+    return unwrapFfiResult(ptr.toDartString());
   } catch (_) {
     rethrow;
   } finally {
     malloc.free(configPtr);
     malloc.free(walletPtr);
     if (ptr != null) {
-      malloc.free(ptr);
+      _stringFree(ptr);
+    }
+  }
+}
+
+final ChangePassword _changePassword = epicCashNative
+    .lookup<NativeFunction<ChangePasswordFFI>>("rust_change_password")
+    .asFunction();
+
+/// Re-encrypt the seed of the wallet in [config] under [newPassword].
+void changePassword(String config, String oldPassword, String newPassword) {
+  Pointer<Utf8>? ptr;
+  final configPtr = config.toNativeUtf8();
+  final oldPtr = oldPassword.toNativeUtf8();
+  final newPtr = newPassword.toNativeUtf8();
+  try {
+    ptr = _changePassword(configPtr, oldPtr, newPtr);
+    unwrapFfiResult(ptr.toDartString());
+  } finally {
+    malloc.free(configPtr);
+    malloc.free(oldPtr);
+    malloc.free(newPtr);
+    if (ptr != null) {
+      _stringFree(ptr);
+    }
+  }
+}
+
+final StatusPoll _accounts = epicCashNative
+    .lookup<NativeFunction<StatusPollFFI>>("rust_accounts")
+    .asFunction();
+
+final OpenWallet _createAccount = epicCashNative
+    .lookup<NativeFunction<OpenWalletFFI>>("rust_create_account")
+    .asFunction();
+
+final OpenWallet _setActiveAccount = epicCashNative
+    .lookup<NativeFunction<OpenWalletFFI>>("rust_set_active_account")
+    .asFunction();
+
+/// List a wallet session's accounts as a JSON array of `{label, path, active}`.
+String accounts(String wallet) {
+  Pointer<Utf8>? ptr;
+  final walletPtr = wallet.toNativeUtf8();
+  try {
+    ptr = _accounts(walletPtr);
+    return unwrapFfiResult(ptr.toDartString());
+  } finally {
+    malloc.free(walletPtr);
+    if (ptr != null) {
+      _stringFree(ptr);
+    }
+  }
+}
+
+String _walletLabelCall(OpenWallet fn, String wallet, String label) {
+  Pointer<Utf8>? ptr;
+  final walletPtr = wallet.toNativeUtf8();
+  final labelPtr = label.toNativeUtf8();
+  try {
+    ptr = fn(walletPtr, labelPtr);
+    return unwrapFfiResult(ptr.toDartString());
+  } finally {
+    malloc.free(walletPtr);
+    malloc.free(labelPtr);
+    if (ptr != null) {
+      _stringFree(ptr);
+    }
+  }
+}
+
+/// Create a named account, returning its parent key path.
+String createAccount(String wallet, String label) =>
+    _walletLabelCall(_createAccount, wallet, label);
+
+/// Switch the account a wallet session sends, receives and reports for.
+void setActiveAccount(String wallet, String label) {
+  _walletLabelCall(_setActiveAccount, wallet, label);
+}
+
+final ChangePassword _backupWallet = epicCashNative
+    .lookup<NativeFunction<ChangePasswordFFI>>("rust_backup_wallet")
+    .asFunction();
+
+final ChangePassword _restoreWalletBackup = epicCashNative
+    .lookup<NativeFunction<ChangePasswordFFI>>("rust_restore_wallet_backup")
+    .asFunction();
+
+String _threeStringCall(ChangePassword fn, String a, String b, String c) {
+  Pointer<Utf8>? ptr;
+  final aPtr = a.toNativeUtf8();
+  final bPtr = b.toNativeUtf8();
+  final cPtr = c.toNativeUtf8();
+  try {
+    ptr = fn(aPtr, bPtr, cPtr);
+    return unwrapFfiResult(ptr.toDartString());
+  } finally {
+    malloc.free(aPtr);
+    malloc.free(bPtr);
+    malloc.free(cPtr);
+    if (ptr != null) {
+      _stringFree(ptr);
+    }
+  }
+}
+
+/// Back up the closed wallet in [config] to [archivePath], encrypted with
+/// [password]. Returns `{files, bytes}` as JSON.
+String backupWallet(String config, String password, String archivePath) =>
+    _threeStringCall(_backupWallet, config, password, archivePath);
+
+/// Restore the backup at [archivePath] into the new or empty [walletDir].
+/// Returns `{files, bytes}` as JSON.
+String restoreWalletBackup(
+        String archivePath, String password, String walletDir) =>
+    _threeStringCall(_restoreWalletBackup, archivePath, password, walletDir);
+
+final StatusPoll _managerListWallets = epicCashNative
+    .lookup<NativeFunction<StatusPollFFI>>("rust_manager_list_wallets")
+    .asFunction();
+
+final ManagerCreateWallet _managerCreateWallet = epicCashNative
+    .lookup<NativeFunction<ManagerCreateWalletFFI>>(
+        "rust_manager_create_wallet")
+    .asFunction();
+
+final WalletInit _managerOpenWallet = epicCashNative
+    .lookup<NativeFunction<WalletInitFFI>>("rust_manager_open_wallet")
+    .asFunction();
+
+final ChangePassword _managerRenameWallet = epicCashNative
+    .lookup<NativeFunction<ChangePasswordFFI>>("rust_manager_rename_wallet")
+    .asFunction();
+
+final OpenWallet _managerDeleteWallet = epicCashNative
+    .lookup<NativeFunction<OpenWalletFFI>>("rust_manager_delete_wallet")
+    .asFunction();
+
+/// List the wallets kept under [root] as a JSON array of
+/// `{name, chain, created_at, wallet_dir}`.
+String managerListWallets(String root) {
+  Pointer<Utf8>? ptr;
+  final rootPtr = root.toNativeUtf8();
+  try {
+    ptr = _managerListWallets(rootPtr);
+    return unwrapFfiResult(ptr.toDartString());
+  } finally {
+    malloc.free(rootPtr);
+    if (ptr != null) {
+      _stringFree(ptr);
+    }
+  }
+}
+
+/// Create a wallet named [name] under [root] on the chain of [config].
+/// Returns its entry as JSON.
+String managerCreateWallet(
+  String root,
+  String config,
+  String name,
+  String mnemonic,
+  String password,
+) {
+  Pointer<Utf8>? ptr;
+  final rootPtr = root.toNativeUtf8();
+  final configPtr = config.toNativeUtf8();
+  final namePtr = name.toNativeUtf8();
+  final mnemonicPtr = mnemonic.toNativeUtf8();
+  final passwordPtr = password.toNativeUtf8();
+  try {
+    ptr = _managerCreateWallet(
+        rootPtr, configPtr, namePtr, mnemonicPtr, passwordPtr);
+    return unwrapFfiResult(ptr.toDartString());
+  } finally {
+    malloc.free(rootPtr);
+    malloc.free(configPtr);
+    malloc.free(namePtr);
+    malloc.free(mnemonicPtr);
+    malloc.free(passwordPtr);
+    if (ptr != null) {
+      _stringFree(ptr);
+    }
+  }
+}
+
+/// Open the wallet named [name] under [root] and return its session id.
+String managerOpenWallet(
+  String root,
+  String config,
+  String name,
+  String password,
+) {
+  Pointer<Utf8>? ptr;
+  final rootPtr = root.toNativeUtf8();
+  final configPtr = config.toNativeUtf8();
+  final namePtr = name.toNativeUtf8();
+  final passwordPtr = password.toNativeUtf8();
+  try {
+    ptr = _managerOpenWallet(rootPtr, configPtr, namePtr, passwordPtr);
+    return unwrapFfiResult(ptr.toDartString());
+  } finally {
+    malloc.free(rootPtr);
+    malloc.free(configPtr);
+    malloc.free(namePtr);
+    malloc.free(passwordPtr);
+    if (ptr != null) {
+      _stringFree(ptr);
+    }
+  }
+}
+
+/// Rename the closed wallet [name] under [root]. Returns its entry as JSON.
+String managerRenameWallet(String root, String name, String newName) =>
+    _threeStringCall(_managerRenameWallet, root, name, newName);
+
+/// Delete the wallet [name] under [root]. Returns what was removed as JSON.
+String managerDeleteWallet(String root, String name) =>
+    _walletLabelCall(_managerDeleteWallet, root, name);
+
+final StatusPoll _walletStatus = epicCashNative
+    .lookup<NativeFunction<StatusPollFFI>>("rust_wallet_status")
+    .asFunction();
+
+/// Report whether the wallet in [config] exists and what it was created with,
+/// as JSON. Needs no password.
+String walletStatus(String config) {
+  Pointer<Utf8>? ptr;
+  final configPtr = config.toNativeUtf8();
+  try {
+    ptr = _walletStatus(configPtr);
+    return unwrapFfiResult(ptr.toDartString());
+  } finally {
+    malloc.free(configPtr);
+    if (ptr != null) {
+      _stringFree(ptr);
+    }
+  }
+}
+
+final OpenWallet _revealMnemonic = epicCashNative
+    .lookup<NativeFunction<OpenWalletFFI>>("rust_reveal_mnemonic")
+    .asFunction();
+
+/// Decrypt the seed of the wallet in [config] and return its recovery phrase.
+/// The native copy is zeroed when freed.
+String revealMnemonic(String config, String password) {
+  Pointer<Utf8>? ptr;
+  final configPtr = config.toNativeUtf8();
+  final pwPtr = password.toNativeUtf8();
+  try {
+    ptr = _revealMnemonic(configPtr, pwPtr);
+    return unwrapFfiResult(ptr.toDartString());
+  } finally {
+    malloc.free(configPtr);
+    malloc.free(pwPtr);
+    if (ptr != null) {
+      _stringFree(ptr);
     }
   }
 }
@@ -550,14 +859,38 @@ String openWallet(String config, String password) {
   final pwPtr = password.toNativeUtf8();
   try {
     ptr = _openWallet(configPtr, pwPtr);
-    return ptr.toDartString();
+    return unwrapFfiResult(ptr.toDartString());
   } catch (_) {
     rethrow;
   } finally {
     malloc.free(configPtr);
     malloc.free(pwPtr);
     if (ptr != null) {
-      malloc.free(ptr);
+      _stringFree(ptr);
+    }
+  }
+}
+
+typedef CloseWallet = Pointer<Utf8> Function(Pointer<Utf8>);
+typedef CloseWalletFFI = Pointer<Utf8> Function(Pointer<Utf8>);
+
+final CloseWallet _closeWallet = epicCashNative
+    .lookup<NativeFunction<CloseWalletFFI>>("rust_close_wallet")
+    .asFunction();
+
+/// Release the wallet behind [wallet]. The handle is invalid afterwards.
+String closeWallet(String wallet) {
+  Pointer<Utf8>? ptr;
+  final walletPtr = wallet.toNativeUtf8();
+  try {
+    ptr = _closeWallet(walletPtr);
+    return unwrapFfiResult(ptr.toDartString());
+  } catch (_) {
+    rethrow;
+  } finally {
+    malloc.free(walletPtr);
+    if (ptr != null) {
+      _stringFree(ptr);
     }
   }
 }
@@ -566,14 +899,18 @@ final TxHttpSend _txHttpSend = epicCashNative
     .lookup<NativeFunction<TxHttpSendFFI>>("rust_tx_send_http")
     .asFunction();
 
+/// Send a transaction over http. With [sweep] set, [amount] and
+/// [selectionStrategyIsAll] are ignored and the whole spendable balance less
+/// the fee is sent.
 Future<String> txHttpSend(
   String wallet,
   int selectionStrategyIsAll,
   int minimumConfirmations,
   String message,
   int amount,
-  String address,
-) async {
+  String address, {
+  bool sweep = false,
+}) async {
   Pointer<Utf8>? ptr;
 
   final walletPtr = wallet.toNativeUtf8();
@@ -582,7 +919,8 @@ Future<String> txHttpSend(
   final minConfsPtr =
       minimumConfirmations.toString().toNativeUtf8().cast<Int8>();
   final messagePtr = message.toNativeUtf8();
-  final amountPtr = amount.toString().toNativeUtf8().cast<Int8>();
+  final amountPtr =
+      (sweep ? 'max' : amount.toString()).toNativeUtf8().cast<Int8>();
   final addressPtr = address.toNativeUtf8();
 
   try {
@@ -595,7 +933,7 @@ Future<String> txHttpSend(
       addressPtr,
     );
 
-    return ptr.toDartString();
+    return unwrapFfiResult(ptr.toDartString());
   } catch (_) {
     rethrow;
   } finally {
@@ -606,7 +944,7 @@ Future<String> txHttpSend(
     malloc.free(amountPtr);
     malloc.free(addressPtr);
     if (ptr != null) {
-      malloc.free(ptr);
+      _stringFree(ptr);
     }
   }
 }
@@ -630,14 +968,14 @@ String txReceive(String wallet, String slateJson) {
 
   try {
     ptr = _txReceive(walletPtr, slateJsonPtr);
-    return ptr.toDartString();
+    return unwrapFfiResult(ptr.toDartString());
   } catch (_) {
     rethrow;
   } finally {
     malloc.free(walletPtr);
     malloc.free(slateJsonPtr);
     if (ptr != null) {
-      malloc.free(ptr);
+      _stringFree(ptr);
     }
   }
 }
@@ -661,14 +999,374 @@ String txFinalize(String wallet, String slateJson) {
 
   try {
     ptr = _txFinalize(walletPtr, slateJsonPtr);
-    return ptr.toDartString();
+    return unwrapFfiResult(ptr.toDartString());
   } catch (_) {
     rethrow;
   } finally {
     malloc.free(walletPtr);
     malloc.free(slateJsonPtr);
     if (ptr != null) {
-      malloc.free(ptr);
+      _stringFree(ptr);
+    }
+  }
+}
+
+typedef OwnerRpc = Pointer<Utf8> Function(Pointer<Utf8>, Pointer<Utf8>);
+typedef OwnerRpcFFI = Pointer<Utf8> Function(Pointer<Utf8>, Pointer<Utf8>);
+
+final OwnerRpc _ownerRpc = epicCashNative
+    .lookup<NativeFunction<OwnerRpcFFI>>("rust_owner_rpc")
+    .asFunction();
+
+/// Run an owner JSON-RPC 2.0 request against a wallet session, returning the
+/// JSON-RPC response. The `token` parameter is filled in on the Rust side.
+Future<String> ownerRpc(String wallet, String request) async {
+  Pointer<Utf8>? ptr;
+  final walletPtr = wallet.toNativeUtf8();
+  final requestPtr = request.toNativeUtf8();
+
+  try {
+    ptr = _ownerRpc(walletPtr, requestPtr);
+    return unwrapFfiResult(ptr.toDartString());
+  } finally {
+    malloc.free(walletPtr);
+    malloc.free(requestPtr);
+    if (ptr != null) {
+      _stringFree(ptr);
+    }
+  }
+}
+
+final OwnerRpc _foreignRpc = epicCashNative
+    .lookup<NativeFunction<OwnerRpcFFI>>("rust_foreign_rpc")
+    .asFunction();
+
+/// Run a foreign JSON-RPC 2.0 request against a wallet session, returning the
+/// JSON-RPC response.
+Future<String> foreignRpc(String wallet, String request) async {
+  Pointer<Utf8>? ptr;
+  final walletPtr = wallet.toNativeUtf8();
+  final requestPtr = request.toNativeUtf8();
+
+  try {
+    ptr = _foreignRpc(walletPtr, requestPtr);
+    return unwrapFfiResult(ptr.toDartString());
+  } finally {
+    malloc.free(walletPtr);
+    malloc.free(requestPtr);
+    if (ptr != null) {
+      _stringFree(ptr);
+    }
+  }
+}
+
+typedef StatusPoll = Pointer<Utf8> Function(Pointer<Utf8>);
+typedef StatusPollFFI = Pointer<Utf8> Function(Pointer<Utf8>);
+
+final StatusPoll _statusPoll = epicCashNative
+    .lookup<NativeFunction<StatusPollFFI>>("rust_status_poll")
+    .asFunction();
+
+/// Take the status messages queued for a wallet session, as a JSON array.
+String statusPoll(String wallet) {
+  Pointer<Utf8>? ptr;
+  final walletPtr = wallet.toNativeUtf8();
+
+  try {
+    ptr = _statusPoll(walletPtr);
+    return unwrapFfiResult(ptr.toDartString());
+  } finally {
+    malloc.free(walletPtr);
+    if (ptr != null) {
+      _stringFree(ptr);
+    }
+  }
+}
+
+final StatusPoll _cancelOperation = epicCashNative
+    .lookup<NativeFunction<StatusPollFFI>>("rust_cancel_operation")
+    .asFunction();
+
+/// Stop the operations in flight on a wallet session. Returns how many were
+/// asked to stop.
+String cancelOperation(String wallet) {
+  Pointer<Utf8>? ptr;
+  final walletPtr = wallet.toNativeUtf8();
+
+  try {
+    ptr = _cancelOperation(walletPtr);
+    return unwrapFfiResult(ptr.toDartString());
+  } finally {
+    malloc.free(walletPtr);
+    if (ptr != null) {
+      _stringFree(ptr);
+    }
+  }
+}
+
+final OpenWallet _setIdleTimeout = epicCashNative
+    .lookup<NativeFunction<OpenWalletFFI>>("rust_set_idle_timeout")
+    .asFunction();
+
+final StatusPoll _isSessionLocked = epicCashNative
+    .lookup<NativeFunction<StatusPollFFI>>("rust_is_session_locked")
+    .asFunction();
+
+final OpenWallet _unlockWallet = epicCashNative
+    .lookup<NativeFunction<OpenWalletFFI>>("rust_unlock_wallet")
+    .asFunction();
+
+/// Lock spending on a wallet session after [seconds] without spending; 0
+/// never locks it.
+void setIdleTimeout(String wallet, int seconds) {
+  _walletLabelCall(_setIdleTimeout, wallet, seconds.toString());
+}
+
+/// Whether a wallet session is locked after being idle.
+bool isSessionLocked(String wallet) {
+  Pointer<Utf8>? ptr;
+  final walletPtr = wallet.toNativeUtf8();
+
+  try {
+    ptr = _isSessionLocked(walletPtr);
+    return unwrapFfiResult(ptr.toDartString()) == 'true';
+  } finally {
+    malloc.free(walletPtr);
+    if (ptr != null) {
+      _stringFree(ptr);
+    }
+  }
+}
+
+/// Allow spending on a wallet session again once [password] is checked.
+void unlockWallet(String wallet, String password) {
+  _walletLabelCall(_unlockWallet, wallet, password);
+}
+
+/// Receives the level (1 = error up to 5 = trace) and a redacted log line.
+/// Called from Rust threads, so create it with `NativeCallable.listener`.
+typedef LogCallbackFFI = Void Function(Uint32, Pointer<Utf8>);
+
+typedef LoggerSetCallback = void Function(
+    Pointer<NativeFunction<LogCallbackFFI>>);
+typedef LoggerSetCallbackFFI = Void Function(
+    Pointer<NativeFunction<LogCallbackFFI>>);
+
+final StatusPoll _loggerInit = epicCashNative
+    .lookup<NativeFunction<StatusPollFFI>>("rust_logger_init")
+    .asFunction();
+
+final LoggerSetCallback _loggerSetCallback = epicCashNative
+    .lookup<NativeFunction<LoggerSetCallbackFFI>>("rust_logger_set_callback")
+    .asFunction();
+
+/// Configure native logging from a JSON config. Returns the log file path, or
+/// an empty string if no file is written.
+String loggerInit(String config) {
+  Pointer<Utf8>? ptr;
+  final configPtr = config.toNativeUtf8();
+
+  try {
+    ptr = _loggerInit(configPtr);
+    return unwrapFfiResult(ptr.toDartString());
+  } finally {
+    malloc.free(configPtr);
+    if (ptr != null) {
+      _stringFree(ptr);
+    }
+  }
+}
+
+/// Set the native log callback, or clear it with `nullptr`.
+void loggerSetCallback(Pointer<NativeFunction<LogCallbackFFI>> callback) {
+  _loggerSetCallback(callback);
+}
+
+// Wallet tasks: long-running operations on a Rust thread. Each start function
+// returns a task handle, or nullptr if its arguments were rejected.
+typedef TaskStart1 = Pointer<Void> Function(Pointer<Utf8>);
+typedef TaskStart1FFI = Pointer<Void> Function(Pointer<Utf8>);
+
+typedef TaskStart2 = Pointer<Void> Function(Pointer<Utf8>, Pointer<Utf8>);
+typedef TaskStart2FFI = Pointer<Void> Function(Pointer<Utf8>, Pointer<Utf8>);
+
+typedef TaskStart3 = Pointer<Void> Function(
+    Pointer<Utf8>, Pointer<Utf8>, Pointer<Utf8>);
+typedef TaskStart3FFI = Pointer<Void> Function(
+    Pointer<Utf8>, Pointer<Utf8>, Pointer<Utf8>);
+
+typedef TaskStart6 = Pointer<Void> Function(Pointer<Utf8>, Pointer<Utf8>,
+    Pointer<Utf8>, Pointer<Utf8>, Pointer<Utf8>, Pointer<Utf8>);
+typedef TaskStart6FFI = Pointer<Void> Function(Pointer<Utf8>, Pointer<Utf8>,
+    Pointer<Utf8>, Pointer<Utf8>, Pointer<Utf8>, Pointer<Utf8>);
+
+typedef TaskStart7 = Pointer<Void> Function(Pointer<Utf8>, Pointer<Utf8>,
+    Pointer<Utf8>, Pointer<Utf8>, Pointer<Utf8>, Pointer<Utf8>, Pointer<Utf8>);
+typedef TaskStart7FFI = Pointer<Void> Function(Pointer<Utf8>, Pointer<Utf8>,
+    Pointer<Utf8>, Pointer<Utf8>, Pointer<Utf8>, Pointer<Utf8>, Pointer<Utf8>);
+
+typedef TaskPoll = Pointer<Utf8> Function(Pointer<Void>);
+typedef TaskPollFFI = Pointer<Utf8> Function(Pointer<Void>);
+
+typedef TaskDestroy = void Function(Pointer<Void>);
+typedef TaskDestroyFFI = Void Function(Pointer<Void>);
+
+final TaskStart3 _scanOutputsTaskStart = epicCashNative
+    .lookup<NativeFunction<TaskStart3FFI>>("rust_scan_outputs_task_start")
+    .asFunction();
+
+final TaskStart1 _refreshTaskStart = epicCashNative
+    .lookup<NativeFunction<TaskStart1FFI>>("rust_refresh_task_start")
+    .asFunction();
+
+final TaskStart7 _sendTaskStart = epicCashNative
+    .lookup<NativeFunction<TaskStart7FFI>>("rust_send_task_start")
+    .asFunction();
+
+final TaskStart6 _sendHttpTaskStart = epicCashNative
+    .lookup<NativeFunction<TaskStart6FFI>>("rust_send_http_task_start")
+    .asFunction();
+
+final TaskStart2 _finalizeTaskStart = epicCashNative
+    .lookup<NativeFunction<TaskStart2FFI>>("rust_finalize_task_start")
+    .asFunction();
+
+final TaskPoll _taskPoll = epicCashNative
+    .lookup<NativeFunction<TaskPollFFI>>("rust_task_poll")
+    .asFunction();
+
+final TaskPoll _taskCancel = epicCashNative
+    .lookup<NativeFunction<TaskPollFFI>>("rust_task_cancel")
+    .asFunction();
+
+final TaskDestroy _taskDestroy = epicCashNative
+    .lookup<NativeFunction<TaskDestroyFFI>>("rust_task_destroy")
+    .asFunction();
+
+/// Call a task start function with [args], freeing them afterwards.
+Pointer<Void> _startTask(
+  List<String> args,
+  Pointer<Void> Function(List<Pointer<Utf8>>) start,
+) {
+  final ptrs = args.map((a) => a.toNativeUtf8()).toList();
+  try {
+    final handle = start(ptrs);
+    if (handle == nullptr) {
+      throw EpicFFIException('Invalid task arguments',
+          code: 'invalid_argument');
+    }
+    return handle;
+  } finally {
+    for (final p in ptrs) {
+      malloc.free(p);
+    }
+  }
+}
+
+/// Start scanning outputs. The task result is the last scanned height.
+Pointer<Void> scanOutputsTaskStart(
+  String wallet,
+  int startHeight,
+  int numberOfBlocks,
+) {
+  return _startTask(
+    [wallet, startHeight.toString(), numberOfBlocks.toString()],
+    (p) => _scanOutputsTaskStart(p[0], p[1], p[2]),
+  );
+}
+
+/// Start refreshing from the node. The task result is the transaction list.
+Pointer<Void> refreshTaskStart(String wallet) {
+  return _startTask([wallet], (p) => _refreshTaskStart(p[0]));
+}
+
+/// Start sending over epicbox. The task result matches [createTransaction].
+Pointer<Void> sendTaskStart(
+  String wallet,
+  int amount,
+  String address,
+  String epicboxConfig,
+  int minimumConfirmations,
+  String note,
+  int returnSlate,
+) {
+  return _startTask(
+    [
+      wallet,
+      amount.toString(),
+      address,
+      epicboxConfig,
+      minimumConfirmations.toString(),
+      note,
+      returnSlate.toString(),
+    ],
+    (p) => _sendTaskStart(p[0], p[1], p[2], p[3], p[4], p[5], p[6]),
+  );
+}
+
+/// Start sending over http. The task result matches [txHttpSend].
+Pointer<Void> sendHttpTaskStart(
+  String wallet,
+  int selectionStrategyIsAll,
+  int minimumConfirmations,
+  String message,
+  int amount,
+  String address,
+) {
+  return _startTask(
+    [
+      wallet,
+      selectionStrategyIsAll.toString(),
+      minimumConfirmations.toString(),
+      message,
+      amount.toString(),
+      address,
+    ],
+    (p) => _sendHttpTaskStart(p[0], p[1], p[2], p[3], p[4], p[5]),
+  );
+}
+
+/// Start finalizing a slate. The task result matches [txFinalize].
+Pointer<Void> finalizeTaskStart(String wallet, String slateJson) {
+  return _startTask(
+    [wallet, slateJson],
+    (p) => _finalizeTaskStart(p[0], p[1]),
+  );
+}
+
+/// Poll a task. Returns null while it is running, and its result once it has
+/// finished. Throws [EpicFFIException] if the operation failed.
+String? taskPoll(Pointer<Void> handle) {
+  Pointer<Utf8>? ptr;
+  try {
+    ptr = _taskPoll(handle);
+    if (ptr == nullptr) {
+      return null;
+    }
+    return unwrapFfiResult(ptr.toDartString());
+  } finally {
+    if (ptr != null && ptr != nullptr) {
+      _stringFree(ptr);
+    }
+  }
+}
+
+/// Ask a task to stop.
+bool taskCancel(Pointer<Void> handle) {
+  Pointer<Utf8>? ptr;
+  try {
+    ptr = _taskCancel(handle);
+    return unwrapFfiResult(ptr.toDartString()) == "true";
+  } catch (_) {
+    return false;
+  } finally {
+    if (ptr != null) {
+      _stringFree(ptr);
     }
   }
 }
+
+/// Free a task handle, cancelling the task if it is still running.
+void taskDestroy(Pointer<Void> handle) {
+  _taskDestroy(handle);
+}
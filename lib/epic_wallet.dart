@@ -30,6 +30,8 @@ class EpicWallet {
   String _config;
   String _epicboxConfig;
 
+  /// Opaque session id of the open wallet. The keychain mask that unlocks the
+  /// wallet stays in Rust and never crosses into Dart.
   String? _walletHandle;
   int? _listenerPointerAddress;
   bool _isClosing = false;
@@ -125,8 +127,6 @@ class EpicWallet {
         ),
       );
 
-      checkForError(initResult);
-
       final walletHandle = await worker.runTask<String>(
         EpicTask(
           func: EpicFuncName.openWallet,
@@ -137,8 +137,6 @@ class EpicWallet {
         ),
       );
 
-      checkForError(walletHandle);
-
       return EpicWallet._(
         walletHandle: walletHandle,
         worker: worker,
@@ -174,8 +172,6 @@ class EpicWallet {
         ),
       );
 
-      checkForError(walletHandle);
-
       return EpicWallet._(
         walletHandle: walletHandle,
         worker: worker,
@@ -215,8 +211,6 @@ class EpicWallet {
         ),
       );
 
-      checkForError(recoverResult);
-
       // Open wallet
       final walletHandle = await worker.runTask<String>(
         EpicTask(
@@ -228,8 +222,6 @@ class EpicWallet {
         ),
       );
 
-      checkForError(walletHandle);
-
       return EpicWallet._(
         walletHandle: walletHandle,
         worker: worker,
@@ -312,8 +304,6 @@ class EpicWallet {
       ),
     );
 
-    checkForError(balancesJson);
-
     return BalanceData.fromJson(balancesJson);
   }
 
@@ -349,8 +339,6 @@ class EpicWallet {
       ),
     );
 
-    checkForError(result);
-
     return int.parse(result);
   }
 
@@ -367,8 +355,6 @@ class EpicWallet {
       ),
     );
 
-    checkForError(txListJson);
-
     final txList = jsonDecode(txListJson) as List<dynamic>;
     return txList.map((tx) => Transaction.fromJson(tx)).toList();
   }
@@ -387,8 +373,6 @@ class EpicWallet {
       ),
     );
 
-    checkForError(address);
-
     return address;
   }
 
@@ -416,8 +400,6 @@ class EpicWallet {
       ),
     );
 
-    checkForError(result);
-
     return SlateResponse.fromResult(result);
   }
 
@@ -456,8 +438,6 @@ class EpicWallet {
       ),
     );
 
-    checkForError(result);
-
     return result;
   }
 
@@ -477,8 +457,6 @@ class EpicWallet {
       ),
     );
 
-    checkForError(balancesJson);
-
     final jsonBalances = parseJsonObject(balancesJson, 'transaction fees');
     final availableEpic = _parseAmount(
       jsonBalances['amount_currently_spendable'],
@@ -513,15 +491,22 @@ class EpicWallet {
       ),
     );
 
-    checkForError(feesJson);
-
     final feesArray = jsonDecode(feesJson);
     if (feesArray is! List || feesArray.isEmpty) {
       throw FormatException(
           'Expected array from getTransactionFees, got ${feesArray.runtimeType}');
     }
 
-    final fees = feesArray[0] as Map<String, dynamic>;
+    // Quotes come smallest-first; take the first one that covers the amount.
+    final quotes = feesArray.cast<Map<String, dynamic>>();
+    final fees = quotes.firstWhere(
+      (quote) => quote['not_enough_funds'] == null,
+      orElse: () => throw EpicFFIException(
+        'Not enough funds',
+        code: 'not_enough_funds',
+        details: quotes.first['not_enough_funds'] as Map<String, dynamic>?,
+      ),
+    );
 
     return (
       fee: parseInt(fees['fee'], 'fee'),
@@ -552,8 +537,6 @@ class EpicWallet {
       ),
     );
 
-    checkForError(result);
-
     final slate = SlateResponse.fromResult(result);
 
     return (slateId: slate.slateId, commitId: slate.commitId);
@@ -572,8 +555,6 @@ class EpicWallet {
       ),
     );
 
-    checkForError(result);
-
     return SlateResponse.fromReceiveResult(result);
   }
 
@@ -603,8 +584,6 @@ class EpicWallet {
       ),
     );
 
-    checkForError(result);
-
     return SlateResponse.fromReceiveResult(result);
   }
 
@@ -649,14 +628,22 @@ class EpicWallet {
       // Stop listener first
       await stopListener();
 
-      // Clear wallet handle
+      // Release the wallet on the Rust side; the handle is invalid afterwards
+      final handle = _walletHandle!;
       _walletHandle = null;
-
-      // Dispose worker (kills isolate)
-      _worker.dispose();
-
-      // Note: Epic FFI may not have a closeWallet function
-      // If it does, you would call it through the worker before disposal
+      try {
+        await _worker.runTask<String>(
+          EpicTask(
+            func: EpicFuncName.closeWallet,
+            args: {
+              "wallet": handle,
+            },
+          ),
+        );
+      } finally {
+        // Dispose worker (kills isolate)
+        _worker.dispose();
+      }
     } finally {
       _isClosing = false;
     }
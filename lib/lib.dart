@@ -1,12 +1,12 @@
 import 'dart:convert';
 import 'dart:ffi';
 
-import 'package:decimal/decimal.dart';
 import 'package:mutex/mutex.dart';
 
 import 'epic_cash.dart' as lib_epiccash;
 import 'epic_wallet.dart';
 import 'models/transaction.dart';
+import 'utils/epic_errors.dart';
 
 export 'epic_wallet.dart';
 
@@ -47,19 +47,69 @@ abstract class ListenerManager {
   static bool get hasActiveListeners => _pointers.isNotEmpty;
 }
 
+/// A wallet operation running on a Rust thread.
+///
+/// Await [result] for its output, and always call [dispose] when done.
+class EpicWalletTask {
+  EpicWalletTask._(this._handle);
+
+  Pointer<Void>? _handle;
+
+  /// Wait for the task to finish, polling every [interval].
+  ///
+  /// Throws [EpicFFIException] with code `cancelled` if the task was cancelled.
+  Future<String> result({
+    Duration interval = const Duration(milliseconds: 200),
+  }) async {
+    while (true) {
+      final handle = _handle;
+      if (handle == null) {
+        throw EpicFFIException('Task was disposed', code: 'invalid_handle');
+      }
+      final output = lib_epiccash.taskPoll(handle);
+      if (output != null) {
+        return output;
+      }
+      await Future<void>.delayed(interval);
+    }
+  }
+
+  /// Ask the task to stop. Returns false if it had already finished.
+  bool cancel() {
+    final handle = _handle;
+    return handle != null && lib_epiccash.taskCancel(handle);
+  }
+
+  /// Free the task, cancelling it if it is still running.
+  void dispose() {
+    final handle = _handle;
+    _handle = null;
+    if (handle != null) {
+      lib_epiccash.taskDestroy(handle);
+    }
+  }
+}
+
 ///
 /// Wrapped up calls to flutter_libepiccash.
 ///
 /// Should all be static calls (no state stored in this class)
 ///
 abstract class LibEpiccash {
+  /// Guards calls that are not tied to one open wallet.
   static final Mutex m = Mutex();
 
-  static void _checkForError(String result) {
-    if (result.startsWith("Error ")) {
-      throw Exception(result);
-    }
-  }
+  /// Per-wallet locks, so calls on one open wallet never wait on another.
+  static final Map<String, Mutex> _walletLocks = {};
+
+  static Mutex _lockFor(String wallet) =>
+      _walletLocks.putIfAbsent(wallet, () => Mutex());
+
+  /// Version of the native crate these bindings were written for.
+  static const String expectedNativeVersion = '0.1.0';
+
+  /// Result envelope version these bindings understand.
+  static const int expectedEnvelopeVersion = 1;
 
   // Private function wrapper for compute
   static Future<String> _initializeWalletWrapper(
@@ -94,8 +144,6 @@ abstract class LibEpiccash {
           name,
         );
 
-        _checkForError(result);
-
         return result;
       } catch (e) {
         throw ("Error creating new wallet : ${e.toString()}");
@@ -107,6 +155,51 @@ abstract class LibEpiccash {
     return EpicWallet.validateSendAddress(address: address);
   }
 
+  /// Configure native logging.
+  ///
+  /// [level] is one of `off`, `error`, `warn`, `info`, `debug` or `trace`.
+  /// With [walletDir], logs are written to rotating files in its `logs`
+  /// directory. Secrets are redacted before anything is written. Returns the
+  /// log file path, or null if no file is written.
+  static String? configureLogging({
+    String level = 'debug',
+    String? walletDir,
+    int? maxFileSize,
+    int? maxFiles,
+    bool stderr = false,
+  }) {
+    final path = lib_epiccash.loggerInit(jsonEncode({
+      'level': level,
+      if (walletDir != null) 'wallet_dir': walletDir,
+      if (maxFileSize != null) 'max_file_size': maxFileSize,
+      if (maxFiles != null) 'max_files': maxFiles,
+      'stderr': stderr,
+    }));
+    return path.isEmpty ? null : path;
+  }
+
+  /// Describe the loaded native library: `version`, `envelope_version`,
+  /// `foreign_api_version`, `supported_slate_versions`, `dependency_revisions`,
+  /// `features` and `transports`.
+  static Map<String, dynamic> getLibInfo() {
+    return jsonDecode(lib_epiccash.libInfo()) as Map<String, dynamic>;
+  }
+
+  /// Throw if the loaded native library is not the build these bindings were
+  /// written for.
+  static void checkNativeLibrary() {
+    final info = getLibInfo();
+    if (info['version'] != expectedNativeVersion ||
+        info['envelope_version'] != expectedEnvelopeVersion) {
+      throw EpicFFIException(
+        'Native library ${info['version']} (envelope v${info['envelope_version']}) '
+        'does not match the bindings ($expectedNativeVersion, envelope '
+        'v$expectedEnvelopeVersion)',
+        code: 'lib_mismatch',
+      );
+    }
+  }
+
   static Future<String> getMnemonic() async {
     return EpicWallet.getMnemonic();
   }
@@ -136,7 +229,7 @@ abstract class LibEpiccash {
     required int refreshFromNode,
     required int minimumConfirmations,
   }) async {
-    return await m.protect(() async {
+    return await _lockFor(wallet).protect(() async {
       try {
         final balances = await lib_epiccash.getWalletInfo(
           wallet,
@@ -144,10 +237,6 @@ abstract class LibEpiccash {
           minimumConfirmations,
         );
 
-        //If balances is valid json return, else return error
-        if (balances.toUpperCase().contains("ERROR")) {
-          throw Exception(balances);
-        }
         final jsonBalances = json.decode(balances);
         //Return balances as record
         final ({
@@ -188,7 +277,7 @@ abstract class LibEpiccash {
     required int numberOfBlocks,
   }) async {
     try {
-      final result = await m.protect(() async {
+      final result = await _lockFor(wallet).protect(() async {
         return await lib_epiccash.scanOutPuts(
           wallet,
           startHeight,
@@ -239,8 +328,9 @@ abstract class LibEpiccash {
     required int minimumConfirmations,
     required String note,
     bool returnSlate = false,
+    bool sweep = false,
   }) async {
-    return await m.protect(() async {
+    return await _lockFor(wallet).protect(() async {
       try {
         final String result = await lib_epiccash.createTransaction(
           wallet,
@@ -251,12 +341,9 @@ abstract class LibEpiccash {
           minimumConfirmations,
           note,
           returnSlate: returnSlate,
+          sweep: sweep,
         );
 
-        if (result.toUpperCase().contains("ERROR")) {
-          throw Exception("Error creating transaction ${result.toString()}");
-        }
-
         // Decode the nested JSON structure.
         // Structure: [inner_json_string, {"slate_msg": ""}]
         // Where inner_json_string is: [tx_entries_json, slate_json]
@@ -316,19 +403,13 @@ abstract class LibEpiccash {
     required String wallet,
     required int refreshFromNode,
   }) async {
-    return await m.protect(() async {
+    return await _lockFor(wallet).protect(() async {
       try {
         final result = await lib_epiccash.getTransactions(
           wallet,
           refreshFromNode,
         );
 
-        if (result.toUpperCase().contains("ERROR")) {
-          throw Exception(
-            "Error getting epic transactions ${result.toString()}",
-          );
-        }
-
         //Parse the returned data as an EpicTransaction
         final List<Transaction> finalResult = [];
         final jsonResult = json.decode(result) as List;
@@ -361,15 +442,13 @@ abstract class LibEpiccash {
     required String wallet,
     required String transactionId,
   }) async {
-    return await m.protect(() async {
+    return await _lockFor(wallet).protect(() async {
       try {
         final result = lib_epiccash.cancelTransaction(
           wallet,
           transactionId,
         );
 
-        _checkForError(result);
-
         return result;
       } catch (e) {
         throw ("Error canceling epic transaction : ${e.toString()}");
@@ -415,7 +494,7 @@ abstract class LibEpiccash {
     required int index,
     required String epicboxConfig,
   }) async {
-    return await m.protect(() async {
+    return await _lockFor(wallet).protect(() async {
       try {
         final result = lib_epiccash.getAddressInfo(
           wallet,
@@ -423,8 +502,6 @@ abstract class LibEpiccash {
           epicboxConfig,
         );
 
-        _checkForError(result);
-
         return result;
       } catch (e) {
         throw ("Error getting address info : ${e.toString()}");
@@ -432,6 +509,40 @@ abstract class LibEpiccash {
     });
   }
 
+  /// List the accounts of [wallet]. Each has a `label`, the BIP32 `path` of
+  /// its parent key, and `active`, set on the account in use.
+  static Future<List<Map<String, dynamic>>> getAccounts({
+    required String wallet,
+  }) async {
+    return await _lockFor(wallet).protect(() async {
+      final List<dynamic> accounts =
+          jsonDecode(lib_epiccash.accounts(wallet)) as List<dynamic>;
+      return accounts.cast<Map<String, dynamic>>();
+    });
+  }
+
+  /// Create an account named [label] and return its parent key path. The
+  /// active account does not change.
+  static Future<String> createAccount({
+    required String wallet,
+    required String label,
+  }) async {
+    return await _lockFor(wallet).protect(() async {
+      return lib_epiccash.createAccount(wallet, label);
+    });
+  }
+
+  /// Make [label] the account [wallet] sends from, receives to and reports
+  /// balances and transactions for.
+  static Future<void> setActiveAccount({
+    required String wallet,
+    required String label,
+  }) async {
+    await _lockFor(wallet).protect(() async {
+      lib_epiccash.setActiveAccount(wallet, label);
+    });
+  }
+
   static Future<String> _transactionFeesWrapper(
     ({
       String wallet,
@@ -457,56 +568,41 @@ abstract class LibEpiccash {
     required int minimumConfirmations,
     required int available,
   }) async {
-    return await m.protect(() async {
+    return await _lockFor(wallet).protect(() async {
       try {
-        String fees = await lib_epiccash.getTransactionFees(
+        var quote = _coveredFeeQuote(await lib_epiccash.getTransactionFees(
           wallet,
           amount,
           minimumConfirmations,
-        );
-
-        if (available == amount) {
-          if (fees.contains("Required")) {
-            final splits = fees.split(" ");
-            Decimal required = Decimal.zero;
-            Decimal available = Decimal.zero;
-            for (int i = 0; i < splits.length; i++) {
-              final word = splits[i];
-              if (word == "Required:") {
-                required = Decimal.parse(splits[i + 1].replaceAll(",", ""));
-              } else if (word == "Available:") {
-                available = Decimal.parse(splits[i + 1].replaceAll(",", ""));
-              }
-            }
-            final int largestSatoshiFee =
-                ((required - available) * Decimal.fromInt(100000000))
-                    .toBigInt()
-                    .toInt();
-            final amountSending = amount - largestSatoshiFee;
-            //Get fees for this new amount
-            fees = await lib_epiccash.getTransactionFees(
-              wallet,
-              amountSending,
-              minimumConfirmations,
+        ));
+        final shortfall = quote['not_enough_funds'];
+        if (shortfall != null) {
+          // When sending the whole balance, leave room for the fee and
+          // quote the largest amount that can actually be sent.
+          if (available != amount) {
+            throw EpicFFIException(
+              'Not enough funds',
+              code: 'not_enough_funds',
+              details: Map<String, dynamic>.from(shortfall),
             );
           }
+          final int largestSatoshiFee =
+              (shortfall['needed'] as int) - (shortfall['available'] as int);
+          quote = _coveredFeeQuote(await lib_epiccash.getTransactionFees(
+            wallet,
+            amount - largestSatoshiFee,
+            minimumConfirmations,
+          ));
         }
 
-        if (fees.toUpperCase().contains("ERROR")) {
-          //Check if the error is an
-          //Throw the returned error
-          throw Exception(fees);
-        }
-        final decodedFees = json.decode(fees);
-        final feeItem = decodedFees[0];
         final ({
           bool strategyUseAll,
           int total,
           int fee,
         }) feeRecord = (
-          strategyUseAll: feeItem['selection_strategy_is_use_all'],
-          total: feeItem['total'],
-          fee: feeItem['fee'],
+          strategyUseAll: quote['selection_strategy_is_use_all'],
+          total: quote['total'],
+          fee: quote['fee'],
         );
         return feeRecord;
       } catch (e) {
@@ -515,6 +611,35 @@ abstract class LibEpiccash {
     });
   }
 
+  /// Pick the first fee quote that covers the amount, or the first quote
+  /// with its shortfall when none does.
+  static Map<String, dynamic> _coveredFeeQuote(String fees) {
+    final quotes = (json.decode(fees) as List).cast<Map<String, dynamic>>();
+    return quotes.firstWhere(
+      (quote) => quote['not_enough_funds'] == null,
+      orElse: () => quotes.first,
+    );
+  }
+
+  /// Get the largest amount a sweep can send with [minimumConfirmations].
+  static Future<({int amount, int fee, int inputs, int spendable})>
+      getMaxSendable({
+    required String wallet,
+    required int minimumConfirmations,
+  }) async {
+    return await _lockFor(wallet).protect(() async {
+      final max = json.decode(
+        lib_epiccash.maxSendable(wallet, minimumConfirmations),
+      ) as Map<String, dynamic>;
+      return (
+        amount: max['amount'] as int,
+        fee: max['fee'] as int,
+        inputs: max['inputs'] as int,
+        spendable: max['spendable'] as int,
+      );
+    });
+  }
+
   static Future<String> _recoverWalletWrapper(
     ({
       String config,
@@ -561,6 +686,16 @@ abstract class LibEpiccash {
     );
   }
 
+  /// Delete the wallet in [config] with all of its data. It need not be
+  /// open and its password is not needed; a session that has it open is
+  /// closed, so [wallet] may be empty; a session in [wallet] must be of this
+  /// wallet or nothing is deleted. The seed file is overwritten before it
+  /// is removed. Files in the wallet directory that are not the wallet's are
+  /// kept, along with the directory.
+  ///
+  /// Returns JSON `{removed, kept, closed_sessions}`, where `removed` lists
+  /// every file removed and `kept` what was left, relative to the wallet
+  /// directory.
   static Future<String> deleteWallet({
     required String wallet,
     required String config,
@@ -571,8 +706,6 @@ abstract class LibEpiccash {
         config,
       );
 
-      _checkForError(result);
-
       return result;
     } catch (e) {
       throw ("Error deleting wallet : ${e.toString()}");
@@ -594,6 +727,11 @@ abstract class LibEpiccash {
   /// This function opens the wallet and returns the handle.
   /// The caller must then call EpicWalletIsolateManager.spawnForWallet()
   /// with the returned handle to create the wallet's dedicated isolate.
+  ///
+  /// Fails with `wallet_seed_doesnt_exist` if there is no wallet in the
+  /// configured directory, `wrong_password`, `unknown_account` if the config
+  /// names an account the wallet does not have, or `wallet_locked` if another
+  /// process has the wallet open. Without an `account` the default one is used.
   static Future<String> openWallet({
     required String config,
     required String password,
@@ -603,8 +741,6 @@ abstract class LibEpiccash {
         // Call directly (without compute(): on main thread)
         final result = lib_epiccash.openWallet(config, password);
 
-        _checkForError(result);
-
         return result;
       } catch (e) {
         throw ("Error opening flutter_libepiccash wallet: ${e.toString()}");
@@ -612,6 +748,169 @@ abstract class LibEpiccash {
     });
   }
 
+  /// Re-encrypt the seed of the wallet in [config] under [newPassword].
+  ///
+  /// Throws an [EpicFFIException] with code `wrong_password` if [oldPassword]
+  /// is wrong. Open handles to the wallet keep working.
+  static Future<void> changePassword({
+    required String config,
+    required String oldPassword,
+    required String newPassword,
+  }) async {
+    await m.protect(() async {
+      lib_epiccash.changePassword(config, oldPassword, newPassword);
+    });
+  }
+
+  /// Report whether the wallet in [config] exists, without its password, to
+  /// choose between the create, restore and unlock screens.
+  ///
+  /// Returns `seed_exists`, `chain`, `last_confirmed_height` and `name`. The
+  /// last two are null when unknown, such as for wallets created by older
+  /// versions or never closed since they were created. A closed wallet's
+  /// height is the one recorded when it was last closed. There is no database
+  /// schema version, as the wallet database does not record one.
+  static Future<Map<String, dynamic>> getWalletStatus({
+    required String config,
+  }) async {
+    return await m.protect(() async {
+      return jsonDecode(lib_epiccash.walletStatus(config))
+          as Map<String, dynamic>;
+    });
+  }
+
+  /// Reveal the recovery phrase of the wallet in [config] after checking
+  /// [password].
+  ///
+  /// Throws an [EpicFFIException] with code `wrong_password` if [password] is
+  /// wrong. Show the phrase and drop it; do not store or log it.
+  static Future<String> revealMnemonic({
+    required String config,
+    required String password,
+  }) async {
+    return await m.protect(() async {
+      return lib_epiccash.revealMnemonic(config, password);
+    });
+  }
+
+  /// Back up the wallet in [config] to an archive at [archivePath], encrypted
+  /// with [password].
+  ///
+  /// The archive keeps what the mnemonic cannot restore: transaction notes,
+  /// the transaction log, payment proofs and account names. The wallet must
+  /// be closed; an open one fails with `lifecycle` or `wallet_locked`.
+  static Future<Map<String, dynamic>> backupWallet({
+    required String config,
+    required String password,
+    required String archivePath,
+  }) async {
+    return await m.protect(() async {
+      return jsonDecode(
+        lib_epiccash.backupWallet(config, password, archivePath),
+      ) as Map<String, dynamic>;
+    });
+  }
+
+  /// Restore the backup at [archivePath] into [walletDir], which must not
+  /// exist yet or be empty. Open it with a config pointing at [walletDir] and
+  /// the wallet's own password.
+  static Future<Map<String, dynamic>> restoreWalletBackup({
+    required String archivePath,
+    required String password,
+    required String walletDir,
+  }) async {
+    return await m.protect(() async {
+      return jsonDecode(
+        lib_epiccash.restoreWalletBackup(archivePath, password, walletDir),
+      ) as Map<String, dynamic>;
+    });
+  }
+
+  /// List the wallets kept under the data directory [root], sorted by name.
+  ///
+  /// Each entry has `name`, `chain`, `created_at` (Unix seconds) and
+  /// `wallet_dir`; `chain` and `created_at` are null for wallets created by
+  /// older versions.
+  static Future<List<Map<String, dynamic>>> listWallets({
+    required String root,
+  }) async {
+    return await m.protect(() async {
+      final List<dynamic> wallets =
+          jsonDecode(lib_epiccash.managerListWallets(root)) as List<dynamic>;
+      return wallets.cast<Map<String, dynamic>>();
+    });
+  }
+
+  /// Create a wallet named [name] under [root] from [mnemonic]. [config]
+  /// supplies the node and the chain; its `wallet_dir` is ignored.
+  ///
+  /// Throws an [EpicFFIException] with code `wallet_seed_exists` if the name
+  /// is taken.
+  static Future<Map<String, dynamic>> createManagedWallet({
+    required String root,
+    required String config,
+    required String name,
+    required String mnemonic,
+    required String password,
+  }) async {
+    return await m.protect(() async {
+      return jsonDecode(
+        lib_epiccash.managerCreateWallet(root, config, name, mnemonic, password),
+      ) as Map<String, dynamic>;
+    });
+  }
+
+  /// Open the wallet named [name] under [root] and return its handle, as
+  /// [openWallet] does. The wallet stays on the chain it was created on,
+  /// whatever the chain of [config].
+  static Future<String> openManagedWallet({
+    required String root,
+    required String config,
+    required String name,
+    required String password,
+  }) async {
+    return await m.protect(() async {
+      return lib_epiccash.managerOpenWallet(root, config, name, password);
+    });
+  }
+
+  /// Rename the closed wallet [name] under [root] to [newName].
+  static Future<Map<String, dynamic>> renameManagedWallet({
+    required String root,
+    required String name,
+    required String newName,
+  }) async {
+    return await m.protect(() async {
+      return jsonDecode(
+        lib_epiccash.managerRenameWallet(root, name, newName),
+      ) as Map<String, dynamic>;
+    });
+  }
+
+  /// Delete the wallet [name] under [root] with all of its data, as
+  /// [deleteWallet] does. Returns `removed`, `kept` and `closed_sessions`.
+  static Future<Map<String, dynamic>> deleteManagedWallet({
+    required String root,
+    required String name,
+  }) async {
+    return await m.protect(() async {
+      return jsonDecode(lib_epiccash.managerDeleteWallet(root, name))
+          as Map<String, dynamic>;
+    });
+  }
+
+  /// Close a wallet opened with [openWallet]. The handle is invalid afterwards.
+  static Future<void> closeWallet({required String wallet}) async {
+    await _lockFor(wallet).protect(() async {
+      try {
+        lib_epiccash.closeWallet(wallet);
+      } catch (e) {
+        throw ("Error closing flutter_libepiccash wallet: ${e.toString()}");
+      }
+    });
+    _walletLocks.remove(wallet);
+  }
+
   static Future<String> _txHttpSendWrapper(
     ({
       String wallet,
@@ -639,6 +938,7 @@ abstract class LibEpiccash {
     required String message,
     required int amount,
     required String address,
+    bool sweep = false,
   }) async {
     try {
       final result = await lib_epiccash.txHttpSend(
@@ -648,10 +948,8 @@ abstract class LibEpiccash {
         message,
         amount,
         address,
+        sweep: sweep,
       );
-      if (result.toUpperCase().contains("ERROR")) {
-        throw Exception("Error creating transaction ${result.toString()}");
-      }
 
       //Decode sent tx and return Slate Id
       final slate0 = jsonDecode(result);
@@ -686,6 +984,10 @@ abstract class LibEpiccash {
 
     try {
       final pointer = lib_epiccash.epicboxListenerStart(wallet, epicboxConfig);
+      if (pointer == nullptr) {
+        throw EpicFFIException('Invalid listener arguments',
+            code: 'invalid_argument');
+      }
       ListenerManager.setPointer(walletId, pointer);
     } catch (e) {
       // Ensure pointer is removed if start fails
@@ -767,17 +1069,13 @@ abstract class LibEpiccash {
     required String wallet,
     required String slateJson,
   }) async {
-    return await m.protect(() async {
+    return await _lockFor(wallet).protect(() async {
       try {
         final String result = lib_epiccash.txReceive(
           wallet,
           slateJson,
         );
 
-        if (result.toUpperCase().contains("ERROR")) {
-          throw Exception("Error receiving transaction ${result.toString()}");
-        }
-
         // Decode the received tx and return Slate Id and CommitId.
         final slate0 = jsonDecode(result);
         final slateResponse = slate0[0] as String;
@@ -825,17 +1123,13 @@ abstract class LibEpiccash {
     required String wallet,
     required String slateJson,
   }) async {
-    return await m.protect(() async {
+    return await _lockFor(wallet).protect(() async {
       try {
         final String result = lib_epiccash.txFinalize(
           wallet,
           slateJson,
         );
 
-        if (result.toUpperCase().contains("ERROR")) {
-          throw Exception("Error finalizing transaction ${result.toString()}");
-        }
-
         // Decode the finalized tx and return Slate Id and CommitId.
         final slate0 = jsonDecode(result);
         final slateResponse = slate0[0] as String;
@@ -859,4 +1153,188 @@ abstract class LibEpiccash {
       }
     });
   }
+
+  /// Call an owner API [method] on [wallet] over JSON-RPC.
+  ///
+  /// [params] are the method's named parameters, without `token`. Returns the
+  /// method's `Ok` value, and throws if the call returned an error.
+  static Future<dynamic> ownerRpc({
+    required String wallet,
+    required String method,
+    Map<String, dynamic> params = const {},
+  }) async {
+    return await _lockFor(wallet).protect(() async {
+      final request = _rpcRequest(method, params);
+      return _rpcResult(
+          'Owner', method, await lib_epiccash.ownerRpc(wallet, request));
+    });
+  }
+
+  /// Call a foreign API [method] on [wallet] over JSON-RPC.
+  ///
+  /// [params] are the method's parameters, as sent to the epic-wallet foreign
+  /// HTTP API. Returns the method's `Ok` value, and throws if the call
+  /// returned an error.
+  static Future<dynamic> foreignRpc({
+    required String wallet,
+    required String method,
+    Object params = const [],
+  }) async {
+    return await _lockFor(wallet).protect(() async {
+      final request = _rpcRequest(method, params);
+      return _rpcResult(
+          'Foreign', method, await lib_epiccash.foreignRpc(wallet, request));
+    });
+  }
+
+  static String _rpcRequest(String method, Object params) {
+    return jsonEncode({
+      'jsonrpc': '2.0',
+      'id': 1,
+      'method': method,
+      'params': params,
+    });
+  }
+
+  static dynamic _rpcResult(String api, String method, String reply) {
+    final response = jsonDecode(reply) as Map<String, dynamic>;
+    if (response.containsKey('error')) {
+      throw EpicFFIException(
+        '$api RPC $method failed: ${response['error']['message']}',
+        code: 'rpc_error',
+      );
+    }
+    final result = response['result'] as Map<String, dynamic>;
+    if (result.containsKey('Err')) {
+      throw EpicFFIException(
+        '$api RPC $method failed: ${jsonEncode(result['Err'])}',
+        code: 'wallet',
+      );
+    }
+    return result['Ok'];
+  }
+
+  /// Take the progress and status messages reported for [wallet] since the
+  /// last call, oldest first.
+  ///
+  /// Each message has a `kind` (`scanning`, `scanning_complete`,
+  /// `updating_outputs`, `updating_transactions`, `full_scan_warning` or
+  /// `update_warning`) and a `message`; `scanning` messages also carry a
+  /// `percent`. This does not take the wallet mutex, so it can be polled while
+  /// a scan or refresh is running.
+  static List<Map<String, dynamic>> pollStatusMessages({
+    required String wallet,
+  }) {
+    final List<dynamic> messages =
+        jsonDecode(lib_epiccash.statusPoll(wallet)) as List<dynamic>;
+    return messages.cast<Map<String, dynamic>>();
+  }
+
+  /// Stop the scans, refreshes and sends running on [wallet]. The stopped
+  /// calls throw an [EpicFFIException] with code `cancelled`; a stopped scan
+  /// keeps the progress saved up to its last batch and reports it as
+  /// `last_scanned_height` in the exception details. Returns how many
+  /// operations were asked to stop.
+  ///
+  /// This does not take the wallet mutex, so it can stop a call holding it.
+  static int cancelOperation({
+    required String wallet,
+  }) {
+    return int.parse(lib_epiccash.cancelOperation(wallet));
+  }
+
+  /// Lock spending on [wallet] after [timeout] without a send or finalize.
+  /// A locked wallet still returns balances and transactions, but sends and
+  /// finalizes throw an [EpicFFIException] with code `session_locked` until
+  /// [unlockWallet] is called. Failed sends and background refreshes do not
+  /// count as activity. A null [timeout], the default, never locks it.
+  ///
+  /// Like [cancelOperation], the lock calls do not take the wallet mutex.
+  static void setIdleTimeout({
+    required String wallet,
+    Duration? timeout,
+  }) {
+    lib_epiccash.setIdleTimeout(wallet, timeout?.inSeconds ?? 0);
+  }
+
+  /// Whether [wallet] is locked after being idle.
+  static bool isSessionLocked({
+    required String wallet,
+  }) {
+    return lib_epiccash.isSessionLocked(wallet);
+  }
+
+  /// Allow spending on [wallet] again. Throws an [EpicFFIException] with code
+  /// `wrong_password` if [password] is wrong.
+  static void unlockWallet({
+    required String wallet,
+    required String password,
+  }) {
+    lib_epiccash.unlockWallet(wallet, password);
+  }
+
+  /// Start scanning [numberOfBlocks] blocks from [startHeight] as a task.
+  /// The result is the last scanned height.
+  static EpicWalletTask startScanOutputsTask({
+    required String wallet,
+    required int startHeight,
+    required int numberOfBlocks,
+  }) {
+    return EpicWalletTask._(lib_epiccash.scanOutputsTaskStart(
+        wallet, startHeight, numberOfBlocks));
+  }
+
+  /// Start refreshing outputs and transactions from the node as a task.
+  static EpicWalletTask startRefreshTask({required String wallet}) {
+    return EpicWalletTask._(lib_epiccash.refreshTaskStart(wallet));
+  }
+
+  /// Start creating and sending a transaction over epicbox as a task.
+  static EpicWalletTask startSendTask({
+    required String wallet,
+    required int amount,
+    required String address,
+    required String epicboxConfig,
+    required int minimumConfirmations,
+    required String note,
+    bool returnSlate = false,
+  }) {
+    return EpicWalletTask._(lib_epiccash.sendTaskStart(
+      wallet,
+      amount,
+      address,
+      epicboxConfig,
+      minimumConfirmations,
+      note,
+      returnSlate ? 1 : 0,
+    ));
+  }
+
+  /// Start creating and sending a transaction over http as a task.
+  static EpicWalletTask startSendHttpTask({
+    required String wallet,
+    required int selectionStrategyIsAll,
+    required int minimumConfirmations,
+    required String message,
+    required int amount,
+    required String address,
+  }) {
+    return EpicWalletTask._(lib_epiccash.sendHttpTaskStart(
+      wallet,
+      selectionStrategyIsAll,
+      minimumConfirmations,
+      message,
+      amount,
+      address,
+    ));
+  }
+
+  /// Start finalizing [slateJson] and posting the transaction as a task.
+  static EpicWalletTask startFinalizeTask({
+    required String wallet,
+    required String slateJson,
+  }) {
+    return EpicWalletTask._(
+        lib_epiccash.finalizeTaskStart(wallet, slateJson));
+  }
 }
@@ -3,6 +3,7 @@ enum EpicFuncName {
   initWallet,
   recoverWallet,
   openWallet,
+  closeWallet,
   getWalletInfo,
   scanOutputs,
   createTransaction,
@@ -66,10 +67,18 @@ class EpicTaskResponse {
 
   final String? error;
 
+  /// Error kind from the FFI result envelope, if the task failed with one.
+  final String? errorCode;
+
+  /// Structured error details from the FFI result envelope.
+  final Map<String, dynamic>? errorDetails;
+
   const EpicTaskResponse({
     required this.id,
     this.result,
     this.error,
+    this.errorCode,
+    this.errorDetails,
   });
 
   bool get isSuccess => error == null;
@@ -79,6 +88,8 @@ class EpicTaskResponse {
       'id': id,
       'result': result,
       'error': error,
+      'errorCode': errorCode,
+      'errorDetails': errorDetails,
     };
   }
 
@@ -87,6 +98,8 @@ class EpicTaskResponse {
       id: map['id'] as String,
       result: map['result'],
       error: map['error'] as String?,
+      errorCode: map['errorCode'] as String?,
+      errorDetails: map['errorDetails'] as Map<String, dynamic>?,
     );
   }
 }
@@ -4,6 +4,7 @@ import 'dart:isolate';
 import 'package:uuid/uuid.dart';
 import 'epic_task.dart';
 import '../epic_cash.dart' as epic_ffi;
+import '../utils/epic_errors.dart';
 
 class EpicWorker {
   EpicWorker._({
@@ -89,6 +90,14 @@ class EpicWorker {
 
     if (response.isSuccess) {
       completer.complete(response.result);
+    } else if (response.errorCode != null) {
+      completer.completeError(
+        EpicFFIException(
+          response.error!,
+          code: response.errorCode,
+          details: response.errorDetails,
+        ),
+      );
     } else {
       completer.completeError(
         Exception('Epic worker error: ${response.error}'),
@@ -144,6 +153,13 @@ class EpicWorker {
           id: id,
           result: result,
         ).toMap());
+      } on EpicFFIException catch (e) {
+        sendToMain.send(EpicTaskResponse(
+          id: id,
+          error: e.message,
+          errorCode: e.code,
+          errorDetails: e.details,
+        ).toMap());
       } catch (e) {
         sendToMain.send(EpicTaskResponse(
           id: id,
@@ -183,6 +199,11 @@ class EpicWorker {
           args['password'] as String,
         );
 
+      case EpicFuncName.closeWallet:
+        return epic_ffi.closeWallet(
+          args['wallet'] as String,
+        );
+
       case EpicFuncName.getWalletInfo:
         return await epic_ffi.getWalletInfo(
           args['wallet'] as String,
@@ -1,3 +1,5 @@
+import 'dart:convert';
+
 class EpicWalletException implements Exception {
   final String message;
   final String? code;
@@ -25,14 +27,21 @@ class EpicWalletClosedException extends EpicWalletException {
 }
 
 class EpicFFIException extends EpicWalletException {
-  EpicFFIException(String message, {String? code, StackTrace? stackTrace})
+  /// Structured error details, if any (e.g. available/needed for
+  /// `not_enough_funds`).
+  final Map<String, dynamic>? details;
+
+  EpicFFIException(String message,
+      {String? code, this.details, StackTrace? stackTrace})
       : super(message, code: code, stackTrace: stackTrace);
 
-  factory EpicFFIException.fromResult(String result) {
-    if (result.startsWith('Error ')) {
-      return EpicFFIException(result.substring(6), code: 'FFI_ERROR');
-    }
-    return EpicFFIException(result, code: 'UNKNOWN_ERROR');
+  /// Build from the `error` object of a failed FFI result envelope.
+  factory EpicFFIException.fromEnvelopeError(Map<String, dynamic> error) {
+    return EpicFFIException(
+      error['message'] as String? ?? '',
+      code: error['kind'] as String? ?? 'UNKNOWN_ERROR',
+      details: error['details'] as Map<String, dynamic>?,
+    );
   }
 }
 
@@ -57,11 +66,31 @@ class EpicTransactionException extends EpicWalletException {
       : super(message, code: code ?? 'TX_ERROR', stackTrace: stackTrace);
 }
 
-void checkForError(String result) {
-  if (result.startsWith('Error ')) {
-    throw EpicFFIException.fromResult(result);
+/// Unwrap a result envelope returned by the Rust library.
+///
+/// Every FFI export returns `{"v":1,"ok":true,"data":...}` on success and
+/// `{"v":1,"ok":false,"error":{"code":...,"kind":...,"message":...}}` on
+/// failure. Returns the data as a string (JSON-encoded unless it already is
+/// a string), or throws an [EpicFFIException] carrying the error kind.
+String unwrapFfiResult(String raw) {
+  final dynamic envelope;
+  try {
+    envelope = jsonDecode(raw);
+  } catch (e) {
+    throw EpicParseException('Invalid FFI result: $e', rawData: raw);
   }
-  if (result.toUpperCase().contains('ERROR')) {
-    throw EpicFFIException(result, code: 'FFI_ERROR');
+  if (envelope is! Map<String, dynamic> || envelope['ok'] is! bool) {
+    throw EpicParseException('Invalid FFI result envelope', rawData: raw);
   }
+
+  if (envelope['ok'] == true) {
+    final data = envelope['data'];
+    if (data == null) return '';
+    if (data is String) return data;
+    return jsonEncode(data);
+  }
+
+  throw EpicFFIException.fromEnvelopeError(
+    envelope['error'] as Map<String, dynamic>,
+  );
 }
@@ -1,9 +1,60 @@
 use std::env;
 use std::path::PathBuf;
+use std::process::Command;
 use glob::glob;
+use serde_json::Value;
 
 fn main() {
     android_on_linux_check();
+    export_build_info();
+}
+
+/// Pass the pinned git revisions of the epic dependencies and the enabled
+/// cargo features to the crate, for `rust_lib_info`.
+///
+/// The revisions come from the dependency graph cargo resolved for this build,
+/// as `Cargo.lock` is not committed and may belong to an enclosing workspace.
+fn export_build_info() {
+    println!("cargo:rerun-if-changed=Cargo.toml");
+    println!("cargo:rerun-if-changed=Cargo.lock");
+
+    let manifest_path = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join("Cargo.toml");
+    let output = Command::new(env::var("CARGO").unwrap())
+        .args(["metadata", "--format-version", "1", "--filter-platform"])
+        .arg(env::var("TARGET").unwrap())
+        .arg("--manifest-path")
+        .arg(&manifest_path)
+        .output()
+        .expect("failed to run cargo metadata");
+    if !output.status.success() {
+        panic!("cargo metadata failed: {}", String::from_utf8_lossy(&output.stderr));
+    }
+    let metadata: Value = serde_json::from_slice(&output.stdout).expect("cargo metadata returned invalid JSON");
+
+    let mut revisions = Vec::new();
+    for package in metadata["packages"].as_array().into_iter().flatten() {
+        let (name, source) = match (package["name"].as_str(), package["source"].as_str()) {
+            (Some(name), Some(source)) => (name, source),
+            _ => continue,
+        };
+        if name.starts_with("epic_") && source.starts_with("git+") {
+            if let Some((_, revision)) = source.rsplit_once('#') {
+                revisions.push(format!("{}={}", name, revision));
+            }
+        }
+    }
+    if !revisions.iter().any(|revision| revision.starts_with("epic_core=")) {
+        panic!("cargo metadata did not resolve the epic_core git revision");
+    }
+    revisions.sort();
+    revisions.dedup();
+    println!("cargo:rustc-env=EPIC_DEPENDENCY_REVISIONS={}", revisions.join(","));
+
+    let mut features: Vec<String> = env::vars()
+        .filter_map(|(key, _)| key.strip_prefix("CARGO_FEATURE_").map(|f| f.to_lowercase().replace('_', "-")))
+        .collect();
+    features.sort();
+    println!("cargo:rustc-env=EPIC_CASH_FEATURES={}", features.join(","));
 }
 
 fn android_on_linux_check() {
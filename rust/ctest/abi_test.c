@@ -0,0 +1,129 @@
+/*
+ * Exercises the typed C ABI through the checked-in header.
+ *
+ * Build and run with scripts/linux/test_c_abi.sh. Everything here works
+ * offline: a wallet is created in a temporary directory but never synced.
+ */
+#define _POSIX_C_SOURCE 200809L
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#include "epic_cash_wallet.h"
+
+static int failures = 0;
+
+#define CHECK(cond)                                                   \
+  do {                                                                \
+    if (!(cond)) {                                                    \
+      fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #cond); \
+      failures++;                                                     \
+    }                                                                 \
+  } while (0)
+
+/* Returns the error kind of the last typed call, or "" if there is none. */
+static const char *last_error_kind(char *buf, size_t len) {
+  char *err = epic_last_error();
+  buf[0] = '\0';
+  if (err != NULL) {
+    const char *kind = strstr(err, "\"kind\":\"");
+    if (kind != NULL) {
+      kind += strlen("\"kind\":\"");
+      size_t n = strcspn(kind, "\"");
+      if (n >= len) {
+        n = len - 1;
+      }
+      memcpy(buf, kind, n);
+      buf[n] = '\0';
+    }
+    rust_string_free(err);
+  }
+  return buf;
+}
+
+/* Copies the string data of a successful envelope into buf. */
+static int envelope_data(const char *envelope, char *buf, size_t len) {
+  const char *data = strstr(envelope, "\"data\":\"");
+  if (strstr(envelope, "\"ok\":true") == NULL || data == NULL) {
+    return 0;
+  }
+  data += strlen("\"data\":\"");
+  size_t n = strcspn(data, "\"");
+  if (n >= len) {
+    return 0;
+  }
+  memcpy(buf, data, n);
+  buf[n] = '\0';
+  return 1;
+}
+
+int main(void) {
+  char kind[64];
+
+  /* Plain values come back through out-pointers. */
+  bool valid = true;
+  CHECK(epic_validate_address("invalid_address", &valid) == EPIC_OK);
+  CHECK(!valid);
+  CHECK(epic_last_error() == NULL);
+
+  /* Errors come back as a status code, with details from epic_last_error. */
+  CHECK(epic_close_wallet(4294967297ULL) != EPIC_OK);
+  CHECK(strcmp(last_error_kind(kind, sizeof kind), "invalid_handle") == 0);
+
+  CHECK(epic_validate_address(NULL, &valid) != EPIC_OK);
+  CHECK(strcmp(last_error_kind(kind, sizeof kind), "invalid_argument") == 0);
+
+  char *json = NULL;
+  CHECK(epic_txs_get(0, false, &json) != EPIC_OK);
+  CHECK(json == NULL);
+
+  /* A full session: create, open, query and close a wallet. */
+  char dir[] = "/tmp/epic_abi_test_XXXXXX";
+  CHECK(mkdtemp(dir) != NULL);
+  char config[512];
+  snprintf(config, sizeof config,
+           "{\"wallet_dir\":\"%s\",\"check_node_api_http_addr\":\"http://127.0.0.1:1\","
+           "\"chain\":\"floonet\",\"account\":\"default\",\"api_listen_port\":3415,"
+           "\"api_listen_interface\":\"127.0.0.1\"}",
+           dir);
+
+  char mnemonic[512];
+  const char *mnemonic_envelope = get_mnemonic();
+  CHECK(envelope_data(mnemonic_envelope, mnemonic, sizeof mnemonic));
+  rust_string_free((char *)mnemonic_envelope);
+
+  const char *created = wallet_init(config, mnemonic, "c_abi_password", "c_abi_wallet");
+  CHECK(strstr(created, "\"ok\":true") != NULL);
+  rust_string_free((char *)created);
+
+  SessionId session = 0;
+  CHECK(epic_open_wallet(config, "c_abi_password", &session) == EPIC_OK);
+  CHECK(session != 0);
+
+  char *address = NULL;
+  const char *epicbox_config =
+      "{\"epicbox_domain\":\"epicbox.epic.tech\",\"epicbox_port\":443,"
+      "\"epicbox_protocol_unsecure\":false,\"epicbox_address_index\":0}";
+  CHECK(epic_get_wallet_address(session, 0, epicbox_config, &address) == EPIC_OK);
+  CHECK(address != NULL && strstr(address, "@epicbox.epic.tech") != NULL);
+  rust_string_free(address);
+
+  CHECK(epic_txs_get(session, false, &json) == EPIC_OK);
+  CHECK(json != NULL && json[0] == '[');
+  rust_string_free(json);
+
+  CHECK(epic_close_wallet(session) == EPIC_OK);
+  CHECK(epic_close_wallet(session) != EPIC_OK);
+
+  char handle[32];
+  snprintf(handle, sizeof handle, "%llu", (unsigned long long)session);
+  rust_string_free((char *)rust_delete_wallet(handle, config));
+
+  if (failures != 0) {
+    fprintf(stderr, "%d check(s) failed\n", failures);
+    return 1;
+  }
+  printf("C ABI test passed\n");
+  return 0;
+}
@@ -0,0 +1,818 @@
+/*
+ * This file is autogenerated by cbindgen. Do not modify this file manually.
+ * To regenerate this file, run:
+ * ```
+ * cbindgen --config cbindgen.toml --crate epic-cash-wallet --output include/epic_cash_wallet.h
+ * ```
+ * from the rust directory.
+ *
+ * For more information, see the project documentation or contact the project maintainer.
+ */
+
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+
+/**
+ * Version of the result envelope layout returned by every FFI export.
+ *
+ * Bump this when the shape of [`Envelope`] changes in a way callers can observe.
+ */
+#define ENVELOPE_VERSION 1
+
+/**
+ * Status returned by every typed export on success.
+ *
+ * Any other status is the numeric code of the error, as listed in
+ * `crate::envelope::ErrorCode`; call `epic_last_error` for the details.
+ */
+#define EPIC_OK 0
+
+/**
+ * Amount that makes a send sweep the whole spendable balance less the fee.
+ */
+#define EPIC_SEND_MAX 18446744073709551615ull
+
+/**
+ * Longest wallet name the manager accepts, in bytes.
+ */
+#define MAX_WALLET_NAME_LEN 64
+
+/**
+ * Number of blocks scanned between checks of the stop flag.
+ */
+#define SCAN_BATCH_BLOCKS 1000
+
+/**
+ * Called with the level (1 = error up to 5 = trace) and the redacted, formatted
+ * line for every log record. It may be called from any thread.
+ */
+typedef void (*LogCallback)(uint32_t level, const char *line);
+
+/**
+ * Opaque id of an open wallet session, as held by FFI callers.
+ *
+ * This is the only thing callers ever see of an open wallet; the wallet and
+ * its keychain mask stay on this side of the boundary.
+ */
+typedef uint64_t SessionId;
+
+/**
+ * Cancel and destroy a listener via FFI.
+ * This cancels the listener task and frees the associated handle memory.
+ * The envelope data is `true` if the listener was cancelled, `false` otherwise.
+ */
+const char *_listener_cancel(void *handler);
+
+/**
+ * Check if the listener is still running via FFI.
+ * The envelope data is `true` if the listener is alive (task not completed), `false` if it has stopped.
+ * Returns `false` if the handler is null.
+ */
+const char *_listener_is_running(void *handler);
+
+/**
+ * List the session's accounts, writing them as a JSON array to `out_json`.
+ */
+uint32_t epic_accounts(SessionId session, char **out_json);
+
+/**
+ * Back up the closed wallet in `config` to an archive encrypted with
+ * `password`, writing `{files, bytes}` as JSON to `out_json`.
+ */
+uint32_t epic_backup_wallet(const char *config,
+                            const char *password,
+                            const char *archive_path,
+                            char **out_json);
+
+/**
+ * Stop the operations in flight on the session, writing how many were asked
+ * to stop to `out_count`.
+ */
+uint32_t epic_cancel_operation(SessionId session, uint64_t *out_count);
+
+/**
+ * Re-encrypt the seed of the wallet in `config` under a new password.
+ */
+uint32_t epic_change_password(const char *config,
+                              const char *old_password,
+                              const char *new_password);
+
+/**
+ * Close a wallet session.
+ */
+uint32_t epic_close_wallet(SessionId session);
+
+/**
+ * Create a named account, writing its parent key path to `out_path`.
+ */
+uint32_t epic_create_account(SessionId session, const char *label, char **out_path);
+
+/**
+ * Create a transaction via epicbox, writing the slate as JSON to `out_json`.
+ *
+ * An `amount` of `EPIC_SEND_MAX` sweeps the whole spendable balance.
+ */
+uint32_t epic_create_tx(SessionId session,
+                        uint64_t amount,
+                        const char *to_address,
+                        const char *epicbox_config,
+                        uint64_t min_confirmations,
+                        const char *note,
+                        bool return_slate,
+                        char **out_json);
+
+/**
+ * Delete the wallet in `config` without its password, writing the removed
+ * and kept files and the number of closed sessions as JSON to `out_json`. A session
+ * that has the wallet open is closed first.
+ */
+uint32_t epic_delete_wallet(const char *config, char **out_json);
+
+/**
+ * Run a foreign JSON-RPC request against a session, writing the JSON-RPC
+ * response to `out_json`.
+ */
+uint32_t epic_foreign_rpc(SessionId session, const char *request, char **out_json);
+
+/**
+ * Get the chain height from the node in `config`, writing it to `out_height`.
+ */
+uint32_t epic_get_chain_height(const char *config, uint64_t *out_height);
+
+/**
+ * Get a fee quote for every coin selection strategy for `amount`, writing them
+ * as a JSON array to `out_json`.
+ */
+uint32_t epic_get_tx_fees(SessionId session,
+                          uint64_t amount,
+                          uint64_t min_confirmations,
+                          char **out_json);
+
+/**
+ * Get the epicbox address at `index`, writing it to `out_address`.
+ */
+uint32_t epic_get_wallet_address(SessionId session,
+                                 uint32_t index,
+                                 const char *epicbox_config,
+                                 char **out_address);
+
+/**
+ * Write whether the session is locked after being idle to `out_locked`.
+ */
+uint32_t epic_is_session_locked(SessionId session, bool *out_locked);
+
+/**
+ * Get the error of the last failed typed call on this thread.
+ *
+ * Returns a failed result envelope, as returned by the string ABI, or null if
+ * the last typed call succeeded. The caller frees it with `rust_string_free`.
+ */
+char *epic_last_error(void);
+
+/**
+ * Describe this build of the library, writing it as JSON to `out_json`.
+ */
+uint32_t epic_lib_info(char **out_json);
+
+/**
+ * Configure logging from a JSON config, as for `rust_logger_init`.
+ */
+uint32_t epic_logger_init(const char *config);
+
+/**
+ * Create a wallet named `name` under `root`, writing its entry as JSON to
+ * `out_json`.
+ */
+uint32_t epic_manager_create_wallet(const char *root,
+                                    const char *config,
+                                    const char *name,
+                                    const char *mnemonic,
+                                    const char *password,
+                                    char **out_json);
+
+/**
+ * Delete the wallet `name` under `root`, writing what was removed as JSON to
+ * `out_json`.
+ */
+uint32_t epic_manager_delete_wallet(const char *root, const char *name, char **out_json);
+
+/**
+ * List the wallets kept under `root`, writing them as a JSON array to
+ * `out_json`.
+ */
+uint32_t epic_manager_list_wallets(const char *root, char **out_json);
+
+/**
+ * Open the wallet named `name` under `root`, writing the session id to
+ * `out_session`.
+ */
+uint32_t epic_manager_open_wallet(const char *root,
+                                  const char *config,
+                                  const char *name,
+                                  const char *password,
+                                  SessionId *out_session);
+
+/**
+ * Rename the closed wallet `name` under `root` to `new_name`, writing its
+ * entry as JSON to `out_json`.
+ */
+uint32_t epic_manager_rename_wallet(const char *root,
+                                    const char *name,
+                                    const char *new_name,
+                                    char **out_json);
+
+/**
+ * Get the largest amount a sweep can send, writing it as JSON to `out_json`.
+ */
+uint32_t epic_max_sendable(SessionId session, uint64_t min_confirmations, char **out_json);
+
+/**
+ * Open a wallet, writing its session id to `out_session`.
+ */
+uint32_t epic_open_wallet(const char *config, const char *password, SessionId *out_session);
+
+/**
+ * Run an owner JSON-RPC request against a session, writing the JSON-RPC
+ * response to `out_json`.
+ */
+uint32_t epic_owner_rpc(SessionId session, const char *request, char **out_json);
+
+/**
+ * Restore a wallet backup into the new or empty `wallet_dir`, writing
+ * `{files, bytes}` as JSON to `out_json`.
+ */
+uint32_t epic_restore_wallet_backup(const char *archive_path,
+                                    const char *password,
+                                    const char *wallet_dir,
+                                    char **out_json);
+
+/**
+ * Decrypt the seed of the wallet in `config` and write its recovery phrase to
+ * `out_mnemonic`. Free it with `rust_string_free` as soon as it was shown.
+ */
+uint32_t epic_reveal_mnemonic(const char *config, const char *password, char **out_mnemonic);
+
+/**
+ * Switch the account the session sends, receives and reports for.
+ */
+uint32_t epic_set_active_account(SessionId session, const char *label);
+
+/**
+ * Lock spending on the session after `seconds` without spending; 0 never
+ * locks it.
+ */
+uint32_t epic_set_idle_timeout(SessionId session, uint64_t seconds);
+
+/**
+ * Take the session's queued status messages, writing them as a JSON array to
+ * `out_json`.
+ */
+uint32_t epic_status_poll(SessionId session, char **out_json);
+
+/**
+ * Cancel a transaction by its UUID.
+ */
+uint32_t epic_tx_cancel(SessionId session, const char *tx_id);
+
+/**
+ * Finalize and post a slate, writing it as JSON to `out_json`.
+ */
+uint32_t epic_tx_finalize(SessionId session, const char *slate_json, char **out_json);
+
+/**
+ * Receive a slate, writing the response slate as JSON to `out_json`.
+ */
+uint32_t epic_tx_receive(SessionId session, const char *slate_json, char **out_json);
+
+/**
+ * Send a transaction over http, writing the slate as JSON to `out_json`.
+ *
+ * An `amount` of `EPIC_SEND_MAX` sweeps the whole spendable balance.
+ */
+uint32_t epic_tx_send_http(SessionId session,
+                           bool selection_strategy_is_use_all,
+                           uint64_t minimum_confirmations,
+                           const char *message,
+                           uint64_t amount,
+                           const char *address,
+                           char **out_json);
+
+/**
+ * Get transactions, writing them as JSON to `out_json`.
+ */
+uint32_t epic_txs_get(SessionId session, bool refresh_from_node, char **out_json);
+
+/**
+ * Allow spending on a session that locked after being idle, once `password`
+ * is checked.
+ */
+uint32_t epic_unlock_wallet(SessionId session, const char *password);
+
+/**
+ * Check an epicbox address, writing the outcome to `out_valid`.
+ */
+uint32_t epic_validate_address(const char *address, bool *out_valid);
+
+/**
+ * Get wallet balances, writing them as JSON to `out_json`.
+ */
+uint32_t epic_wallet_balances(SessionId session,
+                              bool refresh,
+                              uint64_t min_confirmations,
+                              char **out_json);
+
+/**
+ * Scan wallet outputs, writing the result as JSON to `out_json`.
+ */
+uint32_t epic_wallet_scan_outputs(SessionId session,
+                                  uint64_t start_height,
+                                  uint64_t number_of_blocks,
+                                  char **out_json);
+
+/**
+ * Write whether the wallet in `config` exists and what it was created with
+ * to `out_json`. Needs no password.
+ */
+uint32_t epic_wallet_status(const char *config, char **out_json);
+
+/**
+ * Get a new mnemonic.
+ */
+const char *get_mnemonic(void);
+
+/**
+ * List a wallet's accounts via FFI.
+ *
+ * Returns a JSON array of `{label, path, active}` objects, where `path` is the
+ * BIP32 path of the account's parent key and `active` marks the account the
+ * session sends, receives and reports balances and history for.
+ */
+const char *rust_accounts(const char *wallet);
+
+/**
+ * Back up a wallet to an encrypted archive via FFI.
+ *
+ * The archive holds everything in the wallet directory but logs: the seed
+ * file, the database with the transaction log, notes and accounts, and saved
+ * slates. It is encrypted and authenticated with `password`. The wallet must
+ * be closed. Returns `{files, bytes}`.
+ */
+const char *rust_backup_wallet(const char *config, const char *password, const char *archive_path);
+
+/**
+ * Stop the operations in flight on a wallet session via FFI.
+ *
+ * Scans stop after the batch in progress, keeping the progress saved so far
+ * and reporting `last_scanned_height` in the error details; refreshes and
+ * transaction building stop before anything more is written. The stopped
+ * calls fail with a `cancelled` error. Returns the number of
+ * operations asked to stop.
+ */
+const char *rust_cancel_operation(const char *wallet);
+
+/**
+ * Change a wallet's password via FFI.
+ *
+ * The wallet may be open; its sessions keep working. Fails with
+ * `wrong_password` if `old_password` does not decrypt the seed.
+ */
+const char *rust_change_password(const char *config,
+                                 const char *old_password,
+                                 const char *new_password);
+
+/**
+ * Close a wallet via FFI.
+ *
+ * Releases the wallet behind the handle. The handle, and any copy of it, is
+ * invalid afterwards.
+ */
+const char *rust_close_wallet(const char *wallet);
+
+/**
+ * Create a named account via FFI, returning its parent key path.
+ *
+ * The active account does not change.
+ */
+const char *rust_create_account(const char *wallet, const char *label);
+
+/**
+ * Create a transaction via FFI.
+ *
+ * An `amount` of `max` sweeps the whole spendable balance less the fee.
+ */
+const char *rust_create_tx(const char *wallet,
+                           const char *amount,
+                           const char *to_address,
+                           const char *secret_key_index,
+                           const char *epicbox_config,
+                           const char *confirmations,
+                           const char *note,
+                           const char *return_slate_flag);
+
+/**
+ * Delete a wallet via FFI.
+ *
+ * Works from `config` alone: the wallet need not be open, and its password
+ * and database are not needed. A session that has the wallet open is closed
+ * first, which stops its epicbox listeners. `wallet` may be empty; a session
+ * id passed in it must be of this wallet, or the call fails with
+ * `invalid_argument` and nothing is deleted. Only the
+ * wallet's own files are removed, the seed file overwritten first, and the
+ * directory only if nothing else is left in it. Fails with `wallet_locked` if
+ * another process has the wallet open. Returns `{removed, kept,
+ * closed_sessions}`, where `removed` lists every file removed and `kept` what
+ * was left in the directory, relative to it.
+ */
+const char *rust_delete_wallet(const char *wallet, const char *config);
+
+/**
+ * Start a listener via FFI.
+ *
+ * Returns null if an argument is malformed; the listener itself reports a bad
+ * epicbox config or an unknown session when it runs.
+ */
+void *rust_epicbox_listener_start(const char *wallet, const char *epicbox_config);
+
+/**
+ * Start finalizing and posting a slate via FFI.
+ *
+ * The task result is the same as the result of [`rust_tx_finalize`].
+ */
+void *rust_finalize_task_start(const char *wallet, const char *slate_json);
+
+/**
+ * Run a foreign JSON-RPC 2.0 request against a wallet session via FFI.
+ *
+ * Covers `check_version`, `build_coinbase`, `verify_slate_messages`,
+ * `receive_tx` and `finalize_invoice_tx`, with the same request and response
+ * shapes as the epic-wallet foreign HTTP API. Returns the JSON-RPC response.
+ */
+const char *rust_foreign_rpc(const char *wallet, const char *request);
+
+/**
+ * Get chain height via FFI.
+ */
+const char *rust_get_chain_height(const char *config);
+
+/**
+ * Get a fee quote for every coin selection strategy via FFI.
+ *
+ * A strategy that can't cover the amount carries `not_enough_funds` instead
+ * of failing the call.
+ */
+const char *rust_get_tx_fees(const char *wallet,
+                             const char *c_amount,
+                             const char *min_confirmations);
+
+/**
+ * Get a wallet address via FFI.
+ */
+const char *rust_get_wallet_address(const char *wallet,
+                                    const char *index,
+                                    const char *epicbox_config);
+
+/**
+ * Check whether a wallet session is locked after being idle, via FFI.
+ */
+const char *rust_is_session_locked(const char *wallet);
+
+/**
+ * Describe this build of the library via FFI.
+ *
+ * Returns the crate version, the pinned epic dependency revisions, supported
+ * slate versions, enabled cargo features and available transports.
+ */
+const char *rust_lib_info(void);
+
+/**
+ * Configure logging via FFI.
+ *
+ * `config` is a JSON object with optional `level` (`off`, `error`, `warn`,
+ * `info`, `debug` or `trace`), `wallet_dir` (log files go to `logs` under it),
+ * `max_file_size`, `max_files` and `stderr`. Passwords, mnemonics, keychain
+ * masks and other secrets are redacted from every sink. Returns the path of
+ * the log file, or null if none is written.
+ */
+const char *rust_logger_init(const char *config);
+
+/**
+ * Set the callback that receives every redacted log line, or clear it with
+ * null. The callback may be called from any thread, and must not block.
+ */
+void rust_logger_set_callback(LogCallback callback);
+
+/**
+ * Create a wallet named `name` under a data directory via FFI.
+ *
+ * `config` supplies the node and the chain; its `wallet_dir` is ignored.
+ * Fails with `wallet_seed_exists` if the name is taken. Returns the new
+ * wallet's entry, as listed by [`rust_manager_list_wallets`].
+ */
+const char *rust_manager_create_wallet(const char *root,
+                                       const char *config,
+                                       const char *name,
+                                       const char *mnemonic,
+                                       const char *password);
+
+/**
+ * Delete a wallet under a data directory via FFI.
+ *
+ * The same as [`rust_delete_wallet`] for the wallet's directory, returning
+ * `{removed, kept, closed_sessions}`.
+ */
+const char *rust_manager_delete_wallet(const char *root, const char *name);
+
+/**
+ * List the wallets kept under a data directory via FFI.
+ *
+ * Each wallet lives in `root/<name>`. Returns an array of
+ * `{name, chain, created_at, wallet_dir}` sorted by name; `chain` and
+ * `created_at` are null for wallets created before they were recorded.
+ */
+const char *rust_manager_list_wallets(const char *root);
+
+/**
+ * Open the wallet named `name` under a data directory via FFI.
+ *
+ * `config` supplies the node and the account; the wallet directory is the
+ * wallet's own and the chain is the one it was created on. Otherwise the same
+ * as [`rust_open_wallet`], returning a session id.
+ */
+const char *rust_manager_open_wallet(const char *root,
+                                     const char *config,
+                                     const char *name,
+                                     const char *password);
+
+/**
+ * Rename a closed wallet under a data directory via FFI.
+ *
+ * Fails with `wallet_seed_exists` if `new_name` is taken, and with
+ * `lifecycle` or `wallet_locked` if the wallet is open. Returns the renamed
+ * wallet's entry.
+ */
+const char *rust_manager_rename_wallet(const char *root, const char *name, const char *new_name);
+
+/**
+ * Get the largest amount a sweep can send via FFI.
+ *
+ * Returns `{amount, fee, inputs, spendable}`, where `amount` is the spendable
+ * balance less the fee for spending every output with no change.
+ */
+const char *rust_max_sendable(const char *wallet, const char *min_confirmations);
+
+/**
+ * Open a wallet via FFI.
+ */
+const char *rust_open_wallet(const char *config, const char *password);
+
+/**
+ * Run an owner JSON-RPC 2.0 request against a wallet session via FFI.
+ *
+ * Any method of the owner API is available except those that open, close or
+ * re-key the wallet. Callers leave out the `token` parameter; the session's
+ * keychain mask is passed for the methods that take one. Returns the JSON-RPC
+ * response.
+ */
+const char *rust_owner_rpc(const char *wallet, const char *request);
+
+/**
+ * Recover a wallet from a mnemonic via FFI.
+ */
+const char *rust_recover_from_mnemonic(const char *config,
+                                       const char *password,
+                                       const char *mnemonic,
+                                       const char *name);
+
+/**
+ * Start refreshing outputs and transactions from the node via FFI.
+ *
+ * The task result is the refreshed transaction list.
+ */
+void *rust_refresh_task_start(const char *wallet);
+
+/**
+ * Restore a wallet backup into a new wallet directory via FFI.
+ *
+ * `wallet_dir` must not exist yet or be empty. Fails with `wrong_password` if
+ * `password` does not match or the archive was altered, and with `lifecycle`
+ * or `wallet_locked` if the directory is open here or in another process.
+ * Open the restored wallet with a config pointing at `wallet_dir` and the
+ * wallet's own password. Returns `{files, bytes}`.
+ */
+const char *rust_restore_wallet_backup(const char *archive_path,
+                                       const char *password,
+                                       const char *wallet_dir);
+
+/**
+ * Reveal a wallet's recovery phrase via FFI.
+ *
+ * The seed is decrypted with `password`; fails with `wrong_password` if it
+ * does not match. The phrase is never logged, and every buffer holding it on
+ * this side is zeroed. Free the result with [`rust_string_free`] as soon as
+ * the phrase has been shown.
+ */
+const char *rust_reveal_mnemonic(const char *config, const char *password);
+
+/**
+ * Start scanning outputs on a background thread via FFI.
+ *
+ * Cancelling the task stops the scan after the current batch of blocks, failing
+ * as `cancelled` with `last_scanned_height` in the details. The task result is
+ * the last scanned height.
+ */
+void *rust_scan_outputs_task_start(const char *wallet,
+                                   const char *start_height,
+                                   const char *number_of_blocks);
+
+/**
+ * Start creating and sending a transaction over http via FFI.
+ *
+ * Takes the same arguments as [`rust_tx_send_http`], and the task result is
+ * the same as its result.
+ */
+void *rust_send_http_task_start(const char *wallet,
+                                const char *selection_strategy_is_use_all,
+                                const char *minimum_confirmations,
+                                const char *message,
+                                const char *amount,
+                                const char *address);
+
+/**
+ * Start creating and sending a transaction over epicbox via FFI.
+ *
+ * Takes the same arguments as [`rust_create_tx`], and the task result is the
+ * same as its result.
+ */
+void *rust_send_task_start(const char *wallet,
+                           const char *amount,
+                           const char *to_address,
+                           const char *epicbox_config,
+                           const char *confirmations,
+                           const char *note,
+                           const char *return_slate_flag);
+
+/**
+ * Switch the active account of a wallet session via FFI.
+ *
+ * Sends, receives, balances and transaction history of the session use the
+ * active account from then on. Other sessions are not affected.
+ */
+const char *rust_set_active_account(const char *wallet, const char *label);
+
+/**
+ * Set how long a wallet session may go without spending before it locks,
+ * via FFI.
+ *
+ * A locked session keeps serving balances, history and refreshes, but
+ * sending and finalizing fail with `session_locked` until
+ * [`rust_unlock_wallet`] is called with the wallet password. Only successful
+ * spends and unlocks count as activity. `seconds` of 0 turns the lock off,
+ * which is the default.
+ */
+const char *rust_set_idle_timeout(const char *wallet, const char *seconds);
+
+/**
+ * Take the status messages queued for a wallet session via FFI.
+ *
+ * Scans, refreshes and balance or transaction queries that hit the node report
+ * progress here. Returns a JSON array, oldest first, which is empty if nothing
+ * was reported since the last poll.
+ */
+const char *rust_status_poll(const char *wallet);
+
+/**
+ * Free a string returned by any export of this library.
+ *
+ * Every `const char *` returned by this library is owned by the caller and must
+ * be passed back here exactly once. The bytes are zeroed before being released,
+ * since results may carry mnemonics or slates. Passing null is a no-op; passing
+ * a pointer not obtained from this library is undefined behaviour.
+ */
+void rust_string_free(char *s);
+
+/**
+ * Ask a wallet task to stop via FFI.
+ *
+ * Operations stop at the next point where it is safe to do so; a transaction
+ * that is already being sent is not interrupted. The envelope data is `true`
+ * once cancellation has been requested. A task being waited on can be
+ * cancelled from another thread.
+ */
+const char *rust_task_cancel(void *handle);
+
+/**
+ * Free a wallet task handle via FFI.
+ *
+ * A task that is still running is cancelled. Passing null is a no-op.
+ */
+void rust_task_destroy(void *handle);
+
+/**
+ * Poll a wallet task via FFI.
+ *
+ * Returns null while the task is running. Once it has finished, returns its
+ * result envelope, exactly once; later polls return null again.
+ */
+const char *rust_task_poll(void *handle);
+
+/**
+ * Wait for a wallet task to finish via FFI, returning its result envelope.
+ *
+ * Blocks the calling thread. The handle must still be destroyed afterwards.
+ * Fails with a `lifecycle` error if the result was already handed out by
+ * [`rust_task_poll`] or an earlier wait.
+ */
+const char *rust_task_wait(void *handle);
+
+/**
+ * Cancel a transaction via FFI.
+ */
+const char *rust_tx_cancel(const char *wallet, const char *tx_id);
+
+/**
+ * Finalize a slate via FFI.
+ *
+ * This is step 3 of the 3-part transaction process for slates/slatepacks.
+ * The original sender finalizes the transaction with the receiver's response
+ * and broadcasts it to the network.
+ */
+const char *rust_tx_finalize(const char *wallet, const char *slate_json);
+
+/**
+ * Receive a slate via FFI.
+ *
+ * This is step 2 of the 3-part transaction process for slates/slatepacks.
+ * The receiver opens an incoming slate, adds its output and partial signature,
+ * then returns the updated slate.
+ */
+const char *rust_tx_receive(const char *wallet, const char *slate_json);
+
+/**
+ * Send a transaction via FFI.
+ *
+ * An `amount` of `max` sweeps the whole spendable balance less the fee.
+ */
+const char *rust_tx_send_http(const char *wallet,
+                              const char *selection_strategy_is_use_all,
+                              const char *minimum_confirmations,
+                              const char *message,
+                              const char *amount,
+                              const char *address);
+
+/**
+ * Get transactions via FFI.
+ */
+const char *rust_txs_get(const char *wallet, const char *refresh_from_node);
+
+/**
+ * Unlock a wallet session that locked after being idle, via FFI.
+ *
+ * Fails with `wrong_password` if `password` does not decrypt the wallet seed.
+ * Unlocking a session that is not locked only checks the password.
+ */
+const char *rust_unlock_wallet(const char *wallet, const char *password);
+
+/**
+ * Validate an address via FFI.
+ */
+const char *rust_validate_address(const char *address);
+
+/**
+ * Get wallet balances via FFI.
+ */
+const char *rust_wallet_balances(const char *wallet,
+                                 const char *refresh,
+                                 const char *min_confirmations);
+
+/**
+ * Scan wallet outputs via FFI.
+ */
+const char *rust_wallet_scan_outputs(const char *wallet,
+                                     const char *start_height,
+                                     const char *number_of_blocks);
+
+/**
+ * Report whether a wallet exists and what it was created with, via FFI.
+ *
+ * Needs no password and works whether or not the wallet is open. Returns
+ * `{seed_exists, chain, last_confirmed_height, name}`. The last two are null
+ * when unknown: `name` for wallets created before it was recorded, and
+ * `last_confirmed_height` for a wallet that is not open here and has not been
+ * closed since it was created. The database of a closed wallet is not read;
+ * its height is the one recorded when it was last closed. No database schema
+ * version is reported, as the LMDB backend does not record one.
+ */
+const char *rust_wallet_status(const char *config);
+
+/**
+ * Initialize a new wallet via FFI.
+ */
+const char *wallet_init(const char *config,
+                        const char *mnemonic,
+                        const char *password,
+                        const char *name);
@@ -0,0 +1,725 @@
+use std::cell::RefCell;
+use std::ffi::CString;
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use serde_json::Value;
+use uuid::Uuid;
+use zeroize::Zeroizing;
+
+use crate::envelope::{self, ErrorCode, FfiError};
+use crate::ffi::{
+    arg_str, panic_error, secret_into_raw, _accounts, _backup_wallet, _cancel_operation,
+    _change_password, _close_wallet, _create_account, _create_tx, _delete_wallet, _foreign_rpc,
+    _get_chain_height, _get_tx_fees, _get_wallet_address, _is_session_locked, _logger_init,
+    _manager_create_wallet, _manager_delete_wallet, _manager_list_wallets, _manager_open_wallet,
+    _manager_rename_wallet, _max_sendable, _open_wallet, _owner_rpc, _restore_wallet_backup, _reveal_mnemonic,
+    _set_active_account, _set_idle_timeout, _status_poll, _tx_cancel, _tx_finalize, _tx_receive,
+    _tx_send_http, _txs_get, _unlock_wallet, _wallet_balances, _wallet_scan_outputs,
+    _wallet_status,
+};
+use crate::info::lib_info;
+use crate::session::{self, SessionId};
+use crate::wallet::{validate_address, SendAmount};
+
+/// Status returned by every typed export on success.
+///
+/// Any other status is the numeric code of the error, as listed in
+/// `crate::envelope::ErrorCode`; call `epic_last_error` for the details.
+pub const EPIC_OK: u32 = 0;
+
+/// Amount that makes a send sweep the whole spendable balance less the fee.
+pub const EPIC_SEND_MAX: u64 = 0xFFFF_FFFF_FFFF_FFFFu64;
+
+thread_local! {
+    /// The error of the last failed typed call on this thread.
+    static LAST_ERROR: RefCell<Option<FfiError>> = const { RefCell::new(None) };
+}
+
+/// Run the body of a typed export and write its result through `out`.
+///
+/// Returns `EPIC_OK`, or the error code after recording the error for
+/// `epic_last_error`. Panics are caught like in the string ABI.
+unsafe fn call<T, F>(out: *mut T, body: F) -> u32
+where
+    F: FnOnce() -> Result<T, FfiError>,
+{
+    let result = if out.is_null() {
+        Err(FfiError::new(ErrorCode::InvalidArgument, "out pointer is null"))
+    } else {
+        panic::catch_unwind(AssertUnwindSafe(body))
+            .unwrap_or_else(|payload| Err(panic_error(payload)))
+    };
+    match result {
+        Ok(value) => {
+            *out = value;
+            LAST_ERROR.with(|e| *e.borrow_mut() = None);
+            EPIC_OK
+        }
+        Err(err) => {
+            let code = err.code.code();
+            LAST_ERROR.with(|e| *e.borrow_mut() = Some(err));
+            code
+        }
+    }
+}
+
+/// Like [`call`], for exports that have no result.
+unsafe fn call_unit<F>(body: F) -> u32
+where
+    F: FnOnce() -> Result<(), FfiError>,
+{
+    call(&mut (), body)
+}
+
+/// The amount to send for a typed `amount` argument.
+fn send_amount(amount: u64) -> SendAmount {
+    match amount {
+        EPIC_SEND_MAX => SendAmount::Sweep,
+        amount => SendAmount::Exact(amount),
+    }
+}
+
+/// Hand a result to the caller as a string: strings as they are, anything else
+/// as JSON. The caller frees it with `rust_string_free`.
+fn into_c_string(value: Value) -> Result<*mut c_char, FfiError> {
+    let s = match value {
+        Value::String(s) => s,
+        value => value.to_string(),
+    };
+    CString::new(s)
+        .map(CString::into_raw)
+        .map_err(|e| FfiError::new(ErrorCode::Generic, e.to_string()))
+}
+
+/// Get the error of the last failed typed call on this thread.
+///
+/// Returns a failed result envelope, as returned by the string ABI, or null if
+/// the last typed call succeeded. The caller frees it with `rust_string_free`.
+#[no_mangle]
+pub extern "C" fn epic_last_error() -> *mut c_char {
+    match LAST_ERROR.with(|e| e.borrow().clone()) {
+        Some(err) => {
+            let json = envelope::to_json::<FfiError>(Err(err));
+            CString::new(json).map(CString::into_raw).unwrap_or(ptr::null_mut())
+        }
+        None => ptr::null_mut(),
+    }
+}
+
+/// Open a wallet, writing its session id to `out_session`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_open_wallet(
+    config: *const c_char,
+    password: *const c_char,
+    out_session: *mut SessionId,
+) -> u32 {
+    call(out_session, || {
+        let config = arg_str(config, "config")?;
+        let password = arg_str(password, "password")?;
+        let session_id = _open_wallet(config, password)?;
+        Ok(session_id.as_u64().unwrap_or_default())
+    })
+}
+
+/// Close a wallet session.
+#[no_mangle]
+pub unsafe extern "C" fn epic_close_wallet(session: SessionId) -> u32 {
+    call_unit(|| {
+        _close_wallet(session)?;
+        Ok(())
+    })
+}
+
+/// Re-encrypt the seed of the wallet in `config` under a new password.
+#[no_mangle]
+pub unsafe extern "C" fn epic_change_password(
+    config: *const c_char,
+    old_password: *const c_char,
+    new_password: *const c_char,
+) -> u32 {
+    call_unit(|| {
+        let config = arg_str(config, "config")?;
+        let old_password = arg_str(old_password, "old_password")?;
+        let new_password = arg_str(new_password, "new_password")?;
+        _change_password(config, old_password, new_password)?;
+        Ok(())
+    })
+}
+
+/// Write whether the wallet in `config` exists and what it was created with
+/// to `out_json`. Needs no password.
+#[no_mangle]
+pub unsafe extern "C" fn epic_wallet_status(config: *const c_char, out_json: *mut *mut c_char) -> u32 {
+    call(out_json, || {
+        let config = arg_str(config, "config")?;
+        into_c_string(_wallet_status(config)?)
+    })
+}
+
+/// Delete the wallet in `config` without its password, writing the removed
+/// and kept files and the number of closed sessions as JSON to `out_json`. A session
+/// that has the wallet open is closed first.
+#[no_mangle]
+pub unsafe extern "C" fn epic_delete_wallet(config: *const c_char, out_json: *mut *mut c_char) -> u32 {
+    call(out_json, || {
+        let config = arg_str(config, "config")?;
+        into_c_string(serde_json::to_value(_delete_wallet(config)?)?)
+    })
+}
+
+/// Decrypt the seed of the wallet in `config` and write its recovery phrase to
+/// `out_mnemonic`. Free it with `rust_string_free` as soon as it was shown.
+#[no_mangle]
+pub unsafe extern "C" fn epic_reveal_mnemonic(
+    config: *const c_char,
+    password: *const c_char,
+    out_mnemonic: *mut *mut c_char,
+) -> u32 {
+    call(out_mnemonic, || {
+        let config = arg_str(config, "config")?;
+        let password = arg_str(password, "password")?;
+        let mnemonic = _reveal_mnemonic(config, password)?;
+        // Sized for the terminator, so the C string takes the buffer over as it is.
+        let mut phrase = Zeroizing::new(String::with_capacity(mnemonic.len() + 1));
+        phrase.push_str(&mnemonic);
+        Ok(secret_into_raw(phrase))
+    })
+}
+
+/// Back up the closed wallet in `config` to an archive encrypted with
+/// `password`, writing `{files, bytes}` as JSON to `out_json`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_backup_wallet(
+    config: *const c_char,
+    password: *const c_char,
+    archive_path: *const c_char,
+    out_json: *mut *mut c_char,
+) -> u32 {
+    call(out_json, || {
+        let config = arg_str(config, "config")?;
+        let password = arg_str(password, "password")?;
+        let archive_path = arg_str(archive_path, "archive_path")?;
+        into_c_string(_backup_wallet(config, password, archive_path)?)
+    })
+}
+
+/// Restore a wallet backup into the new or empty `wallet_dir`, writing
+/// `{files, bytes}` as JSON to `out_json`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_restore_wallet_backup(
+    archive_path: *const c_char,
+    password: *const c_char,
+    wallet_dir: *const c_char,
+    out_json: *mut *mut c_char,
+) -> u32 {
+    call(out_json, || {
+        let archive_path = arg_str(archive_path, "archive_path")?;
+        let password = arg_str(password, "password")?;
+        let wallet_dir = arg_str(wallet_dir, "wallet_dir")?;
+        into_c_string(_restore_wallet_backup(archive_path, password, wallet_dir)?)
+    })
+}
+
+/// List the wallets kept under `root`, writing them as a JSON array to
+/// `out_json`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_manager_list_wallets(root: *const c_char, out_json: *mut *mut c_char) -> u32 {
+    call(out_json, || {
+        let root = arg_str(root, "root")?;
+        into_c_string(_manager_list_wallets(root)?)
+    })
+}
+
+/// Create a wallet named `name` under `root`, writing its entry as JSON to
+/// `out_json`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_manager_create_wallet(
+    root: *const c_char,
+    config: *const c_char,
+    name: *const c_char,
+    mnemonic: *const c_char,
+    password: *const c_char,
+    out_json: *mut *mut c_char,
+) -> u32 {
+    call(out_json, || {
+        let root = arg_str(root, "root")?;
+        let config = arg_str(config, "config")?;
+        let name = arg_str(name, "name")?;
+        let mnemonic = arg_str(mnemonic, "mnemonic")?;
+        let password = arg_str(password, "password")?;
+        into_c_string(_manager_create_wallet(root, config, name, mnemonic, password)?)
+    })
+}
+
+/// Open the wallet named `name` under `root`, writing the session id to
+/// `out_session`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_manager_open_wallet(
+    root: *const c_char,
+    config: *const c_char,
+    name: *const c_char,
+    password: *const c_char,
+    out_session: *mut SessionId,
+) -> u32 {
+    call(out_session, || {
+        let root = arg_str(root, "root")?;
+        let config = arg_str(config, "config")?;
+        let name = arg_str(name, "name")?;
+        let password = arg_str(password, "password")?;
+        let session_id = _manager_open_wallet(root, config, name, password)?;
+        Ok(session_id.as_u64().unwrap_or_default())
+    })
+}
+
+/// Rename the closed wallet `name` under `root` to `new_name`, writing its
+/// entry as JSON to `out_json`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_manager_rename_wallet(
+    root: *const c_char,
+    name: *const c_char,
+    new_name: *const c_char,
+    out_json: *mut *mut c_char,
+) -> u32 {
+    call(out_json, || {
+        let root = arg_str(root, "root")?;
+        let name = arg_str(name, "name")?;
+        let new_name = arg_str(new_name, "new_name")?;
+        into_c_string(_manager_rename_wallet(root, name, new_name)?)
+    })
+}
+
+/// Delete the wallet `name` under `root`, writing what was removed as JSON to
+/// `out_json`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_manager_delete_wallet(
+    root: *const c_char,
+    name: *const c_char,
+    out_json: *mut *mut c_char,
+) -> u32 {
+    call(out_json, || {
+        let root = arg_str(root, "root")?;
+        let name = arg_str(name, "name")?;
+        into_c_string(_manager_delete_wallet(root, name)?)
+    })
+}
+
+/// Get wallet balances, writing them as JSON to `out_json`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_wallet_balances(
+    session: SessionId,
+    refresh: bool,
+    min_confirmations: u64,
+    out_json: *mut *mut c_char,
+) -> u32 {
+    call(out_json, || {
+        ensure_wallet!(session, wallet, sek_key, status_send, stop);
+        let balances = _wallet_balances(wallet, sek_key, refresh, min_confirmations, status_send, stop.flag());
+        into_c_string(stop.finish(balances)?)
+    })
+}
+
+/// Scan wallet outputs, writing the result as JSON to `out_json`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_wallet_scan_outputs(
+    session: SessionId,
+    start_height: u64,
+    number_of_blocks: u64,
+    out_json: *mut *mut c_char,
+) -> u32 {
+    call(out_json, || {
+        ensure_wallet!(session, wallet, sek_key, status_send, stop);
+        into_c_string(stop.finish(_wallet_scan_outputs(
+            wallet,
+            sek_key,
+            start_height,
+            number_of_blocks,
+            status_send,
+            || stop.is_stopped(),
+        ))?)
+    })
+}
+
+/// Create a transaction via epicbox, writing the slate as JSON to `out_json`.
+///
+/// An `amount` of `EPIC_SEND_MAX` sweeps the whole spendable balance.
+#[no_mangle]
+pub unsafe extern "C" fn epic_create_tx(
+    session: SessionId,
+    amount: u64,
+    to_address: *const c_char,
+    epicbox_config: *const c_char,
+    min_confirmations: u64,
+    note: *const c_char,
+    return_slate: bool,
+    out_json: *mut *mut c_char,
+) -> u32 {
+    call(out_json, || {
+        let to_address = arg_str(to_address, "to_address")?;
+        let epicbox_config = arg_str(epicbox_config, "epicbox_config")?;
+        let note = arg_str(note, "note")?;
+        let sent = session::get_session(session)?.spend(|| {
+            ensure_wallet!(session, wallet, sek_key, _status_send, stop);
+            stop.finish(_create_tx(
+                wallet,
+                sek_key,
+                send_amount(amount),
+                to_address,
+                0,
+                epicbox_config,
+                min_confirmations,
+                note,
+                return_slate,
+                stop.flag(),
+            ))
+        })?;
+        into_c_string(sent)
+    })
+}
+
+/// Get transactions, writing them as JSON to `out_json`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_txs_get(
+    session: SessionId,
+    refresh_from_node: bool,
+    out_json: *mut *mut c_char,
+) -> u32 {
+    call(out_json, || {
+        ensure_wallet!(session, wallet, sek_key, status_send, stop);
+        into_c_string(stop.finish(_txs_get(wallet, sek_key, refresh_from_node, status_send, stop.flag()))?)
+    })
+}
+
+/// Run an owner JSON-RPC request against a session, writing the JSON-RPC
+/// response to `out_json`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_owner_rpc(
+    session: SessionId,
+    request: *const c_char,
+    out_json: *mut *mut c_char,
+) -> u32 {
+    call(out_json, || {
+        let request = arg_str(request, "request")?;
+        into_c_string(_owner_rpc(session, request)?)
+    })
+}
+
+/// Run a foreign JSON-RPC request against a session, writing the JSON-RPC
+/// response to `out_json`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_foreign_rpc(
+    session: SessionId,
+    request: *const c_char,
+    out_json: *mut *mut c_char,
+) -> u32 {
+    call(out_json, || {
+        let request = arg_str(request, "request")?;
+        into_c_string(_foreign_rpc(session, request)?)
+    })
+}
+
+/// Take the session's queued status messages, writing them as a JSON array to
+/// `out_json`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_status_poll(session: SessionId, out_json: *mut *mut c_char) -> u32 {
+    call(out_json, || into_c_string(_status_poll(session)?))
+}
+
+/// Stop the operations in flight on the session, writing how many were asked
+/// to stop to `out_count`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_cancel_operation(session: SessionId, out_count: *mut u64) -> u32 {
+    call(out_count, || {
+        let count = _cancel_operation(session)?;
+        Ok(count.as_u64().unwrap_or_default())
+    })
+}
+
+/// Lock spending on the session after `seconds` without spending; 0 never
+/// locks it.
+#[no_mangle]
+pub unsafe extern "C" fn epic_set_idle_timeout(session: SessionId, seconds: u64) -> u32 {
+    call_unit(|| {
+        _set_idle_timeout(session, seconds)?;
+        Ok(())
+    })
+}
+
+/// Write whether the session is locked after being idle to `out_locked`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_is_session_locked(session: SessionId, out_locked: *mut bool) -> u32 {
+    call(out_locked, || {
+        let locked = _is_session_locked(session)?;
+        Ok(locked.as_bool().unwrap_or_default())
+    })
+}
+
+/// Allow spending on a session that locked after being idle, once `password`
+/// is checked.
+#[no_mangle]
+pub unsafe extern "C" fn epic_unlock_wallet(session: SessionId, password: *const c_char) -> u32 {
+    call_unit(|| {
+        let password = arg_str(password, "password")?;
+        _unlock_wallet(session, password)?;
+        Ok(())
+    })
+}
+
+/// Cancel a transaction by its UUID.
+#[no_mangle]
+pub unsafe extern "C" fn epic_tx_cancel(session: SessionId, tx_id: *const c_char) -> u32 {
+    call_unit(|| {
+        let tx_id = arg_str(tx_id, "tx_id")?;
+        let uuid = Uuid::parse_str(tx_id).map_err(|e| FfiError::new(
+            ErrorCode::InvalidArgument,
+            format!("tx_id '{}' is invalid: {}", tx_id, e),
+        ))?;
+        ensure_wallet!(session, wallet, sek_key);
+        _tx_cancel(wallet, sek_key, uuid)?;
+        Ok(())
+    })
+}
+
+/// Get the chain height from the node in `config`, writing it to `out_height`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_get_chain_height(config: *const c_char, out_height: *mut u64) -> u32 {
+    call(out_height, || {
+        let config = arg_str(config, "config")?;
+        let height = _get_chain_height(config)?;
+        Ok(height.as_u64().unwrap_or_default())
+    })
+}
+
+/// Send a transaction over http, writing the slate as JSON to `out_json`.
+///
+/// An `amount` of `EPIC_SEND_MAX` sweeps the whole spendable balance.
+#[no_mangle]
+pub unsafe extern "C" fn epic_tx_send_http(
+    session: SessionId,
+    selection_strategy_is_use_all: bool,
+    minimum_confirmations: u64,
+    message: *const c_char,
+    amount: u64,
+    address: *const c_char,
+    out_json: *mut *mut c_char,
+) -> u32 {
+    call(out_json, || {
+        let message = arg_str(message, "message")?;
+        let address = arg_str(address, "address")?;
+        let sent = session::get_session(session)?.spend(|| {
+            ensure_wallet!(session, wallet, sek_key, _status_send, stop);
+            stop.finish(_tx_send_http(
+                wallet,
+                sek_key,
+                selection_strategy_is_use_all,
+                minimum_confirmations,
+                message,
+                send_amount(amount),
+                address,
+                stop.flag(),
+            ))
+        })?;
+        into_c_string(sent)
+    })
+}
+
+/// List the session's accounts, writing them as a JSON array to `out_json`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_accounts(session: SessionId, out_json: *mut *mut c_char) -> u32 {
+    call(out_json, || {
+        ensure_wallet!(session, wallet, sek_key);
+        into_c_string(_accounts(wallet, sek_key)?)
+    })
+}
+
+/// Create a named account, writing its parent key path to `out_path`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_create_account(
+    session: SessionId,
+    label: *const c_char,
+    out_path: *mut *mut c_char,
+) -> u32 {
+    call(out_path, || {
+        let label = arg_str(label, "label")?;
+        ensure_wallet!(session, wallet, sek_key);
+        into_c_string(_create_account(wallet, sek_key, label)?)
+    })
+}
+
+/// Switch the account the session sends, receives and reports for.
+#[no_mangle]
+pub unsafe extern "C" fn epic_set_active_account(session: SessionId, label: *const c_char) -> u32 {
+    call_unit(|| {
+        let label = arg_str(label, "label")?;
+        ensure_wallet!(session, wallet, sek_key);
+        _set_active_account(wallet, sek_key, label)?;
+        Ok(())
+    })
+}
+
+/// Get the epicbox address at `index`, writing it to `out_address`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_get_wallet_address(
+    session: SessionId,
+    index: u32,
+    epicbox_config: *const c_char,
+    out_address: *mut *mut c_char,
+) -> u32 {
+    call(out_address, || {
+        let epicbox_config = arg_str(epicbox_config, "epicbox_config")?;
+        ensure_wallet!(session, wallet, sek_key);
+        into_c_string(_get_wallet_address(wallet, sek_key, index, epicbox_config)?)
+    })
+}
+
+/// Describe this build of the library, writing it as JSON to `out_json`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_lib_info(out_json: *mut *mut c_char) -> u32 {
+    call(out_json, || into_c_string(lib_info()?))
+}
+
+/// Configure logging from a JSON config, as for `rust_logger_init`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_logger_init(config: *const c_char) -> u32 {
+    call_unit(|| {
+        let config = arg_str(config, "config")?;
+        _logger_init(config).map(|_| ())
+    })
+}
+
+/// Check an epicbox address, writing the outcome to `out_valid`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_validate_address(address: *const c_char, out_valid: *mut bool) -> u32 {
+    call(out_valid, || {
+        let address = arg_str(address, "address")?;
+        Ok(validate_address(address))
+    })
+}
+
+/// Get a fee quote for every coin selection strategy for `amount`, writing them
+/// as a JSON array to `out_json`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_get_tx_fees(
+    session: SessionId,
+    amount: u64,
+    min_confirmations: u64,
+    out_json: *mut *mut c_char,
+) -> u32 {
+    call(out_json, || {
+        ensure_wallet!(session, wallet, sek_key);
+        into_c_string(_get_tx_fees(wallet, sek_key, amount, min_confirmations)?)
+    })
+}
+
+/// Get the largest amount a sweep can send, writing it as JSON to `out_json`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_max_sendable(
+    session: SessionId,
+    min_confirmations: u64,
+    out_json: *mut *mut c_char,
+) -> u32 {
+    call(out_json, || {
+        ensure_wallet!(session, wallet, _sek_key);
+        into_c_string(_max_sendable(wallet, min_confirmations)?)
+    })
+}
+
+/// Receive a slate, writing the response slate as JSON to `out_json`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_tx_receive(
+    session: SessionId,
+    slate_json: *const c_char,
+    out_json: *mut *mut c_char,
+) -> u32 {
+    call(out_json, || {
+        let slate_json = arg_str(slate_json, "slate_json")?;
+        ensure_wallet!(session, wallet, sek_key);
+        into_c_string(_tx_receive(wallet, sek_key, slate_json)?)
+    })
+}
+
+/// Finalize and post a slate, writing it as JSON to `out_json`.
+#[no_mangle]
+pub unsafe extern "C" fn epic_tx_finalize(
+    session: SessionId,
+    slate_json: *const c_char,
+    out_json: *mut *mut c_char,
+) -> u32 {
+    call(out_json, || {
+        let slate_json = arg_str(slate_json, "slate_json")?;
+        let finalized = session::get_session(session)?.spend(|| {
+            ensure_wallet!(session, wallet, sek_key);
+            Ok(_tx_finalize(wallet, sek_key, slate_json)?)
+        })?;
+        into_c_string(finalized)
+    })
+}
+
+#[cfg(test)]
+mod abi_tests {
+    use super::*;
+    use std::ffi::CStr;
+    use std::path::PathBuf;
+
+    use crate::ffi::rust_string_free;
+
+    unsafe fn last_error_kind() -> String {
+        let ptr = epic_last_error();
+        assert!(!ptr.is_null());
+        let raw = CStr::from_ptr(ptr).to_str().unwrap().to_string();
+        rust_string_free(ptr);
+        let envelope: Value = serde_json::from_str(&raw).unwrap();
+        envelope["error"]["kind"].as_str().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_status_and_last_error() {
+        let address = CString::new("invalid_address").unwrap();
+        unsafe {
+            let mut valid = true;
+            assert_eq!(epic_validate_address(address.as_ptr(), &mut valid), EPIC_OK);
+            assert!(!valid);
+            assert!(epic_last_error().is_null());
+
+            let status = epic_close_wallet(4294967297);
+            assert_eq!(status, ErrorCode::InvalidHandle.code());
+            assert_eq!(last_error_kind(), "invalid_handle");
+
+            let status = epic_validate_address(address.as_ptr(), ptr::null_mut());
+            assert_eq!(status, ErrorCode::InvalidArgument.code());
+
+            let mut json: *mut c_char = ptr::null_mut();
+            let status = epic_txs_get(4294967297, false, &mut json);
+            assert_eq!(status, ErrorCode::InvalidHandle.code());
+            assert!(json.is_null());
+        }
+    }
+
+    #[test]
+    fn test_send_max_sweeps() {
+        assert_eq!(send_amount(EPIC_SEND_MAX), SendAmount::Sweep);
+        assert_eq!(send_amount(EPIC_SEND_MAX - 1), SendAmount::Exact(EPIC_SEND_MAX - 1));
+    }
+
+    /// The checked-in header must match what cbindgen generates from the source.
+    #[test]
+    fn test_header_is_up_to_date() {
+        let crate_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml")).unwrap();
+        let bindings = cbindgen::Builder::new()
+            .with_crate(&crate_dir)
+            .with_config(config)
+            .generate()
+            .expect("cbindgen failed");
+        let mut generated = Vec::new();
+        bindings.write(&mut generated);
+
+        let checked_in = std::fs::read(crate_dir.join("include/epic_cash_wallet.h")).unwrap();
+        assert!(
+            generated == checked_in,
+            "include/epic_cash_wallet.h is out of date; regenerate it with \
+             `cbindgen --config cbindgen.toml --crate epic-cash-wallet --output include/epic_cash_wallet.h`"
+        );
+    }
+}
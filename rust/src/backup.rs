@@ -0,0 +1,641 @@
+use std::cmp;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::num::NonZeroU32;
+use std::path::{Component, Path, PathBuf};
+
+use flate2::bufread::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::pbkdf2;
+use ring::rand::{SecureRandom, SystemRandom};
+use serde::Serialize;
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::config::Config;
+use crate::envelope::{ErrorCode, FfiError};
+use crate::session;
+
+/// First bytes of every backup archive.
+const MAGIC: &[u8; 8] = b"EPICWBK\0";
+
+/// Version of the archive layout.
+const FORMAT_VERSION: u8 = 1;
+
+/// PBKDF2 rounds. Archives record the count, but only this one is accepted,
+/// so a crafted header cannot make opening an archive arbitrarily slow.
+const KDF_ITERATIONS: u32 = 100_000;
+
+const SALT_LEN: usize = 16;
+
+/// The nonce of every chunk is this prefix, the chunk counter and a flag
+/// marking the last chunk.
+const NONCE_PREFIX_LEN: usize = NONCE_LEN - 5;
+
+/// Magic, version, iteration count, salt and nonce prefix. The header is
+/// authenticated along with every chunk.
+const HEADER_LEN: usize = MAGIC.len() + 1 + 4 + SALT_LEN + NONCE_PREFIX_LEN;
+
+/// Size of the compressed contents sealed in each chunk. The last chunk may be
+/// shorter.
+const CHUNK_LEN: usize = 64 * 1024;
+
+/// Paths under the wallet directory that are not backed up: log files and the
+/// lock file of the open wallet.
+const SKIPPED: &[&str] = &["logs", ".wallet.lock"];
+
+/// The seed file, which every wallet backup must contain.
+const SEED_FILE: &str = "wallet_data/wallet.seed";
+
+/// What a backup or restore covered.
+#[derive(Debug, Serialize)]
+pub struct BackupSummary {
+    /// Number of files in the archive.
+    pub files: usize,
+    /// Total size of those files, before compression.
+    pub bytes: u64,
+}
+
+fn io_error(context: &str, e: impl std::fmt::Display) -> FfiError {
+    FfiError::new(ErrorCode::Backend, format!("{}: {}", context, e))
+}
+
+fn backup_error(message: &str) -> FfiError {
+    FfiError::new(ErrorCode::InvalidArgument, message)
+}
+
+/// A chunk failed to decrypt: the password is wrong or the archive was altered.
+#[derive(Debug)]
+struct OpenFailed;
+
+impl fmt::Display for OpenFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Unable to decrypt the backup")
+    }
+}
+
+impl std::error::Error for OpenFailed {}
+
+/// Map an error reading the decrypted contents of an archive.
+fn read_error(e: io::Error) -> FfiError {
+    if e.get_ref().is_some_and(|inner| inner.is::<OpenFailed>()) {
+        return FfiError::new(ErrorCode::WrongPassword, "Wrong password, or the backup is damaged");
+    }
+    match e.kind() {
+        io::ErrorKind::InvalidData | io::ErrorKind::InvalidInput | io::ErrorKind::UnexpectedEof => {
+            backup_error("Backup contents are damaged")
+        }
+        _ => io_error("Unable to read the backup", e),
+    }
+}
+
+/// The files under `dir`, as paths relative to it with `/` separators.
+fn collect_files(dir: &Path, prefix: &str, files: &mut Vec<String>) -> Result<(), FfiError> {
+    let mut entries = fs::read_dir(dir)
+        .map_err(|e| io_error("Unable to read the wallet directory", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| io_error("Unable to read the wallet directory", e))?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let relative = if prefix.is_empty() { name.clone() } else { format!("{}/{}", prefix, name) };
+        if SKIPPED.contains(&relative.as_str()) {
+            continue;
+        }
+        let file_type = entry.file_type().map_err(|e| io_error("Unable to read the wallet directory", e))?;
+        if file_type.is_dir() {
+            collect_files(&entry.path(), &relative, files)?;
+        } else if file_type.is_file() {
+            files.push(relative);
+        }
+    }
+    Ok(())
+}
+
+/// Derive the archive key from a password.
+fn archive_key(password: &str, salt: &[u8], iterations: u32) -> Result<LessSafeKey, FfiError> {
+    let iterations = NonZeroU32::new(iterations).ok_or_else(|| backup_error("Not a wallet backup"))?;
+    let mut key = [0u8; 32];
+    pbkdf2::derive(pbkdf2::PBKDF2_HMAC_SHA512, iterations, salt, password.as_bytes(), &mut key);
+    let unbound = UnboundKey::new(&CHACHA20_POLY1305, &key);
+    key.zeroize();
+    Ok(LessSafeKey::new(unbound.map_err(|_| backup_error("Unable to derive the backup key"))?))
+}
+
+/// The nonce of chunk number `counter`.
+fn chunk_nonce(prefix: &[u8], counter: u32, last: bool) -> Nonce {
+    let mut nonce = [0u8; NONCE_LEN];
+    nonce[..NONCE_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_LEN..NONCE_LEN - 1].copy_from_slice(&counter.to_be_bytes());
+    nonce[NONCE_LEN - 1] = last as u8;
+    Nonce::assume_unique_for_key(nonce)
+}
+
+/// Encrypts everything written to it in chunks of `CHUNK_LEN`, each sealed on
+/// its own, so an archive never has to be held in memory whole.
+struct SealingWriter<W: Write> {
+    inner: W,
+    key: LessSafeKey,
+    header: [u8; HEADER_LEN],
+    counter: u32,
+    chunk: Zeroizing<Vec<u8>>,
+}
+
+impl<W: Write> SealingWriter<W> {
+    fn new(inner: W, key: LessSafeKey, header: [u8; HEADER_LEN]) -> Self {
+        SealingWriter {
+            inner,
+            key,
+            header,
+            counter: 0,
+            chunk: Zeroizing::new(Vec::with_capacity(CHUNK_LEN + CHACHA20_POLY1305.tag_len())),
+        }
+    }
+
+    fn seal_chunk(&mut self, last: bool) -> io::Result<()> {
+        let prefix = &self.header[HEADER_LEN - NONCE_PREFIX_LEN..];
+        let nonce = chunk_nonce(prefix, self.counter, last);
+        self.key
+            .seal_in_place_append_tag(nonce, Aad::from(&self.header[..]), &mut *self.chunk)
+            .map_err(|_| io::Error::other("Unable to encrypt the backup"))?;
+        self.inner.write_all(&self.chunk)?;
+        self.chunk.clear();
+        self.counter = self
+            .counter
+            .checked_add(1)
+            .ok_or_else(|| io::Error::other("The backup is too large"))?;
+        Ok(())
+    }
+
+    /// Seal the last chunk, which may be empty.
+    fn finish(mut self) -> io::Result<W> {
+        self.seal_chunk(true)?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> Write for SealingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        // A full chunk is sealed only once more follows, so the last chunk
+        // can be marked as such in `finish`.
+        if self.chunk.len() == CHUNK_LEN {
+            self.seal_chunk(false)?;
+        }
+        let len = cmp::min(buf.len(), CHUNK_LEN - self.chunk.len());
+        self.chunk.extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Decrypts the chunks written by a [`SealingWriter`], failing with
+/// [`OpenFailed`] on a chunk that was altered, reordered or cut off.
+struct OpeningReader<R: BufRead> {
+    inner: R,
+    key: LessSafeKey,
+    header: [u8; HEADER_LEN],
+    counter: u32,
+    chunk: Zeroizing<Vec<u8>>,
+    /// Length of the decrypted contents at the front of `chunk`.
+    len: usize,
+    pos: usize,
+    done: bool,
+}
+
+impl<R: BufRead> OpeningReader<R> {
+    fn new(inner: R, key: LessSafeKey, header: [u8; HEADER_LEN]) -> Self {
+        OpeningReader {
+            inner,
+            key,
+            header,
+            counter: 0,
+            chunk: Zeroizing::new(Vec::with_capacity(CHUNK_LEN + CHACHA20_POLY1305.tag_len())),
+            len: 0,
+            pos: 0,
+            done: false,
+        }
+    }
+
+    fn open_chunk(&mut self) -> io::Result<()> {
+        let sealed_len = CHUNK_LEN + CHACHA20_POLY1305.tag_len();
+        self.chunk.clear();
+        (&mut self.inner).take(sealed_len as u64).read_to_end(&mut self.chunk)?;
+        let last = self.chunk.len() < sealed_len || self.inner.fill_buf()?.is_empty();
+
+        let prefix = &self.header[HEADER_LEN - NONCE_PREFIX_LEN..];
+        let nonce = chunk_nonce(prefix, self.counter, last);
+        self.len = self
+            .key
+            .open_in_place(nonce, Aad::from(&self.header[..]), &mut self.chunk[..])
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, OpenFailed))?
+            .len();
+        self.pos = 0;
+        self.done = last;
+        self.counter = self.counter.checked_add(1).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, OpenFailed))?;
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for OpeningReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let len = cmp::min(buf.len(), available.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+impl<R: BufRead> BufRead for OpeningReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.pos == self.len && !self.done {
+            self.open_chunk()?;
+        }
+        Ok(&self.chunk[self.pos..self.len])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = cmp::min(self.pos + amt, self.len);
+    }
+}
+
+/// Copy exactly `len` bytes, through a buffer that is wiped afterwards.
+fn copy_exact(
+    from: &mut impl Read,
+    to: &mut impl Write,
+    len: u64,
+    on_read: impl Fn(io::Error) -> FfiError,
+    on_write: impl Fn(io::Error) -> FfiError,
+) -> Result<(), FfiError> {
+    let mut buf = Zeroizing::new(vec![0u8; 8 * 1024]);
+    let mut remaining = len;
+    while remaining > 0 {
+        let want = cmp::min(remaining, buf.len() as u64) as usize;
+        let read = match from.read(&mut buf[..want]) {
+            Ok(0) => return Err(on_read(io::ErrorKind::UnexpectedEof.into())),
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(on_read(e)),
+        };
+        to.write_all(&buf[..read]).map_err(&on_write)?;
+        remaining -= read as u64;
+    }
+    Ok(())
+}
+
+/// Write an encrypted archive of the files under `dir` to `archive`, one file
+/// at a time.
+fn pack(dir: &Path, password: &str, mut archive: impl Write) -> Result<BackupSummary, FfiError> {
+    let mut files = Vec::new();
+    collect_files(dir, "", &mut files)?;
+    if !files.iter().any(|file| file == SEED_FILE) {
+        return Err(FfiError::new(
+            ErrorCode::WalletSeedDoesntExist,
+            format!("There is no wallet in {}", dir.display()),
+        ));
+    }
+
+    let rng = SystemRandom::new();
+    let mut salt = [0u8; SALT_LEN];
+    let mut prefix = [0u8; NONCE_PREFIX_LEN];
+    rng.fill(&mut salt).and_then(|_| rng.fill(&mut prefix)).map_err(|_| backup_error("No randomness available"))?;
+
+    let mut header = [0u8; HEADER_LEN];
+    let mut offset = 0;
+    for field in [&MAGIC[..], &[FORMAT_VERSION], &KDF_ITERATIONS.to_be_bytes(), &salt, &prefix] {
+        header[offset..offset + field.len()].copy_from_slice(field);
+        offset += field.len();
+    }
+
+    let write_error = |e: io::Error| io_error("Unable to write the backup", e);
+    archive.write_all(&header).map_err(write_error)?;
+    let key = archive_key(password, &salt, KDF_ITERATIONS)?;
+    let mut encoder = DeflateEncoder::new(SealingWriter::new(archive, key, header), Compression::default());
+
+    let mut bytes = 0;
+    for name in &files {
+        let mut file = fs::File::open(dir.join(name)).map_err(|e| io_error(&format!("Unable to read {}", name), e))?;
+        let len = file
+            .metadata()
+            .map_err(|e| io_error(&format!("Unable to read {}", name), e))?
+            .len();
+        encoder
+            .write_all(&(name.len() as u16).to_be_bytes())
+            .and_then(|_| encoder.write_all(name.as_bytes()))
+            .and_then(|_| encoder.write_all(&len.to_be_bytes()))
+            .map_err(write_error)?;
+        copy_exact(&mut file, &mut encoder, len, |e| io_error(&format!("Unable to read {}", name), e), write_error)?;
+        bytes += len;
+    }
+    encoder
+        .finish()
+        .and_then(|sealing| sealing.finish())
+        .and_then(|mut archive| archive.flush())
+        .map_err(write_error)?;
+    Ok(BackupSummary { files: files.len(), bytes })
+}
+
+/// A relative archive path as a path under the restore directory, refusing
+/// anything that would land outside it.
+fn entry_path(dir: &Path, name: &str) -> Result<PathBuf, FfiError> {
+    let relative = Path::new(name);
+    if name.is_empty() || !relative.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(backup_error("Backup contains an invalid path"));
+    }
+    Ok(dir.join(relative))
+}
+
+/// Read the first byte of the next entry into `buf`, or return false at the
+/// end of the archive.
+fn read_entry_start(reader: &mut impl Read, buf: &mut [u8; 1]) -> Result<bool, FfiError> {
+    loop {
+        match reader.read(buf) {
+            Ok(read) => return Ok(read == 1),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(read_error(e)),
+        }
+    }
+}
+
+/// Decrypt an archive and write its files under `dir`, which must exist.
+/// Files are written as they are read, so a damaged archive can leave some
+/// behind.
+fn unpack(mut archive: impl BufRead, password: &str, dir: &Path) -> Result<BackupSummary, FfiError> {
+    let mut header = [0u8; HEADER_LEN];
+    archive.read_exact(&mut header).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => backup_error("Not a wallet backup"),
+        _ => io_error("Unable to read the backup", e),
+    })?;
+    if &header[..MAGIC.len()] != MAGIC {
+        return Err(backup_error("Not a wallet backup"));
+    }
+    if header[MAGIC.len()] != FORMAT_VERSION {
+        return Err(backup_error("Unsupported wallet backup version"));
+    }
+    let mut offset = MAGIC.len() + 1;
+    let iterations = u32::from_be_bytes(header[offset..offset + 4].try_into().unwrap());
+    if iterations != KDF_ITERATIONS {
+        return Err(backup_error("Unsupported wallet backup key derivation"));
+    }
+    offset += 4;
+    let salt = &header[offset..offset + SALT_LEN];
+
+    let key = archive_key(password, salt, iterations)?;
+    let mut decoder = DeflateDecoder::new(OpeningReader::new(archive, key, header));
+
+    let mut summary = BackupSummary { files: 0, bytes: 0 };
+    let mut has_seed = false;
+    let mut first = [0u8; 1];
+    while read_entry_start(&mut decoder, &mut first)? {
+        let mut name_len = [first[0], 0];
+        decoder.read_exact(&mut name_len[1..]).map_err(read_error)?;
+        let mut name = vec![0u8; u16::from_be_bytes(name_len) as usize];
+        decoder.read_exact(&mut name).map_err(read_error)?;
+        let name = String::from_utf8(name).map_err(|_| backup_error("Backup contains an invalid path"))?;
+        let mut len = [0u8; 8];
+        decoder.read_exact(&mut len).map_err(read_error)?;
+        let len = u64::from_be_bytes(len);
+
+        let path = entry_path(dir, &name)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| io_error("Unable to create the wallet directory", e))?;
+        }
+        let write_error = |e: io::Error| io_error(&format!("Unable to write {}", name), e);
+        let mut file = fs::File::create(&path).map_err(write_error)?;
+        copy_exact(&mut decoder, &mut file, len, read_error, write_error)?;
+        has_seed |= name == SEED_FILE;
+        summary.files += 1;
+        summary.bytes += len;
+    }
+    // Reaching the end of the last chunk checks that none were cut off.
+    if !decoder.into_inner().fill_buf().map_err(read_error)?.is_empty() {
+        return Err(backup_error("Backup contents are damaged"));
+    }
+    if !has_seed {
+        return Err(backup_error("Backup does not contain a wallet seed"));
+    }
+    Ok(summary)
+}
+
+/// Write an encrypted, integrity-checked archive of a wallet's directory to
+/// `archive`: its seed file, database, saved slates and everything else but
+/// logs.
+///
+/// The wallet must be closed. It is claimed for the duration of the backup,
+/// so this fails with `lifecycle` if a session has it open and with
+/// `wallet_locked` if another process does.
+pub fn backup_wallet(config: &Config, password: &str, archive: &Path) -> Result<BackupSummary, FfiError> {
+    let dir = Path::new(&config.wallet_dir);
+    let _claim = session::claim_wallet_dir(dir)?;
+
+    // Written next to the destination first, so a failed write does not
+    // leave a truncated archive behind.
+    let mut partial = archive.as_os_str().to_owned();
+    partial.push(".partial");
+    let partial = PathBuf::from(partial);
+    let write_error = |e: io::Error| io_error("Unable to write the backup", e);
+    let written = fs::File::create(&partial).map_err(write_error).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        let summary = pack(dir, password, &mut writer)?;
+        let file = writer.into_inner().map_err(|e| write_error(e.into_error()))?;
+        file.sync_all().and_then(|_| fs::rename(&partial, archive)).map_err(write_error)?;
+        Ok(summary)
+    });
+    if written.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    written
+}
+
+/// Marks a staging directory as created by [`restore_wallet`], so that one
+/// left behind by an interrupted restore can be told apart from anything else.
+pub(crate) const STAGING_MARKER: &str = ".restore-staging";
+
+/// Create the marked staging directory `<wallet_dir>.restoring`.
+///
+/// A marked one left behind by an interrupted restore is replaced; with the
+/// wallet directory claimed, no other restore into it can be in progress. An
+/// unmarked one is left alone.
+fn create_staging_dir(wallet_dir: &Path) -> Result<PathBuf, FfiError> {
+    let mut staging = wallet_dir.as_os_str().to_owned();
+    staging.push(".restoring");
+    let staging = PathBuf::from(staging);
+    if staging.join(STAGING_MARKER).is_file() {
+        let _ = fs::remove_dir_all(&staging);
+    }
+    let create_error = |e: io::Error| io_error(&format!("Unable to create {}", staging.display()), e);
+    fs::create_dir(&staging).map_err(create_error)?;
+    if let Err(e) = fs::write(staging.join(STAGING_MARKER), b"") {
+        let _ = fs::remove_dir_all(&staging);
+        return Err(create_error(e));
+    }
+    Ok(staging)
+}
+
+/// Restore a wallet backup into `wallet_dir`, which must not exist yet or be
+/// empty. Nothing is left in `wallet_dir` if the restore fails.
+///
+/// The directory is claimed for the duration of the restore, so this fails
+/// with `lifecycle` if a session has it open and with `wallet_locked` if
+/// another process does.
+pub fn restore_wallet(archive: &Path, password: &str, wallet_dir: &Path) -> Result<BackupSummary, FfiError> {
+    let archive = fs::File::open(archive).map_err(|e| io_error("Unable to read the backup", e))?;
+    // The directory must exist for its lock file to be claimed.
+    let created = !wallet_dir.exists();
+    fs::create_dir_all(wallet_dir).map_err(|e| io_error("Unable to create the wallet directory", e))?;
+
+    let restored = session::claim_wallet_dir(wallet_dir).and_then(|mut claim| {
+        let restored = restore_claimed(archive, password, wallet_dir, &mut claim);
+        if restored.is_err() {
+            let _ = claim.remove_lock_file();
+        }
+        restored
+    });
+    if restored.is_err() && created {
+        let _ = fs::remove_dir(wallet_dir);
+    }
+    restored
+}
+
+fn restore_claimed(
+    archive: fs::File,
+    password: &str,
+    wallet_dir: &Path,
+    claim: &mut session::WalletDirClaim,
+) -> Result<BackupSummary, FfiError> {
+    let not_empty = fs::read_dir(wallet_dir)
+        .map_err(|e| io_error("Unable to read the wallet directory", e))?
+        .filter_map(Result::ok)
+        .any(|entry| entry.file_name() != session::LOCK_FILE_NAME);
+    if not_empty {
+        return Err(FfiError::new(
+            ErrorCode::WalletSeedExists,
+            format!("{} is not empty", wallet_dir.display()),
+        ));
+    }
+
+    let staging = create_staging_dir(wallet_dir)?;
+    let restored = unpack(BufReader::new(archive), password, &staging).and_then(|summary| {
+        let move_error = |e: io::Error| io_error("Unable to move the restored wallet", e);
+        fs::remove_file(staging.join(STAGING_MARKER)).map_err(move_error)?;
+        // The empty target directory is replaced by the restored one.
+        claim.remove_lock_file().map_err(move_error)?;
+        fs::remove_dir(wallet_dir).map_err(move_error)?;
+        fs::rename(&staging, wallet_dir).map_err(move_error)?;
+        Ok(summary)
+    });
+    if restored.is_err() {
+        let _ = fs::remove_dir_all(&staging);
+    }
+    restored
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::fake_wallet;
+
+    /// A fake wallet with a database and a log file next to its seed.
+    fn fake_wallet_with_db(dir: &Path) {
+        fake_wallet(dir, None);
+        fs::create_dir_all(dir.join("wallet_data/db/lmdb")).unwrap();
+        fs::create_dir_all(dir.join("logs")).unwrap();
+        fs::write(dir.join("wallet_data/db/lmdb/data.mdb"), vec![7u8; 4096]).unwrap();
+        fs::write(dir.join("logs/epic-cash-wallet.log"), b"log line").unwrap();
+    }
+
+    #[test]
+    fn test_pack_and_unpack_round_trip() {
+        let source = PathBuf::from("test_backup_round_trip_source");
+        let target = PathBuf::from("test_backup_round_trip_target");
+        fake_wallet_with_db(&source);
+        let _ = fs::remove_dir_all(&target);
+        fs::create_dir_all(&target).unwrap();
+
+        let mut archive = Vec::new();
+        let packed = pack(&source, "backup password", &mut archive).unwrap();
+        assert_eq!(packed.files, 2);
+        let unpacked = unpack(&archive[..], "backup password", &target).unwrap();
+        assert_eq!(unpacked.files, 2);
+        assert_eq!(unpacked.bytes, packed.bytes);
+        assert_eq!(fs::read(target.join(SEED_FILE)).unwrap(), b"encrypted seed");
+        assert!(!target.join("logs").exists());
+
+        let _ = fs::remove_dir_all(&source);
+        let _ = fs::remove_dir_all(&target);
+    }
+
+    #[test]
+    fn test_unpack_rejects_wrong_password_and_tampering() {
+        let source = PathBuf::from("test_backup_tamper_source");
+        let target = PathBuf::from("test_backup_tamper_target");
+        fake_wallet_with_db(&source);
+        let _ = fs::remove_dir_all(&target);
+        fs::create_dir_all(&target).unwrap();
+
+        let mut archive = Vec::new();
+        pack(&source, "backup password", &mut archive).unwrap();
+        assert_eq!(unpack(&archive[..], "other password", &target).unwrap_err().code, ErrorCode::WrongPassword);
+
+        let last = archive.len() - 1;
+        archive[last] ^= 1;
+        assert_eq!(unpack(&archive[..], "backup password", &target).unwrap_err().code, ErrorCode::WrongPassword);
+        assert_eq!(unpack(&b"not a backup"[..], "backup password", &target).unwrap_err().code, ErrorCode::InvalidArgument);
+        assert!(fs::read_dir(&target).unwrap().next().is_none());
+
+        let _ = fs::remove_dir_all(&source);
+        let _ = fs::remove_dir_all(&target);
+    }
+
+    #[test]
+    fn test_unpack_checks_every_chunk() {
+        let source = PathBuf::from("test_backup_chunks_source");
+        let target = PathBuf::from("test_backup_chunks_target");
+        fake_wallet_with_db(&source);
+        let _ = fs::remove_dir_all(&target);
+        fs::create_dir_all(&target).unwrap();
+
+        // Random contents do not compress, so they span several chunks.
+        let mut large = vec![0u8; 3 * CHUNK_LEN];
+        SystemRandom::new().fill(&mut large).unwrap();
+        fs::write(source.join("wallet_data/large.bin"), &large).unwrap();
+        let mut archive = Vec::new();
+        pack(&source, "backup password", &mut archive).unwrap();
+        assert!(archive.len() > HEADER_LEN + 3 * CHUNK_LEN);
+
+        unpack(&archive[..], "backup password", &target).unwrap();
+        assert_eq!(fs::read(target.join("wallet_data/large.bin")).unwrap(), large);
+
+        // Dropping the last chunk is noticed, even at a chunk boundary.
+        let sealed_len = CHUNK_LEN + CHACHA20_POLY1305.tag_len();
+        let cut = HEADER_LEN + sealed_len * ((archive.len() - HEADER_LEN - 1) / sealed_len);
+        let _ = fs::remove_dir_all(&target);
+        fs::create_dir_all(&target).unwrap();
+        assert_eq!(unpack(&archive[..cut], "backup password", &target).unwrap_err().code, ErrorCode::WrongPassword);
+
+        // So is an iteration count other than the one written.
+        let mut slow = archive.clone();
+        slow[MAGIC.len() + 1..MAGIC.len() + 5].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_eq!(unpack(&slow[..], "backup password", &target).unwrap_err().code, ErrorCode::InvalidArgument);
+
+        let _ = fs::remove_dir_all(&source);
+        let _ = fs::remove_dir_all(&target);
+    }
+
+    #[test]
+    fn test_entry_path_stays_inside_the_target() {
+        let dir = Path::new("restore");
+        assert_eq!(entry_path(dir, "wallet_data/wallet.seed").unwrap(), dir.join("wallet_data/wallet.seed"));
+        assert!(entry_path(dir, "../outside").is_err());
+        assert!(entry_path(dir, "/etc/passwd").is_err());
+        assert!(entry_path(dir, "").is_err());
+    }
+}
@@ -28,17 +28,31 @@ impl Config {
         };
         Ok(result)
     }
+
+    /// Name of the chain the wallet is on. Unknown names fall back to floonet.
+    pub fn chain_name(&self) -> &'static str {
+        match self.chain.as_ref() {
+            "mainnet" => "mainnet",
+            "usertesting" => "usertesting",
+            "automatedtesting" => "automatedtesting", // TODO: Use for tests.
+            _ => "floonet",
+        }
+    }
+
+    /// The chain the wallet is on.
+    pub fn chain_type(&self) -> ChainTypes {
+        match self.chain_name() {
+            "mainnet" => ChainTypes::Mainnet,
+            "usertesting" => ChainTypes::UserTesting,
+            "automatedtesting" => ChainTypes::AutomatedTesting,
+            _ => ChainTypes::Floonet,
+        }
+    }
 }
 
 /// Create a wallet config.
 pub fn create_wallet_config(config: Config) -> Result<WalletConfig, Error> {
-    let chain_type = match config.chain.as_ref() {
-        "mainnet" => ChainTypes::Mainnet,
-        "floonet" => ChainTypes::Floonet,
-        "usertesting" => ChainTypes::UserTesting,
-        "automatedtesting" => ChainTypes::AutomatedTesting, // TODO: Use for tests.
-        _ => ChainTypes::Floonet,
-    };
+    let chain_type = config.chain_type();
 
     let api_secret_path = config.wallet_dir.clone() + "/.api_secret";
     let api_listen_port = config.api_listen_port;
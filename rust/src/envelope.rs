@@ -0,0 +1,300 @@
+use std::fmt;
+
+use serde::Serialize;
+use serde_json::Value;
+use zeroize::Zeroizing;
+
+use epic_wallet_libwallet::Error;
+
+/// Version of the result envelope layout returned by every FFI export.
+///
+/// Bump this when the shape of [`Envelope`] changes in a way callers can observe.
+pub const ENVELOPE_VERSION: u32 = 1;
+
+/// Stable, machine-readable error codes.
+///
+/// The numeric `code` and the string `kind` of a variant never change once released;
+/// new variants may be added.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorCode {
+    Generic,
+    Wallet,
+    Panic,
+    Cancelled,
+    InvalidArgument,
+    InvalidConfig,
+    InvalidHandle,
+    NotEnoughFunds,
+    WalletSeedDoesntExist,
+    WalletSeedExists,
+    WrongPassword,
+    InvalidKeychainMask,
+    UnknownAccount,
+    AccountExists,
+    WalletLocked,
+    SessionLocked,
+    NodeUnreachable,
+    InvalidSlate,
+    TransactionNotFound,
+    TransactionNotCancellable,
+    TransactionAlreadyReceived,
+    Backend,
+    Lifecycle,
+}
+
+impl ErrorCode {
+    /// Numeric code of this error.
+    pub fn code(&self) -> u32 {
+        match self {
+            ErrorCode::Generic => 1,
+            ErrorCode::Wallet => 2,
+            ErrorCode::Panic => 3,
+            ErrorCode::Cancelled => 4,
+            ErrorCode::InvalidArgument => 10,
+            ErrorCode::InvalidConfig => 11,
+            ErrorCode::InvalidHandle => 12,
+            ErrorCode::NotEnoughFunds => 100,
+            ErrorCode::WalletSeedDoesntExist => 101,
+            ErrorCode::WalletSeedExists => 102,
+            ErrorCode::WrongPassword => 103,
+            ErrorCode::InvalidKeychainMask => 104,
+            ErrorCode::UnknownAccount => 105,
+            ErrorCode::AccountExists => 106,
+            ErrorCode::WalletLocked => 107,
+            ErrorCode::SessionLocked => 108,
+            ErrorCode::NodeUnreachable => 110,
+            ErrorCode::InvalidSlate => 120,
+            ErrorCode::TransactionNotFound => 130,
+            ErrorCode::TransactionNotCancellable => 131,
+            ErrorCode::TransactionAlreadyReceived => 132,
+            ErrorCode::Backend => 140,
+            ErrorCode::Lifecycle => 141,
+        }
+    }
+
+    /// String kind of this error.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ErrorCode::Generic => "generic",
+            ErrorCode::Wallet => "wallet",
+            ErrorCode::Panic => "panic",
+            ErrorCode::Cancelled => "cancelled",
+            ErrorCode::InvalidArgument => "invalid_argument",
+            ErrorCode::InvalidConfig => "invalid_config",
+            ErrorCode::InvalidHandle => "invalid_handle",
+            ErrorCode::NotEnoughFunds => "not_enough_funds",
+            ErrorCode::WalletSeedDoesntExist => "wallet_seed_doesnt_exist",
+            ErrorCode::WalletSeedExists => "wallet_seed_exists",
+            ErrorCode::WrongPassword => "wrong_password",
+            ErrorCode::InvalidKeychainMask => "invalid_keychain_mask",
+            ErrorCode::UnknownAccount => "unknown_account",
+            ErrorCode::AccountExists => "account_exists",
+            ErrorCode::WalletLocked => "wallet_locked",
+            ErrorCode::SessionLocked => "session_locked",
+            ErrorCode::NodeUnreachable => "node_unreachable",
+            ErrorCode::InvalidSlate => "invalid_slate",
+            ErrorCode::TransactionNotFound => "transaction_not_found",
+            ErrorCode::TransactionNotCancellable => "transaction_not_cancellable",
+            ErrorCode::TransactionAlreadyReceived => "transaction_already_received",
+            ErrorCode::Backend => "backend",
+            ErrorCode::Lifecycle => "lifecycle",
+        }
+    }
+}
+
+/// Map a wallet error onto its stable code.
+impl From<&Error> for ErrorCode {
+    fn from(err: &Error) -> Self {
+        match err {
+            Error::NotEnoughFunds { .. } => ErrorCode::NotEnoughFunds,
+            Error::WalletSeedDoesntExist { .. } => ErrorCode::WalletSeedDoesntExist,
+            Error::WalletSeedExists { .. } => ErrorCode::WalletSeedExists,
+            Error::WalletSeedDecryption { .. } => ErrorCode::WrongPassword,
+            Error::InvalidKeychainMask { .. } => ErrorCode::InvalidKeychainMask,
+            Error::UnknownAccountLabel { .. } => ErrorCode::UnknownAccount,
+            Error::AccountLabelAlreadyExists { .. } => ErrorCode::AccountExists,
+            Error::ClientCallback { .. } => ErrorCode::NodeUnreachable,
+            Error::SlateDeser { .. }
+            | Error::SlateVersion { .. }
+            | Error::SlateVersionParse { .. } => ErrorCode::InvalidSlate,
+            Error::TransactionDoesntExist { .. } => ErrorCode::TransactionNotFound,
+            Error::TransactionNotCancellable { .. } => ErrorCode::TransactionNotCancellable,
+            Error::TransactionAlreadyReceived { .. } => ErrorCode::TransactionAlreadyReceived,
+            Error::Backend { .. } => ErrorCode::Backend,
+            Error::Lifecycle { .. } => ErrorCode::Lifecycle,
+            Error::GenericError { .. } => ErrorCode::Generic,
+            _ => ErrorCode::Wallet,
+        }
+    }
+}
+
+/// An error as it crosses the FFI boundary.
+#[derive(Debug, Clone)]
+pub struct FfiError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub details: Option<Value>,
+}
+
+impl FfiError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        FfiError {
+            code,
+            message: message.into(),
+            details: None,
+        }
+    }
+}
+
+impl fmt::Display for FfiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code.kind(), self.message)
+    }
+}
+
+impl From<Error> for FfiError {
+    fn from(err: Error) -> Self {
+        let details = match &err {
+            Error::NotEnoughFunds { available, needed, .. } => Some(serde_json::json!({
+                "available": available,
+                "needed": needed,
+            })),
+            _ => None,
+        };
+        FfiError {
+            code: ErrorCode::from(&err),
+            message: err.to_string(),
+            details,
+        }
+    }
+}
+
+impl From<serde_json::Error> for FfiError {
+    fn from(err: serde_json::Error) -> Self {
+        FfiError::new(ErrorCode::InvalidArgument, err.to_string())
+    }
+}
+
+/// Error body of a failed envelope.
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    code: u32,
+    kind: &'static str,
+    message: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<&'a Value>,
+}
+
+/// Result envelope returned by every FFI export.
+///
+/// Success: `{"v":1,"ok":true,"data":...}`
+/// Failure: `{"v":1,"ok":false,"error":{"code":...,"kind":...,"message":...}}`
+#[derive(Serialize)]
+struct Envelope<'a> {
+    v: u32,
+    ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<&'a Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<ErrorBody<'a>>,
+}
+
+/// Serialize a result into its envelope JSON.
+pub fn to_json<E: Into<FfiError>>(result: Result<Value, E>) -> String {
+    let result = result.map_err(Into::into);
+    let envelope = match &result {
+        Ok(data) => Envelope {
+            v: ENVELOPE_VERSION,
+            ok: true,
+            data: Some(data),
+            error: None,
+        },
+        Err(err) => Envelope {
+            v: ENVELOPE_VERSION,
+            ok: false,
+            data: None,
+            error: Some(ErrorBody {
+                code: err.code.code(),
+                kind: err.code.kind(),
+                message: &err.message,
+                details: err.details.as_ref(),
+            }),
+        },
+    };
+    // Serializing a `Value` tree cannot fail.
+    serde_json::to_string(&envelope).unwrap()
+}
+
+/// Serialize a successful result whose data is a secret string, such as a
+/// mnemonic, into its envelope JSON.
+///
+/// The JSON is built in one zeroizing buffer with room left for a C string
+/// terminator, so turning it into a C string leaves no copy of the secret
+/// behind. Only secrets that need no JSON escaping are accepted.
+pub fn secret_to_json(secret: &str) -> Result<Zeroizing<String>, FfiError> {
+    if secret.chars().any(|c| c == '"' || c == '\\' || c.is_control()) {
+        return Err(FfiError::new(ErrorCode::Generic, "Secret cannot be returned without escaping"));
+    }
+    let prefix = format!("{{\"v\":{},\"ok\":true,\"data\":\"", ENVELOPE_VERSION);
+    let mut json = Zeroizing::new(String::with_capacity(prefix.len() + secret.len() + 3));
+    json.push_str(&prefix);
+    json.push_str(secret);
+    json.push_str("\"}");
+    Ok(json)
+}
+
+#[cfg(test)]
+mod envelope_tests {
+    use super::*;
+
+    #[test]
+    fn test_ok_envelope_shape() {
+        let json = to_json::<FfiError>(Ok(serde_json::json!({"total": 1})));
+        let parsed: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed["v"], ENVELOPE_VERSION);
+        assert_eq!(parsed["ok"], true);
+        assert_eq!(parsed["data"]["total"], 1);
+        assert!(parsed.get("error").is_none());
+    }
+
+    #[test]
+    fn test_error_envelope_shape() {
+        let json = to_json::<Error>(Err(Error::WalletSeedDoesntExist));
+        let parsed: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed["ok"], false);
+        assert!(parsed.get("data").is_none());
+        assert_eq!(parsed["error"]["code"], ErrorCode::WalletSeedDoesntExist.code());
+        assert_eq!(parsed["error"]["kind"], "wallet_seed_doesnt_exist");
+    }
+
+    #[test]
+    fn test_secret_envelope_matches_plain_envelope() {
+        let phrase = "abandon ability able";
+        let json = secret_to_json(phrase).unwrap();
+        assert_eq!(*json, to_json::<FfiError>(Ok(Value::String(phrase.into()))));
+        assert_eq!(json.capacity(), json.len() + 1);
+        assert!(secret_to_json("quote\"d").is_err());
+    }
+
+    #[test]
+    fn test_payload_containing_error_is_still_ok() {
+        // A note mentioning "error" must not be mistaken for a failure.
+        let json = to_json::<FfiError>(Ok(Value::String("Error: refund for order 12".into())));
+        let parsed: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed["ok"], true);
+    }
+
+    #[test]
+    fn test_not_enough_funds_details() {
+        let err = Error::NotEnoughFunds {
+            available: 10,
+            available_disp: "0.00000010".into(),
+            needed: 20,
+            needed_disp: "0.00000020".into(),
+        };
+        let parsed: Value = serde_json::from_str(&to_json::<Error>(Err(err))).unwrap();
+        assert_eq!(parsed["error"]["kind"], "not_enough_funds");
+        assert_eq!(parsed["error"]["details"]["available"], 10);
+        assert_eq!(parsed["error"]["details"]["needed"], 20);
+    }
+}
@@ -1,39 +1,60 @@
+use std::any::Any;
 use std::ffi::{c_void, CStr, CString};
+use std::fmt::Display;
 use std::os::raw::c_char;
-
+use std::path::{Path, PathBuf};
+use std::panic::{self, AssertUnwindSafe};
+use std::mem;
+use std::ptr;
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde_json::Value;
 use uuid::Uuid;
+use zeroize::{Zeroize, Zeroizing};
 
 use epic_wallet_api::{self, Owner};
 use epic_wallet_config::EpicboxConfig;
-use epic_wallet_libwallet::Error;
-use epic_wallet_controller::Error as EpicWalletControllerError;
+use epic_wallet_libwallet::{Error, StatusMessage};
 use std::sync::Arc;
+use std::sync::mpsc::Sender;
 use std::sync::atomic::AtomicBool;
 
 use epic_util::secp::key::SecretKey;
+use epic_util::ZeroingString;
 
+use crate::backup;
 use crate::config::Config;
+use crate::envelope::{self, ErrorCode, FfiError};
+use crate::info;
+use crate::logging::{self, LogCallback, LogConfig};
+use crate::manager;
+use crate::rpc;
+use crate::session::{self, SessionId};
 
 use crate::mnemonic::mnemonic;
-use crate::mnemonic::create_seed;
-use crate::mnemonic::_get_mnemonic;
 
 use crate::wallet::Wallet;
 use crate::wallet::create_wallet;
 use crate::wallet::recover_from_mnemonic;
 use crate::wallet::open_wallet;
+use crate::wallet::close_wallet;
+use crate::wallet::change_password;
+use crate::wallet::reveal_mnemonic;
+use crate::wallet::verify_password;
+use crate::wallet::{record_last_confirmed_height, wallet_status};
 use crate::wallet::get_wallet_info;
 use crate::wallet::validate_address;
-use crate::wallet::wallet_scan_outputs;
-use crate::wallet::tx_strategies;
+use crate::wallet::{wallet_scan_outputs, ScanEnd};
+use crate::wallet::{max_sendable, tx_strategies, SendAmount};
 use crate::wallet::tx_create;
 use crate::wallet::txs_get;
 use crate::wallet::tx_cancel;
 use crate::wallet::tx_receive;
 use crate::wallet::tx_finalize;
-use crate::wallet::delete_wallet;
 use crate::wallet::tx_send_http;
 use crate::wallet::get_chain_height;
+use crate::wallet::{accounts, create_account, set_active_account};
 
 use crate::listener::Listener;
 use crate::listener::listener_spawn;
@@ -41,10 +62,103 @@ use crate::listener::listener_cancel;
 use crate::listener::listener_cancelled;
 use crate::listener::listener_handle_destroy;
 use crate::listener::listener_poll;
-use crate::init_logger;
+use crate::tasks::{Operation, WalletTask, WalletTaskHandle};
 
 use ffi_helpers::task::TaskHandle;
 
+/// Hand a result to the caller as a JSON envelope.
+///
+/// See [`crate::envelope`] for the layout. The caller owns the returned string
+/// and must release it with [`rust_string_free`].
+fn envelope_ptr<E: Into<FfiError>>(result: Result<Value, E>) -> *const c_char {
+    // Envelope JSON never contains interior NULs; serde escapes control characters.
+    let s = CString::new(envelope::to_json(result)).unwrap();
+    s.into_raw()
+}
+
+/// Run the body of an export, turning a panic into a `panic` error envelope.
+///
+/// Unwinding across `extern "C"` aborts the host process, so every export that
+/// returns an envelope goes through here.
+fn guard<F>(body: F) -> *const c_char
+where
+    F: FnOnce() -> Result<Value, FfiError>,
+{
+    let result = panic::catch_unwind(AssertUnwindSafe(body))
+        .unwrap_or_else(|payload| Err(panic_error(payload)));
+    envelope_ptr(result)
+}
+
+/// Describe a caught panic.
+pub(crate) fn panic_error(payload: Box<dyn Any + Send>) -> FfiError {
+    let message = if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic".to_string()
+    };
+    error!("FFI call panicked: {}", message);
+    FfiError::new(ErrorCode::Panic, message)
+}
+
+/// Hand a secret string to the caller without leaving a copy of it behind.
+///
+/// The buffer must have room for the terminator, as the ones built by
+/// [`envelope::secret_to_json`] do, so the C string takes it over as it is.
+pub(crate) fn secret_into_raw(mut secret: Zeroizing<String>) -> *mut c_char {
+    debug_assert!(secret.capacity() > secret.len());
+    // Secrets are checked for control characters, so there is no interior NUL.
+    CString::new(mem::take(&mut *secret)).unwrap().into_raw()
+}
+
+/// Borrow a string argument, rejecting null and invalid UTF-8.
+pub(crate) unsafe fn arg_str<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, FfiError> {
+    if ptr.is_null() {
+        return Err(FfiError::new(
+            ErrorCode::InvalidArgument,
+            format!("{} is null", name),
+        ));
+    }
+    CStr::from_ptr(ptr).to_str().map_err(|e| FfiError::new(
+        ErrorCode::InvalidArgument,
+        format!("{} is not valid UTF-8: {}", name, e),
+    ))
+}
+
+/// Parse a string argument, such as a decimal amount or a session id.
+unsafe fn arg_parse<T>(ptr: *const c_char, name: &str) -> Result<T, FfiError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let s = arg_str(ptr, name)?;
+    s.parse().map_err(|e| FfiError::new(
+        ErrorCode::InvalidArgument,
+        format!("{} '{}' is invalid: {}", name, s, e),
+    ))
+}
+
+/// Parse a decimal flag argument; any non-zero value is `true`.
+unsafe fn arg_flag(ptr: *const c_char, name: &str) -> Result<bool, FfiError> {
+    Ok(arg_parse::<u64>(ptr, name)? != 0)
+}
+
+/// Free a string returned by any export of this library.
+///
+/// Every `const char *` returned by this library is owned by the caller and must
+/// be passed back here exactly once. The bytes are zeroed before being released,
+/// since results may carry mnemonics or slates. Passing null is a no-op; passing
+/// a pointer not obtained from this library is undefined behaviour.
+#[no_mangle]
+pub unsafe extern "C" fn rust_string_free(s: *mut c_char) {
+    if s.is_null() {
+        return;
+    }
+    let mut bytes = CString::from_raw(s).into_bytes_with_nul();
+    bytes.zeroize();
+}
+
 /// Initialize a new wallet via FFI.
 #[no_mangle]
 pub unsafe extern "C" fn wallet_init(
@@ -53,87 +167,74 @@ pub unsafe extern "C" fn wallet_init(
     password: *const c_char,
     name: *const c_char
 ) -> *const c_char {
-
-    let result = match _wallet_init(config, mnemonic, password, name) {
-        Ok(created) => {
-            created
-        }, Err(e ) => {
-            let error_msg = format!("Error {}", &e.to_string());
-            let error_msg_ptr = CString::new(error_msg).unwrap();
-            let ptr = error_msg_ptr.as_ptr();
-            std::mem::forget(error_msg_ptr);
-            ptr
-        }
-    };
-    result
+    guard(|| {
+        let config = arg_str(config, "config")?;
+        let mnemonic = arg_str(mnemonic, "mnemonic")?;
+        let password = arg_str(password, "password")?;
+        let name = arg_str(name, "name")?;
+        Ok(_wallet_init(config, mnemonic, password, name)?)
+    })
 }
 
 /// Get a new mnemonic.
 #[no_mangle]
 pub unsafe extern "C" fn get_mnemonic() -> *const c_char {
-    let result = match _get_mnemonic() {
-        Ok(phrase) => {
-            phrase
-        }, Err(e ) => {
-            let error_msg = format!("Error {}", &e.to_string());
-            let error_msg_ptr = CString::new(error_msg).unwrap();
-            let ptr = error_msg_ptr.as_ptr();
-            std::mem::forget(error_msg_ptr);
-            ptr
-        }
-    };
-    result
+    guard(|| {
+        mnemonic()
+            .map(Value::String)
+            .map_err(|e| FfiError::new(ErrorCode::Generic, e.to_string()))
+    })
 }
 
-/// A helper to initialize a new wallet.
-fn _wallet_init(
-    config: *const c_char,
-    mnemonic: *const c_char,
-    password: *const c_char,
-    name: *const c_char
-) -> Result<*const c_char, Error> {
-
-    let config = unsafe { CStr::from_ptr(config) };
-    let mnemonic = unsafe { CStr::from_ptr(mnemonic) };
-    let password = unsafe { CStr::from_ptr(password) };
-    let name = unsafe { CStr::from_ptr(name) };
-
-    let str_password = match password.to_str() {
-        Ok(str_pass) => {str_pass}, Err(e) => {return Err(
-            Error::GenericError(e.to_string())
-        )}
-    };
+/// Describe this build of the library via FFI.
+///
+/// Returns the crate version, the pinned epic dependency revisions, supported
+/// slate versions, enabled cargo features and available transports.
+#[no_mangle]
+pub unsafe extern "C" fn rust_lib_info() -> *const c_char {
+    guard(info::lib_info)
+}
 
-    let str_config = match config.to_str() {
-        Ok(str_conf) => {str_conf}, Err(e) => {return Err(
-            Error::GenericError(e.to_string())
-        )}
-    };
+/// Configure logging via FFI.
+///
+/// `config` is a JSON object with optional `level` (`off`, `error`, `warn`,
+/// `info`, `debug` or `trace`), `wallet_dir` (log files go to `logs` under it),
+/// `max_file_size`, `max_files` and `stderr`. Passwords, mnemonics, keychain
+/// masks and other secrets are redacted from every sink. Returns the path of
+/// the log file, or null if none is written.
+#[no_mangle]
+pub unsafe extern "C" fn rust_logger_init(config: *const c_char) -> *const c_char {
+    guard(|| {
+        let config = arg_str(config, "config")?;
+        _logger_init(config)
+    })
+}
 
-    let phrase = match mnemonic.to_str() {
-        Ok(str_phrase) => {str_phrase}, Err(e) => {return Err(
-            Error::GenericError(e.to_string())
-        )}
-    };
+/// A helper to configure logging.
+pub(crate) fn _logger_init(config: &str) -> Result<Value, FfiError> {
+    let config: LogConfig = serde_json::from_str(config).map_err(|e| {
+        FfiError::new(ErrorCode::InvalidConfig, format!("Logger config error : {}", e))
+    })?;
+    let path = logging::configure(config)?;
+    Ok(path.map_or(Value::Null, |p| Value::String(p.to_string_lossy().into_owned())))
+}
 
-    let str_name = match name.to_str() {
-        Ok(str_name) => {str_name}, Err(e) => {return Err(
-            Error::GenericError(e.to_string())
-        )}
-    };
+/// Set the callback that receives every redacted log line, or clear it with
+/// null. The callback may be called from any thread, and must not block.
+#[no_mangle]
+pub unsafe extern "C" fn rust_logger_set_callback(callback: LogCallback) {
+    logging::set_callback(callback);
+}
 
-    let mut create_msg = "".to_string();
-    match create_wallet(str_config, phrase, str_password, str_name) {
-        Ok(_) => {
-            create_msg.push_str("");
-        },Err(e) => {
-            return Err(e);
-        }
-    }
-    let s = CString::new(create_msg).unwrap();
-    let p = s.as_ptr();
-    std::mem::forget(s); // Give up the responsibility of cleaning up/freeing s.
-    Ok(p)
+/// A helper to initialize a new wallet.
+fn _wallet_init(
+    config: &str,
+    mnemonic: &str,
+    password: &str,
+    name: &str
+) -> Result<Value, Error> {
+    create_wallet(config, mnemonic, password, name)?;
+    Ok(Value::String("".to_string()))
 }
 
 /// Open a wallet via FFI.
@@ -142,54 +243,36 @@ pub unsafe extern "C"  fn rust_open_wallet(
     config: *const c_char,
     password: *const c_char,
 ) -> *const c_char {
-    init_logger();
-    let result = match _open_wallet(
-        config,
-        password
-    ) {
-        Ok(wallet) => {
-            wallet
-        }, Err(e ) => {
-            let error_msg = format!("Error {}", &e.to_string());
-            let error_msg_ptr = CString::new(error_msg).unwrap();
-            let ptr = error_msg_ptr.as_ptr();
-            std::mem::forget(error_msg_ptr);
-            ptr
-        }
-    };
-    result
+    logging::init();
+    guard(|| {
+        let config = arg_str(config, "config")?;
+        let password = arg_str(password, "password")?;
+        Ok(_open_wallet(config, password)?)
+    })
 }
 
 /// A helper to open a wallet.
-fn _open_wallet(
-    config: *const c_char,
-    password: *const c_char,
-) -> Result<*const c_char, Error> {
-    let c_conf = unsafe { CStr::from_ptr(config) };
-    let c_password = unsafe { CStr::from_ptr(password) };
-
-    let str_config = c_conf.to_str().unwrap();
-    let str_password = c_password.to_str().unwrap();
-
-    let mut result = String::from("");
-    match open_wallet(&str_config, str_password) {
-        Ok(res) => {
-            let wlt = res.0;
-            let sek_key = res.1;
-            let wallet_int = Box::into_raw(Box::new(wlt)) as i64;
-            let wallet_data = (wallet_int, sek_key);
-            let wallet_ptr = serde_json::to_string(&wallet_data).unwrap();
-            result.push_str(&wallet_ptr);
-        }
-        Err(err) => {
-            return Err(err);
+pub(crate) fn _open_wallet(
+    config: &str,
+    password: &str,
+) -> Result<Value, FfiError> {
+    let wallet_config = match Config::from_str(config) {
+        Ok(config) => {
+            config
+        }, Err(err) => {
+            return Err(FfiError::new(
+                ErrorCode::InvalidConfig,
+                format!("Wallet config error : {}", err),
+            ))
         }
     };
-
-    let s = CString::new(result).unwrap();
-    let p = s.as_ptr();
-    std::mem::forget(s); // Give up the responsibility of cleaning up/freeing s.
-    Ok(p)
+    // Claimed before opening, so the same wallet is never opened twice at once;
+    // a wallet another process has open fails here with `wallet_locked`.
+    let claim = session::claim_wallet_dir(Path::new(&wallet_config.wallet_dir))?;
+    let (wlt, sek_key) = open_wallet(config, password)?;
+    // The keychain mask never leaves Rust; the caller only gets the session id.
+    let session_id = claim.open_session(wlt, sek_key);
+    Ok(Value::from(session_id))
 }
 
 /// Get wallet balances via FFI.
@@ -199,78 +282,46 @@ pub unsafe extern "C"  fn rust_wallet_balances(
     refresh: *const c_char,
     min_confirmations: *const c_char,
 ) -> *const c_char {
-    let wallet_ptr = CStr::from_ptr(wallet);
-    let c_refresh = CStr::from_ptr(refresh);
-    let minimum_confirmations = CStr::from_ptr(min_confirmations);
-    let minimum_confirmations: u64 = minimum_confirmations.to_str().unwrap().to_string().parse().unwrap();
-
-    let refresh_from_node: u64 = c_refresh.to_str().unwrap().to_string().parse().unwrap();
-    let refresh = match refresh_from_node {
-        0 => false,
-        _=> true
-    };
-
-    let wallet_data = wallet_ptr.to_str().unwrap();
-    let tuple_wallet_data: (i64, Option<SecretKey>) = serde_json::from_str(wallet_data).unwrap();
-    let wlt = tuple_wallet_data.0;
-    let sek_key = tuple_wallet_data.1;
-
-    ensure_wallet!(wlt, wallet);
-
-    let result = match _wallet_balances(
-        wallet,
-        sek_key,
-        refresh,
-        minimum_confirmations
-    ) {
-        Ok(balances) => {
-            balances
-        }, Err(e ) => {
-            let error_msg = format!("Error {}", &e.to_string());
-            let error_msg_ptr = CString::new(error_msg).unwrap();
-            let ptr = error_msg_ptr.as_ptr();
-            std::mem::forget(error_msg_ptr);
-            ptr
-        }
-    };
-    result
+    guard(|| {
+        let session_id: SessionId = arg_parse(wallet, "wallet")?;
+        let refresh = arg_flag(refresh, "refresh")?;
+        let minimum_confirmations: u64 = arg_parse(min_confirmations, "min_confirmations")?;
+
+        ensure_wallet!(session_id, wallet, sek_key, status_send, stop);
+
+        stop.finish(_wallet_balances(
+            wallet,
+            sek_key,
+            refresh,
+            minimum_confirmations,
+            status_send,
+            stop.flag(),
+        ))
+    })
 }
 
 /// A helper to get wallet balances.
-fn _wallet_balances(
+pub(crate) fn _wallet_balances(
     wallet: &Wallet,
     keychain_mask: Option<SecretKey>,
     refresh: bool,
     min_confirmations: u64,
-) -> Result<*const c_char, Error> {
-    // Print arguments for debugging/test-vector use.
-    println!(
-        ">> _wallet_balances called with refresh={refresh}, min_confirmations={min_confirmations}"
-    );
-
-    let mut wallet_info_str = String::new();
+    status_send: Option<Sender<StatusMessage>>,
+    is_stopped: Arc<AtomicBool>,
+) -> Result<Value, Error> {
+    debug!("_wallet_balances called with refresh={refresh}, min_confirmations={min_confirmations}");
 
     // Call get_wallet_info under the hood.
-    match get_wallet_info(wallet, keychain_mask, refresh, min_confirmations) {
+    match get_wallet_info(wallet, keychain_mask, refresh, min_confirmations, status_send, is_stopped) {
         Ok(info) => {
-            // Print intermediate data
-            println!(">> _wallet_balances got info: {:?}", info);
-
-            // Convert to JSON.
-            let str_wallet_info = serde_json::to_string(&info).unwrap();
-            wallet_info_str.push_str(&str_wallet_info);
+            debug!("_wallet_balances got info: {:?}", info);
+            Ok(serde_json::to_value(&info)?)
         }
         Err(e) => {
-            println!(">> _wallet_balances encountered error: {e}");
-            return Err(e);
+            error!("_wallet_balances encountered error: {e}");
+            Err(e)
         }
     }
-
-    // Convert final string result into a *const c_char.
-    let s = CString::new(wallet_info_str).unwrap();
-    let p = s.as_ptr();
-    std::mem::forget(s); // Hand off responsibility to caller.
-    Ok(p)
 }
 
 /// Recover a wallet from a mnemonic via FFI.
@@ -281,147 +332,104 @@ pub unsafe extern "C"  fn rust_recover_from_mnemonic(
     mnemonic: *const c_char,
     name: *const c_char
 ) -> *const c_char {
-
-    let result = match _recover_from_mnemonic(
-        config,
-        password,
-        mnemonic,
-        name
-    ) {
-        Ok(recovered) => {
-            recovered
-        }, Err(e ) => {
-            let error_msg = format!("Error {}", &e.to_string());
-            let error_msg_ptr = CString::new(error_msg).unwrap();
-            let ptr = error_msg_ptr.as_ptr();
-            std::mem::forget(error_msg_ptr);
-            ptr
-        }
-    };
-    result
+    guard(|| {
+        let config = arg_str(config, "config")?;
+        let password = arg_str(password, "password")?;
+        let mnemonic = arg_str(mnemonic, "mnemonic")?;
+        let name = arg_str(name, "name")?;
+        _recover_from_mnemonic(config, password, mnemonic, name)
+    })
 }
 
 /// A helper to recover a wallet from a mnemonic.
 fn _recover_from_mnemonic(
-    config: *const c_char,
-    password: *const c_char,
-    mnemonic: *const c_char,
-    name: *const c_char
-) -> Result<*const c_char, Error> {
-    let c_conf = unsafe { CStr::from_ptr(config) };
-    let c_password = unsafe { CStr::from_ptr(password) };
-    let c_mnemonic = unsafe { CStr::from_ptr(mnemonic) };
-    let c_name = unsafe { CStr::from_ptr(name) };
-
-    let input_conf = c_conf.to_str().unwrap();
-    let str_password = c_password.to_str().unwrap();
-    let wallet_config = match Config::from_str(&input_conf.to_string()) {
+    config: &str,
+    password: &str,
+    mnemonic: &str,
+    name: &str
+) -> Result<Value, FfiError> {
+    let wallet_config = match Config::from_str(config) {
         Ok(config) => {
             config
         }, Err(err) => {
-            return Err(Error::GenericError(format!(
-                "Wallet config error : {}",
-                err.to_string()
-            )))
+            return Err(FfiError::new(
+                ErrorCode::InvalidConfig,
+                format!("Wallet config error : {}", err),
+            ))
         }
     };
-    let phrase = c_mnemonic.to_str().unwrap();
-    let name = c_name.to_str().unwrap();
 
-    let mut recover_response = "".to_string();
-    match recover_from_mnemonic(phrase, str_password, &wallet_config, name) {
-        Ok(_)=> {
-            recover_response.push_str("recovered");
-        },
-        Err(e)=> {
-            return Err(e);
-        }
-    }
-    let s = CString::new(recover_response).unwrap();
-    let p = s.as_ptr();
-    std::mem::forget(s); // Give up the responsibility of cleaning up/freeing s.
-    Ok(p)
+    recover_from_mnemonic(mnemonic, password, &wallet_config, name)?;
+    Ok(Value::String("recovered".to_string()))
 }
 
-/// Validate an address via FFI.
+/// Scan wallet outputs via FFI.
 #[no_mangle]
 pub unsafe extern "C" fn rust_wallet_scan_outputs(
     wallet: *const c_char,
     start_height: *const c_char,
     number_of_blocks: *const c_char,
 ) -> *const c_char {
-    let wallet_ptr = CStr::from_ptr(wallet);
-    let c_start_height = CStr::from_ptr(start_height);
-    let c_number_of_blocks = CStr::from_ptr(number_of_blocks);
-    let start_height: u64 = c_start_height.to_str().unwrap().to_string().parse().unwrap();
-    let number_of_blocks: u64 = c_number_of_blocks.to_str().unwrap().to_string().parse().unwrap();
-
-    let wallet_data = wallet_ptr.to_str().unwrap();
-    let tuple_wallet_data: (i64, Option<SecretKey>) = serde_json::from_str(wallet_data).unwrap();
-    let wlt = tuple_wallet_data.0;
-    let sek_key = tuple_wallet_data.1;
-
-    ensure_wallet!(wlt, wallet);
-
-    let result = match _wallet_scan_outputs(
-        wallet,
-        sek_key,
-        start_height,
-        number_of_blocks
-    ) {
-        Ok(scan) => {
-            scan
-        }, Err(e ) => {
-            let error_msg = format!("Error {}", &e.to_string());
-            let error_msg_ptr = CString::new(error_msg).unwrap();
-            let ptr = error_msg_ptr.as_ptr();
-            std::mem::forget(error_msg_ptr);
-            ptr
-        }
-    };
-    result
+    guard(|| {
+        let session_id: SessionId = arg_parse(wallet, "wallet")?;
+        let start_height: u64 = arg_parse(start_height, "start_height")?;
+        let number_of_blocks: u64 = arg_parse(number_of_blocks, "number_of_blocks")?;
+
+        ensure_wallet!(session_id, wallet, sek_key, status_send, stop);
+
+        stop.finish(_wallet_scan_outputs(
+            wallet,
+            sek_key,
+            start_height,
+            number_of_blocks,
+            status_send,
+            || stop.is_stopped(),
+        ))
+    })
 }
 
 /// A helper to scan outputs.
-fn _wallet_scan_outputs(
+///
+/// A scan stopped by `is_stopped` fails as `cancelled` with the last scanned
+/// height in the details, so callers can resume from there.
+pub(crate) fn _wallet_scan_outputs(
     wallet: &Wallet,
     keychain_mask: Option<SecretKey>,
     start_height: u64,
-    number_of_blocks: u64
-) -> Result<*const c_char, Error> {
-    // Print arguments for debugging/test-vector use.
-    println!(
-        ">> _wallet_scan_outputs called with start_height={start_height}, number_of_blocks={number_of_blocks}"
-    );
-
-    let mut scan_result = String::new();
+    number_of_blocks: u64,
+    status_send: Option<Sender<StatusMessage>>,
+    is_stopped: impl Fn() -> bool,
+) -> Result<Value, FfiError> {
+    debug!("_wallet_scan_outputs called with start_height={start_height}, number_of_blocks={number_of_blocks}");
 
     // Call wallet_scan_outputs under the hood.
     match wallet_scan_outputs(
         wallet,
         keychain_mask,
         Some(start_height),
-        Some(number_of_blocks)
+        Some(number_of_blocks),
+        status_send,
+        is_stopped,
     ) {
-        Ok(scan_str) => {
-            // Print intermediate data.
-            println!(">> _wallet_scan_outputs result: {scan_str}");
-            scan_result.push_str(&scan_str);
+        Ok(ScanEnd::Finished(height)) => {
+            debug!("_wallet_scan_outputs result: {height}");
+            Ok(Value::from(height))
         },
+        Ok(ScanEnd::Stopped(height)) => Err(FfiError {
+            code: ErrorCode::Cancelled,
+            message: format!("Scan stopped after height {height}"),
+            details: Some(serde_json::json!({ "last_scanned_height": height })),
+        }),
         Err(err) => {
-            println!(">> _wallet_scan_outputs encountered error: {err}");
-            return Err(err);
+            error!("_wallet_scan_outputs encountered error: {err}");
+            Err(err.into())
         },
     }
-
-    // Convert final string result into a *const c_char.
-    let s = CString::new(scan_result).unwrap();
-    let p = s.as_ptr();
-    std::mem::forget(s); // Hand off responsibility to caller.
-    Ok(p)
 }
 
 /// Create a transaction via FFI.
+///
+/// An `amount` of `max` sweeps the whole spendable balance less the fee.
 #[no_mangle]
 pub unsafe extern "C" fn rust_create_tx(
     wallet: *const c_char,
@@ -433,69 +441,52 @@ pub unsafe extern "C" fn rust_create_tx(
     note: *const c_char,
     return_slate_flag: *const c_char,
 ) -> *const c_char {
-
-    let wallet_data = CStr::from_ptr(wallet).to_str().unwrap();
-    let min_confirmations: u64 = CStr::from_ptr(confirmations).to_str().unwrap().to_string().parse().unwrap();
-    let amount: u64 = CStr::from_ptr(amount).to_str().unwrap().to_string().parse().unwrap();
-    let address = CStr::from_ptr(to_address).to_str().unwrap();
-    let note = CStr::from_ptr(note).to_str().unwrap();
-    let key_index: u32 = CStr::from_ptr(secret_key_index).to_str().unwrap().parse().unwrap();
-    let epicbox_config = CStr::from_ptr(epicbox_config).to_str().unwrap();
-
-    let c_return_slate = CStr::from_ptr(return_slate_flag);
-    let return_slate_u64: u64 = c_return_slate.to_str().unwrap().parse().unwrap_or(0);
-    let return_slate = return_slate_u64 != 0;
-
-    let tuple_wallet_data: (i64, Option<SecretKey>) = serde_json::from_str(wallet_data).unwrap();
-
-    // Note: Listener management is now handled by Dart via startEpicboxListener/stopEpicboxListener.
-    // Previously this code spawned/canceled/re-spawned listeners here.
-    // The Dart layer should ensure a listener is running before calling this function (when not using slates).
-
-    let wlt = tuple_wallet_data.0;
-    let sek_key = tuple_wallet_data.1;
-
-    ensure_wallet!(wlt, wallet);
-
-    let result = match _create_tx(
-        wallet,
-        sek_key,
-        amount,
-        address,
-        key_index,
-        epicbox_config,
-        min_confirmations,
-        note,
-        return_slate,
-    ) {
-        Ok(slate) => {
-            slate
-        }, Err(e ) => {
-            let error_msg = format!("Error {}", &e.to_string());
-            let error_msg_ptr = CString::new(error_msg).unwrap();
-            let ptr = error_msg_ptr.as_ptr();
-            std::mem::forget(error_msg_ptr);
-            ptr
-        }
-    };
-    result
-
+    guard(|| {
+        let session_id: SessionId = arg_parse(wallet, "wallet")?;
+        let min_confirmations: u64 = arg_parse(confirmations, "confirmations")?;
+        let amount: SendAmount = arg_parse(amount, "amount")?;
+        let address = arg_str(to_address, "to_address")?;
+        let note = arg_str(note, "note")?;
+        let key_index: u32 = arg_parse(secret_key_index, "secret_key_index")?;
+        let epicbox_config = arg_str(epicbox_config, "epicbox_config")?;
+        let return_slate = arg_flag(return_slate_flag, "return_slate_flag")?;
+
+        // Note: Listener management is now handled by Dart via startEpicboxListener/stopEpicboxListener.
+        // Previously this code spawned/canceled/re-spawned listeners here.
+        // The Dart layer should ensure a listener is running before calling this function (when not using slates).
+
+        session::get_session(session_id)?.spend(|| {
+            ensure_wallet!(session_id, wallet, sek_key, _status_send, stop);
+            stop.finish(_create_tx(
+                wallet,
+                sek_key,
+                amount,
+                address,
+                key_index,
+                epicbox_config,
+                min_confirmations,
+                note,
+                return_slate,
+                stop.flag(),
+            ))
+        })
+    })
 }
 
 /// A helper to create a transaction.
-fn _create_tx(
+pub(crate) fn _create_tx(
     wallet: &Wallet,
     keychain_mask: Option<SecretKey>,
-    amount: u64,
+    amount: SendAmount,
     address: &str,
     _secret_key_index: u32,
     epicbox_config: &str,
     minimum_confirmations: u64,
     note: &str,
     return_slate: bool,
-) -> Result<*const c_char, Error> {
-    let mut message = String::from("");
-    match tx_create(
+    is_stopped: Arc<AtomicBool>,
+) -> Result<Value, Error> {
+    let slate = tx_create(
         &wallet,
         keychain_mask.clone(),
         amount,
@@ -505,23 +496,11 @@ fn _create_tx(
         address,
         note,
         Some(return_slate),
-    ) {
-        Ok(slate) => {
-            let empty_json = format!(r#"{{"slate_msg": ""}}"#);
-            let create_response = (&slate, &empty_json);
-            let str_create_response = serde_json::to_string(&create_response).unwrap();
-            message.push_str(&str_create_response);
-        },
-        Err(e) => {
-            message.push_str(&e.to_string());
-            return Err(e);
-        }
-    }
-
-    let s = CString::new(message).unwrap();
-    let p = s.as_ptr();
-    std::mem::forget(s); // Give up the responsibility of cleaning up/freeing s.
-    Ok(p)
+        is_stopped,
+    )?;
+    let empty_json = format!(r#"{{"slate_msg": ""}}"#);
+    let create_response = (&slate, &empty_json);
+    Ok(serde_json::to_value(&create_response)?)
 }
 
 /// Get transactions via FFI.
@@ -530,119 +509,230 @@ pub unsafe extern "C" fn rust_txs_get(
     wallet: *const c_char,
     refresh_from_node: *const c_char,
 ) -> *const c_char {
-    let c_wallet = CStr::from_ptr(wallet);
-    let c_refresh_from_node = CStr::from_ptr(refresh_from_node);
-    let refresh_from_node: u64 = c_refresh_from_node.to_str().unwrap().to_string().parse().unwrap();
-    let refresh = match refresh_from_node {
-        0 => false,
-        _=> true
-    };
-
-    let wallet_data = c_wallet.to_str().unwrap();
-    let tuple_wallet_data: (i64, Option<SecretKey>) = serde_json::from_str(wallet_data).unwrap();
-    let wlt = tuple_wallet_data.0;
-    let sek_key = tuple_wallet_data.1;
-
-    ensure_wallet!(wlt, wallet);
-
-    let result = match _txs_get(
-        wallet,
-        sek_key,
-        refresh,
-    ) {
-        Ok(txs) => {
-            txs
-        }, Err(e ) => {
-            let error_msg = format!("Error {}", &e.to_string());
-            let error_msg_ptr = CString::new(error_msg).unwrap();
-            let ptr = error_msg_ptr.as_ptr();
-            std::mem::forget(error_msg_ptr);
-            ptr
-        }
-    };
-    result
+    guard(|| {
+        let session_id: SessionId = arg_parse(wallet, "wallet")?;
+        let refresh = arg_flag(refresh_from_node, "refresh_from_node")?;
+
+        ensure_wallet!(session_id, wallet, sek_key, status_send, stop);
+
+        stop.finish(_txs_get(
+            wallet,
+            sek_key,
+            refresh,
+            status_send,
+            stop.flag(),
+        ))
+    })
 }
 
 /// A helper to get transactions.
-fn _txs_get(
+pub(crate) fn _txs_get(
     wallet: &Wallet,
     keychain_mask: Option<SecretKey>,
     refresh_from_node: bool,
-) -> Result<*const c_char, Error> {
-    let mut txs_result = "".to_string();
-    match txs_get(
+    status_send: Option<Sender<StatusMessage>>,
+    is_stopped: Arc<AtomicBool>,
+) -> Result<Value, Error> {
+    let txs = txs_get(
         wallet,
         keychain_mask,
-        refresh_from_node
-    ) {
-        Ok(txs) => {
-            txs_result.push_str(&txs);
-        },
-        Err(err) => {
-            return Err(err);
-        },
-    }
+        refresh_from_node,
+        status_send,
+        is_stopped,
+    )?;
+    Ok(serde_json::from_str(&txs)?)
+}
+
+/// Take the status messages queued for a wallet session via FFI.
+///
+/// Scans, refreshes and balance or transaction queries that hit the node report
+/// progress here. Returns a JSON array, oldest first, which is empty if nothing
+/// was reported since the last poll.
+#[no_mangle]
+pub unsafe extern "C" fn rust_status_poll(
+    wallet: *const c_char,
+) -> *const c_char {
+    guard(|| {
+        let session_id: SessionId = arg_parse(wallet, "wallet")?;
+        _status_poll(session_id)
+    })
+}
 
-    let s = CString::new(txs_result).unwrap();
-    let p = s.as_ptr();
-    std::mem::forget(s); // Give up the responsibility of cleaning up/freeing s.
-    Ok(p)
+/// A helper to take the status messages queued for a session.
+pub(crate) fn _status_poll(session_id: SessionId) -> Result<Value, FfiError> {
+    let session = session::get_session(session_id)?;
+    Ok(Value::Array(session.status().drain()))
 }
 
-/// Cancel a transaction via FFI.
+/// Stop the operations in flight on a wallet session via FFI.
+///
+/// Scans stop after the batch in progress, keeping the progress saved so far
+/// and reporting `last_scanned_height` in the error details; refreshes and
+/// transaction building stop before anything more is written. The stopped
+/// calls fail with a `cancelled` error. Returns the number of
+/// operations asked to stop.
 #[no_mangle]
-pub unsafe extern "C" fn rust_tx_cancel(
+pub unsafe extern "C" fn rust_cancel_operation(
     wallet: *const c_char,
-    tx_id: *const c_char,
 ) -> *const c_char {
-    let wallet_ptr = CStr::from_ptr(wallet);
-    let tx_id = CStr::from_ptr(tx_id);
-    let tx_id = tx_id.to_str().unwrap();
-    let uuid = Uuid::parse_str(tx_id).map_err(|e| EpicWalletControllerError::GenericError(e.to_string())).unwrap();
+    guard(|| {
+        let session_id: SessionId = arg_parse(wallet, "wallet")?;
+        _cancel_operation(session_id)
+    })
+}
 
-    let wallet_data = wallet_ptr.to_str().unwrap();
-    let tuple_wallet_data: (i64, Option<SecretKey>) = serde_json::from_str(wallet_data).unwrap();
-    let wlt = tuple_wallet_data.0;
-    let sek_key = tuple_wallet_data.1;
+/// A helper to stop the operations in flight on a session.
+pub(crate) fn _cancel_operation(session_id: SessionId) -> Result<Value, FfiError> {
+    let session = session::get_session(session_id)?;
+    Ok(Value::from(session.stop_operations()))
+}
+
+/// Set how long a wallet session may go without spending before it locks,
+/// via FFI.
+///
+/// A locked session keeps serving balances, history and refreshes, but
+/// sending and finalizing fail with `session_locked` until
+/// [`rust_unlock_wallet`] is called with the wallet password. Only successful
+/// spends and unlocks count as activity. `seconds` of 0 turns the lock off,
+/// which is the default.
+#[no_mangle]
+pub unsafe extern "C" fn rust_set_idle_timeout(
+    wallet: *const c_char,
+    seconds: *const c_char,
+) -> *const c_char {
+    guard(|| {
+        let session_id: SessionId = arg_parse(wallet, "wallet")?;
+        let seconds: u64 = arg_parse(seconds, "seconds")?;
+        _set_idle_timeout(session_id, seconds)
+    })
+}
 
-    ensure_wallet!(wlt, wallet);
+/// A helper to set the idle timeout of a session.
+pub(crate) fn _set_idle_timeout(session_id: SessionId, seconds: u64) -> Result<Value, FfiError> {
+    let session = session::get_session(session_id)?;
+    session.set_idle_timeout(match seconds {
+        0 => None,
+        seconds => Some(Duration::from_secs(seconds)),
+    });
+    Ok(Value::Null)
+}
 
-    let result = match _tx_cancel(
-        wallet,
-        sek_key,
-        uuid,
-    ) {
-        Ok(cancelled) => {
-            cancelled
-        }, Err(e ) => {
-            let error_msg = format!("Error {}", &e.to_string());
-            let error_msg_ptr = CString::new(error_msg).unwrap();
-            let ptr = error_msg_ptr.as_ptr();
-            std::mem::forget(error_msg_ptr);
-            ptr
-        }
-    };
-    result
+/// Check whether a wallet session is locked after being idle, via FFI.
+#[no_mangle]
+pub unsafe extern "C" fn rust_is_session_locked(
+    wallet: *const c_char,
+) -> *const c_char {
+    guard(|| {
+        let session_id: SessionId = arg_parse(wallet, "wallet")?;
+        _is_session_locked(session_id)
+    })
+}
+
+/// A helper to check whether a session is locked.
+pub(crate) fn _is_session_locked(session_id: SessionId) -> Result<Value, FfiError> {
+    let session = session::get_session(session_id)?;
+    Ok(Value::Bool(session.is_locked()))
+}
+
+/// Unlock a wallet session that locked after being idle, via FFI.
+///
+/// Fails with `wrong_password` if `password` does not decrypt the wallet seed.
+/// Unlocking a session that is not locked only checks the password.
+#[no_mangle]
+pub unsafe extern "C" fn rust_unlock_wallet(
+    wallet: *const c_char,
+    password: *const c_char,
+) -> *const c_char {
+    guard(|| {
+        let session_id: SessionId = arg_parse(wallet, "wallet")?;
+        let password = arg_str(password, "password")?;
+        _unlock_wallet(session_id, password)
+    })
+}
+
+/// A helper to unlock a session.
+pub(crate) fn _unlock_wallet(session_id: SessionId, password: &str) -> Result<Value, FfiError> {
+    let session = session::get_session(session_id)?;
+    verify_password(session.wallet(), password)?;
+    session.unlock();
+    Ok(Value::Null)
+}
+
+/// Run an owner JSON-RPC 2.0 request against a wallet session via FFI.
+///
+/// Any method of the owner API is available except those that open, close or
+/// re-key the wallet. Callers leave out the `token` parameter; the session's
+/// keychain mask is passed for the methods that take one. Returns the JSON-RPC
+/// response.
+#[no_mangle]
+pub unsafe extern "C" fn rust_owner_rpc(
+    wallet: *const c_char,
+    request: *const c_char,
+) -> *const c_char {
+    guard(|| {
+        let session_id: SessionId = arg_parse(wallet, "wallet")?;
+        let request = arg_str(request, "request")?;
+        _owner_rpc(session_id, request)
+    })
+}
+
+/// A helper to run an owner JSON-RPC request.
+pub(crate) fn _owner_rpc(session_id: SessionId, request: &str) -> Result<Value, FfiError> {
+    let session = session::get_session(session_id)?;
+    rpc::owner_rpc(&session, request)
+}
+
+/// Run a foreign JSON-RPC 2.0 request against a wallet session via FFI.
+///
+/// Covers `check_version`, `build_coinbase`, `verify_slate_messages`,
+/// `receive_tx` and `finalize_invoice_tx`, with the same request and response
+/// shapes as the epic-wallet foreign HTTP API. Returns the JSON-RPC response.
+#[no_mangle]
+pub unsafe extern "C" fn rust_foreign_rpc(
+    wallet: *const c_char,
+    request: *const c_char,
+) -> *const c_char {
+    guard(|| {
+        let session_id: SessionId = arg_parse(wallet, "wallet")?;
+        let request = arg_str(request, "request")?;
+        _foreign_rpc(session_id, request)
+    })
+}
+
+/// A helper to run a foreign JSON-RPC request.
+pub(crate) fn _foreign_rpc(session_id: SessionId, request: &str) -> Result<Value, FfiError> {
+    let session = session::get_session(session_id)?;
+    rpc::foreign_rpc(&session, request)
+}
+
+/// Cancel a transaction via FFI.
+#[no_mangle]
+pub unsafe extern "C" fn rust_tx_cancel(
+    wallet: *const c_char,
+    tx_id: *const c_char,
+) -> *const c_char {
+    guard(|| {
+        let session_id: SessionId = arg_parse(wallet, "wallet")?;
+        let uuid: Uuid = arg_parse(tx_id, "tx_id")?;
+
+        ensure_wallet!(session_id, wallet, sek_key);
+
+        Ok(_tx_cancel(
+            wallet,
+            sek_key,
+            uuid,
+        )?)
+    })
 }
 
 /// A helper to cancel a transaction.
-fn _tx_cancel(
+pub(crate) fn _tx_cancel(
     wallet: &Wallet,
     keychain_mask: Option<SecretKey>,
     tx_id: Uuid,
-) -> Result<*const c_char, Error>{
-    let mut cancel_msg = "".to_string();
-    match  tx_cancel(wallet, keychain_mask, tx_id) {
-        Ok(_) => {
-            cancel_msg.push_str("");
-        },Err(err) => {
-            return Err(err);
-        }
-    }
-    let s = CString::new(cancel_msg).unwrap();
-    let p = s.as_ptr();
-    std::mem::forget(s); // Give up the responsibility of cleaning up/freeing s.
-    Ok(p)
+) -> Result<Value, Error>{
+    tx_cancel(wallet, keychain_mask, tx_id)?;
+    Ok(Value::String("".to_string()))
 }
 
 /// Get chain height via FFI.
@@ -650,88 +740,410 @@ fn _tx_cancel(
 pub unsafe extern "C" fn rust_get_chain_height(
     config: *const c_char,
 ) -> *const c_char {
-    let result = match _get_chain_height(
-        config
-    ) {
-        Ok(chain_height) => {
-            chain_height
-        }, Err(e ) => {
-            let error_msg = format!("Error {}", &e.to_string());
-            let error_msg_ptr = CString::new(error_msg).unwrap();
-            let ptr = error_msg_ptr.as_ptr();
-            std::mem::forget(error_msg_ptr);
-            ptr
-        }
-    };
-    result
+    guard(|| {
+        let config = arg_str(config, "config")?;
+        Ok(_get_chain_height(config)?)
+    })
 }
 
 /// A helper to get chain height.
-fn _get_chain_height(config: *const c_char) -> Result<*const c_char, Error> {
-    let c_config = unsafe { CStr::from_ptr(config) };
-    let str_config = c_config.to_str().unwrap();
-    let mut chain_height = "".to_string();
-    match get_chain_height(&str_config) {
+pub(crate) fn _get_chain_height(config: &str) -> Result<Value, Error> {
+    match get_chain_height(config) {
         Ok(chain_tip) => {
-            chain_height.push_str(&chain_tip.to_string());
+            Ok(Value::from(chain_tip))
         },
         Err(e) => {
             debug!("CHAIN_HEIGHT_ERROR {}", e.to_string());
-            return Err(e);
+            Err(e)
         },
     }
-    let s = CString::new(chain_height).unwrap();
-    let p = s.as_ptr();
-    std::mem::forget(s); // Give up the responsibility of cleaning up/freeing s.
-    Ok(p)
+}
+
+/// Close a wallet via FFI.
+///
+/// Releases the wallet behind the handle. The handle, and any copy of it, is
+/// invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rust_close_wallet(
+    wallet: *const c_char,
+) -> *const c_char {
+    guard(|| {
+        let session_id: SessionId = arg_parse(wallet, "wallet")?;
+        _close_wallet(session_id)
+    })
+}
+
+/// A helper to close a wallet.
+pub(crate) fn _close_wallet(session_id: SessionId) -> Result<Value, FfiError> {
+    let session = session::close_session(session_id)?;
+    if let Some(dir) = session.wallet_dir() {
+        record_last_confirmed_height(dir, session.wallet());
+    }
+    close_wallet(session.wallet())?;
+    Ok(Value::Null)
 }
 
 /// Delete a wallet via FFI.
+///
+/// Works from `config` alone: the wallet need not be open, and its password
+/// and database are not needed. A session that has the wallet open is closed
+/// first, which stops its epicbox listeners. `wallet` may be empty; a session
+/// id passed in it must be of this wallet, or the call fails with
+/// `invalid_argument` and nothing is deleted. Only the
+/// wallet's own files are removed, the seed file overwritten first, and the
+/// directory only if nothing else is left in it. Fails with `wallet_locked` if
+/// another process has the wallet open. Returns `{removed, kept,
+/// closed_sessions}`, where `removed` lists every file removed and `kept` what
+/// was left in the directory, relative to it.
 #[no_mangle]
 pub unsafe extern "C" fn rust_delete_wallet(
-    _wallet: *const c_char,
+    wallet: *const c_char,
     config: *const c_char,
 ) -> *const c_char  {
-    let c_conf = CStr::from_ptr(config);
-    let _config = Config::from_str(c_conf.to_str().unwrap()).unwrap(); // TODO: handle error here.
-
-    let result = match _delete_wallet(
-        _config,
-    ) {
-        Ok(deleted) => {
-            deleted
-        }, Err(err) => {
-            let error_msg = format!("Error deleting wallet from _delete_wallet in rust_delete_wallet {}", &err.to_string());
-            let error_msg_ptr = CString::new(error_msg).unwrap();
-            let ptr = error_msg_ptr.as_ptr();
-            std::mem::forget(error_msg_ptr);
-            ptr
+    guard(|| {
+        let config = arg_str(config, "config")?;
+        let wallet_dir = config_wallet_dir(config)?;
+        // The session that has the wallet open is found and closed by its
+        // directory; a session passed in only has to be that one.
+        if let Ok(session_id) = arg_parse::<SessionId>(wallet, "wallet") {
+            if let Ok(session) = session::get_session(session_id) {
+                if !session.holds_wallet_dir(&wallet_dir) {
+                    return Err(FfiError::new(
+                        ErrorCode::InvalidArgument,
+                        "The session does not hold the wallet in the config",
+                    ));
+                }
+            }
         }
-    };
-    result
+
+        let deleted = manager::delete_wallet_dir(&wallet_dir)?;
+        Ok(serde_json::to_value(&deleted)?)
+    })
+}
+
+/// The wallet directory of a wallet config.
+fn config_wallet_dir(config: &str) -> Result<PathBuf, FfiError> {
+    let config = Config::from_str(config).map_err(|err| FfiError::new(
+        ErrorCode::InvalidConfig,
+        format!("Wallet config error : {}", err),
+    ))?;
+    Ok(PathBuf::from(config.wallet_dir))
 }
 
 /// A helper to delete a wallet.
-fn _delete_wallet(
-    config: Config,
-) -> Result<*const c_char, Error> {
-    let mut delete_result = String::from("");
-    match delete_wallet(config) {
-        Ok(deleted) => {
-            delete_result.push_str(&deleted);
-        },
-        Err(err) => {
-            return Err(err);
-        },
+pub(crate) fn _delete_wallet(config: &str) -> Result<manager::DeletedWallet, FfiError> {
+    manager::delete_wallet_dir(&config_wallet_dir(config)?)
+}
+
+/// Change a wallet's password via FFI.
+///
+/// The wallet may be open; its sessions keep working. Fails with
+/// `wrong_password` if `old_password` does not decrypt the seed.
+#[no_mangle]
+pub unsafe extern "C" fn rust_change_password(
+    config: *const c_char,
+    old_password: *const c_char,
+    new_password: *const c_char,
+) -> *const c_char {
+    guard(|| {
+        let config = arg_str(config, "config")?;
+        let old_password = arg_str(old_password, "old_password")?;
+        let new_password = arg_str(new_password, "new_password")?;
+        _change_password(config, old_password, new_password)
+    })
+}
+
+/// A helper to change a wallet's password.
+pub(crate) fn _change_password(
+    config: &str,
+    old_password: &str,
+    new_password: &str,
+) -> Result<Value, FfiError> {
+    let config = Config::from_str(config).map_err(|err| FfiError::new(
+        ErrorCode::InvalidConfig,
+        format!("Wallet config error : {}", err),
+    ))?;
+    change_password(&config, old_password, new_password)?;
+    Ok(Value::Null)
+}
+
+/// Report whether a wallet exists and what it was created with, via FFI.
+///
+/// Needs no password and works whether or not the wallet is open. Returns
+/// `{seed_exists, chain, last_confirmed_height, name}`. The last two are null
+/// when unknown: `name` for wallets created before it was recorded, and
+/// `last_confirmed_height` for a wallet that is not open here and has not been
+/// closed since it was created. The database of a closed wallet is not read;
+/// its height is the one recorded when it was last closed. No database schema
+/// version is reported, as the LMDB backend does not record one.
+#[no_mangle]
+pub unsafe extern "C" fn rust_wallet_status(
+    config: *const c_char,
+) -> *const c_char {
+    guard(|| {
+        let config = arg_str(config, "config")?;
+        _wallet_status(config)
+    })
+}
+
+/// A helper to report the status of a wallet.
+pub(crate) fn _wallet_status(config: &str) -> Result<Value, FfiError> {
+    let config = Config::from_str(config).map_err(|err| FfiError::new(
+        ErrorCode::InvalidConfig,
+        format!("Wallet config error : {}", err),
+    ))?;
+    let status = match session::find_session_by_dir(Path::new(&config.wallet_dir)) {
+        Some((_, session)) => wallet_status(&config, Some(session.wallet()))?,
+        None => wallet_status(&config, None)?,
+    };
+    Ok(serde_json::to_value(status)?)
+}
+
+/// Reveal a wallet's recovery phrase via FFI.
+///
+/// The seed is decrypted with `password`; fails with `wrong_password` if it
+/// does not match. The phrase is never logged, and every buffer holding it on
+/// this side is zeroed. Free the result with [`rust_string_free`] as soon as
+/// the phrase has been shown.
+#[no_mangle]
+pub unsafe extern "C" fn rust_reveal_mnemonic(
+    config: *const c_char,
+    password: *const c_char,
+) -> *const c_char {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let config = arg_str(config, "config")?;
+        let password = arg_str(password, "password")?;
+        let mnemonic = _reveal_mnemonic(config, password)?;
+        envelope::secret_to_json(&mnemonic)
+    }))
+    .unwrap_or_else(|payload| Err(panic_error(payload)));
+    match result {
+        Ok(json) => secret_into_raw(json),
+        Err(err) => envelope_ptr::<FfiError>(Err(err)),
     }
-    let s = CString::new(delete_result).unwrap();
-    let p = s.as_ptr();
-    std::mem::forget(s); // Give up the responsibility of cleaning up/freeing s.
-    Ok(p)
+}
 
+/// A helper to reveal a wallet's recovery phrase.
+pub(crate) fn _reveal_mnemonic(config: &str, password: &str) -> Result<ZeroingString, FfiError> {
+    let config = Config::from_str(config).map_err(|err| FfiError::new(
+        ErrorCode::InvalidConfig,
+        format!("Wallet config error : {}", err),
+    ))?;
+    Ok(reveal_mnemonic(&config, password)?)
+}
+
+/// Back up a wallet to an encrypted archive via FFI.
+///
+/// The archive holds everything in the wallet directory but logs: the seed
+/// file, the database with the transaction log, notes and accounts, and saved
+/// slates. It is encrypted and authenticated with `password`. The wallet must
+/// be closed. Returns `{files, bytes}`.
+#[no_mangle]
+pub unsafe extern "C" fn rust_backup_wallet(
+    config: *const c_char,
+    password: *const c_char,
+    archive_path: *const c_char,
+) -> *const c_char {
+    guard(|| {
+        let config = arg_str(config, "config")?;
+        let password = arg_str(password, "password")?;
+        let archive_path = arg_str(archive_path, "archive_path")?;
+        _backup_wallet(config, password, archive_path)
+    })
+}
+
+/// A helper to back up a wallet.
+pub(crate) fn _backup_wallet(
+    config: &str,
+    password: &str,
+    archive_path: &str,
+) -> Result<Value, FfiError> {
+    let config = Config::from_str(config).map_err(|err| FfiError::new(
+        ErrorCode::InvalidConfig,
+        format!("Wallet config error : {}", err),
+    ))?;
+    let summary = backup::backup_wallet(&config, password, Path::new(archive_path))?;
+    Ok(serde_json::to_value(&summary)?)
+}
+
+/// Restore a wallet backup into a new wallet directory via FFI.
+///
+/// `wallet_dir` must not exist yet or be empty. Fails with `wrong_password` if
+/// `password` does not match or the archive was altered, and with `lifecycle`
+/// or `wallet_locked` if the directory is open here or in another process.
+/// Open the restored wallet with a config pointing at `wallet_dir` and the
+/// wallet's own password. Returns `{files, bytes}`.
+#[no_mangle]
+pub unsafe extern "C" fn rust_restore_wallet_backup(
+    archive_path: *const c_char,
+    password: *const c_char,
+    wallet_dir: *const c_char,
+) -> *const c_char {
+    guard(|| {
+        let archive_path = arg_str(archive_path, "archive_path")?;
+        let password = arg_str(password, "password")?;
+        let wallet_dir = arg_str(wallet_dir, "wallet_dir")?;
+        _restore_wallet_backup(archive_path, password, wallet_dir)
+    })
+}
+
+/// A helper to restore a wallet backup.
+pub(crate) fn _restore_wallet_backup(
+    archive_path: &str,
+    password: &str,
+    wallet_dir: &str,
+) -> Result<Value, FfiError> {
+    let summary = backup::restore_wallet(Path::new(archive_path), password, Path::new(wallet_dir))?;
+    Ok(serde_json::to_value(&summary)?)
+}
+
+/// List the wallets kept under a data directory via FFI.
+///
+/// Each wallet lives in `root/<name>`. Returns an array of
+/// `{name, chain, created_at, wallet_dir}` sorted by name; `chain` and
+/// `created_at` are null for wallets created before they were recorded.
+#[no_mangle]
+pub unsafe extern "C" fn rust_manager_list_wallets(
+    root: *const c_char,
+) -> *const c_char {
+    guard(|| {
+        let root = arg_str(root, "root")?;
+        _manager_list_wallets(root)
+    })
+}
+
+/// A helper to list the wallets under a data directory.
+pub(crate) fn _manager_list_wallets(root: &str) -> Result<Value, FfiError> {
+    let wallets = manager::list_wallets(Path::new(root))?;
+    Ok(serde_json::to_value(&wallets)?)
+}
+
+/// Create a wallet named `name` under a data directory via FFI.
+///
+/// `config` supplies the node and the chain; its `wallet_dir` is ignored.
+/// Fails with `wallet_seed_exists` if the name is taken. Returns the new
+/// wallet's entry, as listed by [`rust_manager_list_wallets`].
+#[no_mangle]
+pub unsafe extern "C" fn rust_manager_create_wallet(
+    root: *const c_char,
+    config: *const c_char,
+    name: *const c_char,
+    mnemonic: *const c_char,
+    password: *const c_char,
+) -> *const c_char {
+    guard(|| {
+        let root = arg_str(root, "root")?;
+        let config = arg_str(config, "config")?;
+        let name = arg_str(name, "name")?;
+        let mnemonic = arg_str(mnemonic, "mnemonic")?;
+        let password = arg_str(password, "password")?;
+        _manager_create_wallet(root, config, name, mnemonic, password)
+    })
+}
+
+/// A helper to create a wallet under a data directory.
+pub(crate) fn _manager_create_wallet(
+    root: &str,
+    config: &str,
+    name: &str,
+    mnemonic: &str,
+    password: &str,
+) -> Result<Value, FfiError> {
+    let config = Config::from_str(config).map_err(|err| FfiError::new(
+        ErrorCode::InvalidConfig,
+        format!("Wallet config error : {}", err),
+    ))?;
+    let created = manager::create_wallet(Path::new(root), &config, name, mnemonic, password)?;
+    Ok(serde_json::to_value(&created)?)
+}
+
+/// Open the wallet named `name` under a data directory via FFI.
+///
+/// `config` supplies the node and the account; the wallet directory is the
+/// wallet's own and the chain is the one it was created on. Otherwise the same
+/// as [`rust_open_wallet`], returning a session id.
+#[no_mangle]
+pub unsafe extern "C" fn rust_manager_open_wallet(
+    root: *const c_char,
+    config: *const c_char,
+    name: *const c_char,
+    password: *const c_char,
+) -> *const c_char {
+    logging::init();
+    guard(|| {
+        let root = arg_str(root, "root")?;
+        let config = arg_str(config, "config")?;
+        let name = arg_str(name, "name")?;
+        let password = arg_str(password, "password")?;
+        _manager_open_wallet(root, config, name, password)
+    })
+}
+
+/// A helper to open a wallet under a data directory.
+pub(crate) fn _manager_open_wallet(
+    root: &str,
+    config: &str,
+    name: &str,
+    password: &str,
+) -> Result<Value, FfiError> {
+    let config = Config::from_str(config).map_err(|err| FfiError::new(
+        ErrorCode::InvalidConfig,
+        format!("Wallet config error : {}", err),
+    ))?;
+    let config = manager::wallet_config(Path::new(root), &config, name)?;
+    _open_wallet(&serde_json::to_string(&config)?, password)
+}
+
+/// Rename a closed wallet under a data directory via FFI.
+///
+/// Fails with `wallet_seed_exists` if `new_name` is taken, and with
+/// `lifecycle` or `wallet_locked` if the wallet is open. Returns the renamed
+/// wallet's entry.
+#[no_mangle]
+pub unsafe extern "C" fn rust_manager_rename_wallet(
+    root: *const c_char,
+    name: *const c_char,
+    new_name: *const c_char,
+) -> *const c_char {
+    guard(|| {
+        let root = arg_str(root, "root")?;
+        let name = arg_str(name, "name")?;
+        let new_name = arg_str(new_name, "new_name")?;
+        _manager_rename_wallet(root, name, new_name)
+    })
+}
+
+/// A helper to rename a wallet under a data directory.
+pub(crate) fn _manager_rename_wallet(root: &str, name: &str, new_name: &str) -> Result<Value, FfiError> {
+    let renamed = manager::rename_wallet(Path::new(root), name, new_name)?;
+    Ok(serde_json::to_value(&renamed)?)
+}
+
+/// Delete a wallet under a data directory via FFI.
+///
+/// The same as [`rust_delete_wallet`] for the wallet's directory, returning
+/// `{removed, kept, closed_sessions}`.
+#[no_mangle]
+pub unsafe extern "C" fn rust_manager_delete_wallet(
+    root: *const c_char,
+    name: *const c_char,
+) -> *const c_char {
+    guard(|| {
+        let root = arg_str(root, "root")?;
+        let name = arg_str(name, "name")?;
+        _manager_delete_wallet(root, name)
+    })
+}
+
+/// A helper to delete a wallet under a data directory.
+pub(crate) fn _manager_delete_wallet(root: &str, name: &str) -> Result<Value, FfiError> {
+    let deleted = manager::delete_wallet(Path::new(root), name)?;
+    Ok(serde_json::to_value(&deleted)?)
 }
 
 /// Send a transaction via FFI.
+///
+/// An `amount` of `max` sweeps the whole spendable balance less the fee.
 #[no_mangle]
 pub unsafe extern "C" fn rust_tx_send_http(
     wallet: *const c_char,
@@ -741,84 +1153,138 @@ pub unsafe extern "C" fn rust_tx_send_http(
     amount: *const c_char,
     address: *const c_char,
 ) -> *const c_char  {
-    let c_wallet = CStr::from_ptr(wallet);
-    let c_strategy_is_use_all = CStr::from_ptr(selection_strategy_is_use_all);
-    let strategy_is_use_all: u64 = c_strategy_is_use_all.to_str().unwrap().to_string().parse().unwrap();
-    let strategy_use_all = match strategy_is_use_all {
-        0 => false,
-        _=> true
-    };
-    let c_minimum_confirmations = CStr::from_ptr(minimum_confirmations);
-    let minimum_confirmations: u64 = c_minimum_confirmations.to_str().unwrap().to_string().parse().unwrap();
-    let c_message = CStr::from_ptr(message);
-    let str_message = c_message.to_str().unwrap();
-    let c_amount = CStr::from_ptr(amount);
-    let amount: u64 = c_amount.to_str().unwrap().to_string().parse().unwrap();
-    let c_address = CStr::from_ptr(address);
-    let str_address = c_address.to_str().unwrap();
-
-    let wallet_data = c_wallet.to_str().unwrap();
-    let tuple_wallet_data: (i64, Option<SecretKey>) = serde_json::from_str(wallet_data).unwrap();
-    let wlt = tuple_wallet_data.0;
-    let sek_key = tuple_wallet_data.1;
-    ensure_wallet!(wlt, wallet);
-
-    let result = match _tx_send_http(
-        wallet,
-        sek_key,
-        strategy_use_all,
-        minimum_confirmations,
-        str_message,
-        amount,
-        str_address
-    ) {
-        Ok(tx_data) => {
-            tx_data
-        }, Err(err ) => {
-            let error_msg = format!("Error {}", &err.to_string());
-            let error_msg_ptr = CString::new(error_msg).unwrap();
-            let ptr = error_msg_ptr.as_ptr();
-            std::mem::forget(error_msg_ptr);
-            ptr
-        }
-    };
-    result
+    guard(|| {
+        let session_id: SessionId = arg_parse(wallet, "wallet")?;
+        let strategy_use_all = arg_flag(selection_strategy_is_use_all, "selection_strategy_is_use_all")?;
+        let minimum_confirmations: u64 = arg_parse(minimum_confirmations, "minimum_confirmations")?;
+        let str_message = arg_str(message, "message")?;
+        let amount: SendAmount = arg_parse(amount, "amount")?;
+        let str_address = arg_str(address, "address")?;
+
+        session::get_session(session_id)?.spend(|| {
+            ensure_wallet!(session_id, wallet, sek_key, _status_send, stop);
+            stop.finish(_tx_send_http(
+                wallet,
+                sek_key,
+                strategy_use_all,
+                minimum_confirmations,
+                str_message,
+                amount,
+                str_address,
+                stop.flag(),
+            ))
+        })
+    })
 }
 
 /// A helper to send a transaction.
-fn _tx_send_http(
+pub(crate) fn _tx_send_http(
     wallet: &Wallet,
     keychain_mask: Option<SecretKey>,
     selection_strategy_is_use_all: bool,
     minimum_confirmations: u64,
     message: &str,
-    amount: u64,
-    address: &str
-) -> Result<*const c_char, Error> {
-    let mut send_result = String::from("");
-    match tx_send_http(
+    amount: SendAmount,
+    address: &str,
+    is_stopped: Arc<AtomicBool>,
+) -> Result<Value, Error> {
+    let sent = tx_send_http(
         wallet,
         keychain_mask,
         selection_strategy_is_use_all,
         minimum_confirmations,
         message,
         amount,
-        address
-    ) {
-        Ok(sent) => {
-            let empty_json = format!(r#"{{"slate_msg": ""}}"#);
-            let create_response = (&sent, &empty_json);
-            let str_create_response = serde_json::to_string(&create_response).unwrap();
-            send_result.push_str(&str_create_response);
-        },
-        Err(err) => {
-            return Err(err);
-        },
-    }
-    let s = CString::new(send_result).unwrap();
-    let p = s.as_ptr();
-    std::mem::forget(s); // Give up the responsibility of cleaning up/freeing s.
-    Ok(p)
+        address,
+        is_stopped,
+    )?;
+    let empty_json = format!(r#"{{"slate_msg": ""}}"#);
+    let create_response = (&sent, &empty_json);
+    Ok(serde_json::to_value(&create_response)?)
+}
+
+/// List a wallet's accounts via FFI.
+///
+/// Returns a JSON array of `{label, path, active}` objects, where `path` is the
+/// BIP32 path of the account's parent key and `active` marks the account the
+/// session sends, receives and reports balances and history for.
+#[no_mangle]
+pub unsafe extern "C" fn rust_accounts(
+    wallet: *const c_char,
+) -> *const c_char {
+    guard(|| {
+        let session_id: SessionId = arg_parse(wallet, "wallet")?;
+
+        ensure_wallet!(session_id, wallet, sek_key);
+
+        Ok(_accounts(wallet, sek_key)?)
+    })
+}
+
+/// A helper to list accounts.
+pub(crate) fn _accounts(
+    wallet: &Wallet,
+    keychain_mask: Option<SecretKey>,
+) -> Result<Value, Error> {
+    let accounts = accounts(wallet, keychain_mask)?;
+    Ok(serde_json::to_value(&accounts)?)
+}
+
+/// Create a named account via FFI, returning its parent key path.
+///
+/// The active account does not change.
+#[no_mangle]
+pub unsafe extern "C" fn rust_create_account(
+    wallet: *const c_char,
+    label: *const c_char,
+) -> *const c_char {
+    guard(|| {
+        let session_id: SessionId = arg_parse(wallet, "wallet")?;
+        let label = arg_str(label, "label")?;
+
+        ensure_wallet!(session_id, wallet, sek_key);
+
+        Ok(_create_account(wallet, sek_key, label)?)
+    })
+}
+
+/// A helper to create an account.
+pub(crate) fn _create_account(
+    wallet: &Wallet,
+    keychain_mask: Option<SecretKey>,
+    label: &str,
+) -> Result<Value, Error> {
+    let path = create_account(wallet, keychain_mask, label)?;
+    Ok(Value::String(path))
+}
+
+/// Switch the active account of a wallet session via FFI.
+///
+/// Sends, receives, balances and transaction history of the session use the
+/// active account from then on. Other sessions are not affected.
+#[no_mangle]
+pub unsafe extern "C" fn rust_set_active_account(
+    wallet: *const c_char,
+    label: *const c_char,
+) -> *const c_char {
+    guard(|| {
+        let session_id: SessionId = arg_parse(wallet, "wallet")?;
+        let label = arg_str(label, "label")?;
+
+        ensure_wallet!(session_id, wallet, sek_key);
+
+        Ok(_set_active_account(wallet, sek_key, label)?)
+    })
+}
+
+/// A helper to switch the active account.
+pub(crate) fn _set_active_account(
+    wallet: &Wallet,
+    keychain_mask: Option<SecretKey>,
+    label: &str,
+) -> Result<Value, Error> {
+    set_active_account(wallet, keychain_mask, label)?;
+    Ok(Value::Null)
 }
 
 /// Get a wallet address via FFI.
@@ -828,49 +1294,31 @@ pub unsafe extern "C" fn rust_get_wallet_address(
     index: *const c_char,
     epicbox_config: *const c_char,
 ) -> *const c_char {
-    let wallet_ptr = CStr::from_ptr(wallet);
-    let index = CStr::from_ptr(index);
-    let epicbox_config = CStr::from_ptr(epicbox_config);
-    let epicbox_config = epicbox_config.to_str().unwrap();
-    let index: u32 = index.to_str().unwrap().to_string().parse().unwrap();
-
-    let wallet_data = wallet_ptr.to_str().unwrap();
-    let tuple_wallet_data: (i64, Option<SecretKey>) = serde_json::from_str(wallet_data).unwrap();
-    let wlt = tuple_wallet_data.0;
-    let sek_key = tuple_wallet_data.1;
-
-    ensure_wallet!(wlt, wallet);
-    let result = match _get_wallet_address(
-        wallet,
-        sek_key,
-        index,
-        epicbox_config
-    ) {
-        Ok(address) => {
-            address
-        }, Err(e ) => {
-            let error_msg = format!("Error {}", &e.to_string());
-            let error_msg_ptr = CString::new(error_msg).unwrap();
-            let ptr = error_msg_ptr.as_ptr();
-            std::mem::forget(error_msg_ptr);
-            ptr
-        }
-    };
-    result
+    guard(|| {
+        let session_id: SessionId = arg_parse(wallet, "wallet")?;
+        let index: u32 = arg_parse(index, "index")?;
+        let epicbox_config = arg_str(epicbox_config, "epicbox_config")?;
+
+        ensure_wallet!(session_id, wallet, sek_key);
+
+        _get_wallet_address(
+            wallet,
+            sek_key,
+            index,
+            epicbox_config
+        )
+    })
 }
 
 /// A helper to get a wallet address.
-fn _get_wallet_address(
+pub(crate) fn _get_wallet_address(
     wallet: &Wallet,
     keychain_mask: Option<SecretKey>,
     index: u32,
     epicbox_config: &str
-) -> Result<*const c_char, Error> {
-    let address = get_wallet_address(&wallet, keychain_mask, index, epicbox_config);
-    let s = CString::new(address).unwrap();
-    let p = s.as_ptr();
-    std::mem::forget(s); // Give up the responsibility of cleaning up/freeing s
-    Ok(p)
+) -> Result<Value, FfiError> {
+    let address = get_wallet_address(&wallet, keychain_mask, index, epicbox_config)?;
+    Ok(Value::String(address))
 }
 
 /// Get a wallet address.
@@ -879,13 +1327,15 @@ pub fn get_wallet_address(
     keychain_mask: Option<SecretKey>,
     index: u32,
     epicbox_config: &str,
-) -> String {
-
-    let epicbox_conf = serde_json::from_str::<EpicboxConfig>(epicbox_config).unwrap();
+) -> Result<String, FfiError> {
+    let epicbox_conf = serde_json::from_str::<EpicboxConfig>(epicbox_config).map_err(|e| FfiError::new(
+        ErrorCode::InvalidConfig,
+        format!("Epicbox config error : {}", e),
+    ))?;
     let is_stopped = Arc::new(AtomicBool::new(false));
     let api = Owner::new(wallet.clone(), None, is_stopped.clone());
-    let address = api.get_public_address(keychain_mask.as_ref(), index).unwrap();
-    format!("{}@{}", address.public_key, epicbox_conf.epicbox_domain.as_deref().unwrap_or(""))
+    let address = api.get_public_address(keychain_mask.as_ref(), index)?;
+    Ok(format!("{}@{}", address.public_key, epicbox_conf.epicbox_domain.as_deref().unwrap_or("")))
 }
 
 /// Validate an address via FFI.
@@ -893,160 +1343,357 @@ pub fn get_wallet_address(
 pub unsafe extern "C" fn rust_validate_address(
     address: *const c_char,
 ) -> *const c_char {
-    let address = unsafe { CStr::from_ptr(address) };
-    let str_address = address.to_str().unwrap();
-    let validate = validate_address(str_address);
-    let return_value = match validate {
-        true => 1,
-        false => 0
-    };
-
-    let s = CString::new(return_value.to_string()).unwrap();
-    let p = s.as_ptr();
-    std::mem::forget(s); // Give up the responsibility of cleaning up/freeing s.
-    p
+    guard(|| {
+        let str_address = arg_str(address, "address")?;
+        let validate = validate_address(str_address);
+        let return_value = match validate {
+            true => 1,
+            false => 0
+        };
+        Ok(Value::from(return_value))
+    })
 }
 
-/// Validate an address.
+/// Get a fee quote for every coin selection strategy via FFI.
+///
+/// A strategy that can't cover the amount carries `not_enough_funds` instead
+/// of failing the call.
 #[no_mangle]
 pub unsafe extern "C" fn rust_get_tx_fees(
     wallet: *const c_char,
     c_amount: *const c_char,
     min_confirmations: *const c_char,
 ) -> *const c_char {
-
-    let minimum_confirmations = CStr::from_ptr(min_confirmations);
-    let minimum_confirmations: u64 = minimum_confirmations.to_str().unwrap().to_string().parse().unwrap();
-    let wallet_ptr = CStr::from_ptr(wallet);
-
-    let amount = CStr::from_ptr(c_amount);
-    let amount: u64 = amount.to_str().unwrap().to_string().parse().unwrap();
-
-    let wallet_data = wallet_ptr.to_str().unwrap();
-    let tuple_wallet_data: (i64, Option<SecretKey>) = serde_json::from_str(wallet_data).unwrap();
-    let wlt = tuple_wallet_data.0;
-    let sek_key = tuple_wallet_data.1;
-
-    ensure_wallet!(wlt, wallet);
-
-    let result = match _get_tx_fees(
-        &wallet,
-        sek_key,
-        amount,
-        minimum_confirmations,
-    ) {
-        Ok(fees) => {
-            fees
-        }, Err(e ) => {
-            let error_msg = format!("Error {}", &e.to_string());
-            let error_msg_ptr = CString::new(error_msg).unwrap();
-            let ptr = error_msg_ptr.as_ptr();
-            std::mem::forget(error_msg_ptr);
-            ptr
-        }
-    };
-    result
+    guard(|| {
+        let session_id: SessionId = arg_parse(wallet, "wallet")?;
+        let amount: u64 = arg_parse(c_amount, "amount")?;
+        let minimum_confirmations: u64 = arg_parse(min_confirmations, "min_confirmations")?;
+
+        ensure_wallet!(session_id, wallet, sek_key);
+
+        Ok(_get_tx_fees(
+            &wallet,
+            sek_key,
+            amount,
+            minimum_confirmations,
+        )?)
+    })
 }
 
 /// A helper to get transaction fees.
-fn _get_tx_fees(
+pub(crate) fn _get_tx_fees(
     wallet: &Wallet,
     keychain_mask: Option<SecretKey>,
     amount: u64,
     minimum_confirmations: u64,
-) -> Result<*const c_char, Error> {
-    let mut fees_data = "".to_string();
-    match tx_strategies(wallet, keychain_mask, amount, minimum_confirmations) {
-        Ok(fees) => {
-            fees_data.push_str(&fees);
-        }, Err(e) => {
-            return Err(e);
-        }
-    }
-    let s = CString::new(fees_data).unwrap();
-    let p = s.as_ptr();
-    std::mem::forget(s); // Give up the responsibility of cleaning up/freeing s.
-    Ok(p)
+) -> Result<Value, Error> {
+    let fees = tx_strategies(wallet, keychain_mask, amount, minimum_confirmations)?;
+    Ok(serde_json::from_str(&fees)?)
+}
+
+/// Get the largest amount a sweep can send via FFI.
+///
+/// Returns `{amount, fee, inputs, spendable}`, where `amount` is the spendable
+/// balance less the fee for spending every output with no change.
+#[no_mangle]
+pub unsafe extern "C" fn rust_max_sendable(
+    wallet: *const c_char,
+    min_confirmations: *const c_char,
+) -> *const c_char {
+    guard(|| {
+        let session_id: SessionId = arg_parse(wallet, "wallet")?;
+        let minimum_confirmations: u64 = arg_parse(min_confirmations, "min_confirmations")?;
+
+        ensure_wallet!(session_id, wallet, _sek_key);
+
+        Ok(_max_sendable(wallet, minimum_confirmations)?)
+    })
+}
+
+/// A helper to get the largest amount a sweep can send.
+pub(crate) fn _max_sendable(wallet: &Wallet, minimum_confirmations: u64) -> Result<Value, Error> {
+    let max = max_sendable(wallet, minimum_confirmations)?;
+    Ok(serde_json::to_value(&max)?)
 }
 
 /// Start a listener via FFI.
+///
+/// Returns null if an argument is malformed; the listener itself reports a bad
+/// epicbox config or an unknown session when it runs.
 #[no_mangle]
 pub unsafe extern "C" fn rust_epicbox_listener_start(
     wallet: *const c_char,
     epicbox_config: *const c_char,
 ) -> *mut c_void {
-    let wallet_ptr = CStr::from_ptr(wallet);
-    let epicbox_config = CStr::from_ptr(epicbox_config);
-    let epicbox_config = epicbox_config.to_str().unwrap();
-
-    let wallet_data = wallet_ptr.to_str().unwrap();
-    // let tuple_wallet_data: (i64, Option<SecretKey>) = serde_json::from_str(wallet_data).unwrap();
-    let listen = Listener {
-        wallet_ptr_str: wallet_data.to_string(),
-        epicbox_config: epicbox_config.parse().unwrap()
-    };
-
-    let handler = listener_spawn(&listen);
-    let handler_value = handler.read();
-    let boxed_handler = Box::new(handler_value);
-    Box::into_raw(boxed_handler) as *mut _
+    let started = panic::catch_unwind(AssertUnwindSafe(|| -> Result<*mut c_void, FfiError> {
+        let session_id: SessionId = arg_parse(wallet, "wallet")?;
+        let epicbox_config = arg_str(epicbox_config, "epicbox_config")?;
+        let listen = Listener {
+            session_id,
+            epicbox_config: epicbox_config.to_string()
+        };
+
+        let handler = listener_spawn(&listen);
+        let handler_value = handler.read();
+        let boxed_handler = Box::new(handler_value);
+        Ok(Box::into_raw(boxed_handler) as *mut _)
+    }));
+    match started {
+        Ok(Ok(handler)) => handler,
+        Ok(Err(e)) => {
+            error!("Could not start epicbox listener: {}", e);
+            ptr::null_mut()
+        }
+        Err(payload) => {
+            panic_error(payload);
+            ptr::null_mut()
+        }
+    }
 }
 
 /// Cancel and destroy a listener via FFI.
 /// This cancels the listener task and frees the associated handle memory.
+/// The envelope data is `true` if the listener was cancelled, `false` otherwise.
 #[no_mangle]
 pub unsafe extern "C" fn _listener_cancel(handler: *mut c_void) -> *const c_char {
-    // Validate handler is not null
-    if handler.is_null() {
-        let error_msg = CString::new("false").unwrap();
-        let ptr = error_msg.as_ptr();
-        std::mem::forget(error_msg);
-        return ptr;
-    }
+    guard(|| {
+        // Validate handler is not null
+        if handler.is_null() {
+            return Ok(Value::Bool(false));
+        }
 
-    let handle = handler as *mut TaskHandle<usize>;
+        let handle = handler as *mut TaskHandle<usize>;
 
-    // Request cancellation of the listener task
-    listener_cancel(handle);
-    let was_cancelled = listener_cancelled(handle);
+        // Request cancellation of the listener task
+        listener_cancel(handle);
+        let was_cancelled = listener_cancelled(handle);
 
-    // Destroy the handle to free resources
-    // Note: listener_handle_destroy takes ownership and frees the memory,
-    // so we must NOT also call Box::from_raw (that would be a double-free)
-    listener_handle_destroy(handle);
+        // Destroy the handle to free resources
+        // Note: listener_handle_destroy takes ownership and frees the memory,
+        // so we must NOT also call Box::from_raw (that would be a double-free)
+        listener_handle_destroy(handle);
 
-    let error_msg = format!("{}", was_cancelled);
-    let error_msg_ptr = CString::new(error_msg).unwrap();
-    let ptr = error_msg_ptr.as_ptr();
-    std::mem::forget(error_msg_ptr);
-    ptr
+        Ok(Value::Bool(was_cancelled != 0))
+    })
 }
 
 /// Check if the listener is still running via FFI.
-/// Returns "true" if the listener is alive (task not completed), "false" if it has stopped.
-/// Returns "false" if the handler is null.
+/// The envelope data is `true` if the listener is alive (task not completed), `false` if it has stopped.
+/// Returns `false` if the handler is null.
 #[no_mangle]
 pub unsafe extern "C" fn _listener_is_running(handler: *mut c_void) -> *const c_char {
-    // Validate handler is not null
-    if handler.is_null() {
-        let result = CString::new("false").unwrap();
-        let ptr = result.as_ptr();
-        std::mem::forget(result);
-        return ptr;
+    guard(|| {
+        // Validate handler is not null
+        if handler.is_null() {
+            return Ok(Value::Bool(false));
+        }
+
+        let handle = handler as *mut TaskHandle<usize>;
+
+        // Poll the task to check if it's still running
+        // listener_poll returns a null pointer if the task is still running,
+        // or a non-null pointer to the result if the task has completed
+        let poll_result = listener_poll(handle);
+        let is_running = poll_result.is_null();
+
+        Ok(Value::Bool(is_running))
+    })
+}
+
+/// Spawn a wallet task from checked arguments, handing its handle to the caller.
+///
+/// Returns null, and logs why, if the arguments are malformed.
+fn start_task<F>(body: F) -> *mut c_void
+where
+    F: FnOnce() -> Result<WalletTask, FfiError>,
+{
+    let task = panic::catch_unwind(AssertUnwindSafe(body))
+        .unwrap_or_else(|payload| Err(panic_error(payload)));
+    match task {
+        Ok(task) => Box::into_raw(Box::new(WalletTaskHandle::spawn(&task))) as *mut c_void,
+        Err(e) => {
+            error!("Could not start wallet task: {}", e);
+            ptr::null_mut()
+        }
     }
+}
+
+/// Start scanning outputs on a background thread via FFI.
+///
+/// Cancelling the task stops the scan after the current batch of blocks, failing
+/// as `cancelled` with `last_scanned_height` in the details. The task result is
+/// the last scanned height.
+#[no_mangle]
+pub unsafe extern "C" fn rust_scan_outputs_task_start(
+    wallet: *const c_char,
+    start_height: *const c_char,
+    number_of_blocks: *const c_char,
+) -> *mut c_void {
+    start_task(|| {
+        Ok(WalletTask::new(
+            arg_parse(wallet, "wallet")?,
+            Operation::Scan {
+                start_height: arg_parse(start_height, "start_height")?,
+                number_of_blocks: arg_parse(number_of_blocks, "number_of_blocks")?,
+            },
+        ))
+    })
+}
 
-    let handle = handler as *mut TaskHandle<usize>;
+/// Start refreshing outputs and transactions from the node via FFI.
+///
+/// The task result is the refreshed transaction list.
+#[no_mangle]
+pub unsafe extern "C" fn rust_refresh_task_start(wallet: *const c_char) -> *mut c_void {
+    start_task(|| {
+        Ok(WalletTask::new(
+            arg_parse(wallet, "wallet")?,
+            Operation::Refresh,
+        ))
+    })
+}
 
-    // Poll the task to check if it's still running
-    // listener_poll returns a null pointer if the task is still running,
-    // or a non-null pointer to the result if the task has completed
-    let poll_result = listener_poll(handle);
-    let is_running = poll_result.is_null();
+/// Start creating and sending a transaction over epicbox via FFI.
+///
+/// Takes the same arguments as [`rust_create_tx`], and the task result is the
+/// same as its result.
+#[no_mangle]
+pub unsafe extern "C" fn rust_send_task_start(
+    wallet: *const c_char,
+    amount: *const c_char,
+    to_address: *const c_char,
+    epicbox_config: *const c_char,
+    confirmations: *const c_char,
+    note: *const c_char,
+    return_slate_flag: *const c_char,
+) -> *mut c_void {
+    start_task(|| {
+        Ok(WalletTask::new(
+            arg_parse(wallet, "wallet")?,
+            Operation::Send {
+                amount: arg_parse(amount, "amount")?,
+                address: arg_str(to_address, "to_address")?.to_string(),
+                epicbox_config: arg_str(epicbox_config, "epicbox_config")?.to_string(),
+                minimum_confirmations: arg_parse(confirmations, "confirmations")?,
+                note: arg_str(note, "note")?.to_string(),
+                return_slate: arg_flag(return_slate_flag, "return_slate_flag")?,
+            },
+        ))
+    })
+}
 
-    let result = CString::new(if is_running { "true" } else { "false" }).unwrap();
-    let ptr = result.as_ptr();
-    std::mem::forget(result);
-    ptr
+/// Start creating and sending a transaction over http via FFI.
+///
+/// Takes the same arguments as [`rust_tx_send_http`], and the task result is
+/// the same as its result.
+#[no_mangle]
+pub unsafe extern "C" fn rust_send_http_task_start(
+    wallet: *const c_char,
+    selection_strategy_is_use_all: *const c_char,
+    minimum_confirmations: *const c_char,
+    message: *const c_char,
+    amount: *const c_char,
+    address: *const c_char,
+) -> *mut c_void {
+    start_task(|| {
+        Ok(WalletTask::new(
+            arg_parse(wallet, "wallet")?,
+            Operation::SendHttp {
+                amount: arg_parse(amount, "amount")?,
+                address: arg_str(address, "address")?.to_string(),
+                selection_strategy_is_use_all: arg_flag(selection_strategy_is_use_all, "selection_strategy_is_use_all")?,
+                minimum_confirmations: arg_parse(minimum_confirmations, "minimum_confirmations")?,
+                message: arg_str(message, "message")?.to_string(),
+            },
+        ))
+    })
+}
+
+/// Start finalizing and posting a slate via FFI.
+///
+/// The task result is the same as the result of [`rust_tx_finalize`].
+#[no_mangle]
+pub unsafe extern "C" fn rust_finalize_task_start(
+    wallet: *const c_char,
+    slate_json: *const c_char,
+) -> *mut c_void {
+    start_task(|| {
+        Ok(WalletTask::new(
+            arg_parse(wallet, "wallet")?,
+            Operation::Finalize {
+                slate_json: arg_str(slate_json, "slate_json")?.to_string(),
+            },
+        ))
+    })
+}
+
+/// The wallet task behind a handle from one of the `*_task_start` exports.
+unsafe fn task_handle<'a>(handle: *mut c_void) -> Result<&'a WalletTaskHandle, FfiError> {
+    if handle.is_null() {
+        return Err(FfiError::new(ErrorCode::InvalidArgument, "handle is null"));
+    }
+    Ok(&*(handle as *const WalletTaskHandle))
+}
+
+/// Poll a wallet task via FFI.
+///
+/// Returns null while the task is running. Once it has finished, returns its
+/// result envelope, exactly once; later polls return null again.
+#[no_mangle]
+pub unsafe extern "C" fn rust_task_poll(handle: *mut c_void) -> *const c_char {
+    let polled = panic::catch_unwind(AssertUnwindSafe(|| task_handle(handle).map(|task| task.poll())))
+        .unwrap_or_else(|payload| Err(panic_error(payload)));
+    match polled {
+        // The task output is already an envelope.
+        Ok(Some(envelope_json)) => CString::new(envelope_json).unwrap().into_raw(),
+        Ok(None) => ptr::null(),
+        Err(e) => envelope_ptr(Err(e)),
+    }
+}
+
+/// Wait for a wallet task to finish via FFI, returning its result envelope.
+///
+/// Blocks the calling thread. The handle must still be destroyed afterwards.
+/// Fails with a `lifecycle` error if the result was already handed out by
+/// [`rust_task_poll`] or an earlier wait.
+#[no_mangle]
+pub unsafe extern "C" fn rust_task_wait(handle: *mut c_void) -> *const c_char {
+    let waited = panic::catch_unwind(AssertUnwindSafe(|| task_handle(handle)?.wait()))
+        .unwrap_or_else(|payload| Err(panic_error(payload)));
+    match waited {
+        // The task output is already an envelope.
+        Ok(envelope_json) => CString::new(envelope_json).unwrap().into_raw(),
+        Err(e) => envelope_ptr(Err(e)),
+    }
+}
+
+/// Ask a wallet task to stop via FFI.
+///
+/// Operations stop at the next point where it is safe to do so; a transaction
+/// that is already being sent is not interrupted. The envelope data is `true`
+/// once cancellation has been requested. A task being waited on can be
+/// cancelled from another thread.
+#[no_mangle]
+pub unsafe extern "C" fn rust_task_cancel(handle: *mut c_void) -> *const c_char {
+    guard(|| {
+        if handle.is_null() {
+            return Ok(Value::Bool(false));
+        }
+        let task = task_handle(handle)?;
+        task.cancel();
+        Ok(Value::Bool(task.cancelled()))
+    })
+}
+
+/// Free a wallet task handle via FFI.
+///
+/// A task that is still running is cancelled. Passing null is a no-op.
+#[no_mangle]
+pub unsafe extern "C" fn rust_task_destroy(handle: *mut c_void) {
+    if handle.is_null() {
+        return;
+    }
+    drop(Box::from_raw(handle as *mut WalletTaskHandle));
 }
 
 /// Receive a slate via FFI.
@@ -1059,49 +1706,27 @@ pub unsafe extern "C" fn rust_tx_receive(
     wallet: *const c_char,
     slate_json: *const c_char,
 ) -> *const c_char {
-    let wallet_str = CStr::from_ptr(wallet).to_str().unwrap();
-    let slate_str = CStr::from_ptr(slate_json).to_str().unwrap();
-
-    let (wlt, sek_key): (i64, Option<SecretKey>) =
-        serde_json::from_str(wallet_str).unwrap();
+    guard(|| {
+        let session_id: SessionId = arg_parse(wallet, "wallet")?;
+        let slate_str = arg_str(slate_json, "slate_json")?;
 
-    ensure_wallet!(wlt, wallet);
+        ensure_wallet!(session_id, wallet, sek_key);
 
-    match _tx_receive(wallet, sek_key, slate_str) {
-        Ok(ptr) => ptr,
-        Err(e) => {
-            let err = CString::new(format!("Error {}", e)).unwrap();
-            let p = err.as_ptr();
-            std::mem::forget(err);
-            p
-        }
-    }
+        Ok(_tx_receive(wallet, sek_key, slate_str)?)
+    })
 }
 
 /// Helper for tx_receive.
-fn _tx_receive(
+pub(crate) fn _tx_receive(
     wallet: &Wallet,
     keychain_mask: Option<SecretKey>,
     slate_json: &str,
-) -> Result<*const c_char, Error> {
-    let mut out = String::new();
-
-    match tx_receive(wallet, keychain_mask, slate_json, None) {
-        Ok(processed_slate) => {
-            // Keep the outer API uniform with (<slate>, {"slate_msg":""}).
-            let empty_json = r#"{"slate_msg": ""}"#;
-            let response_tuple = (&processed_slate, &empty_json);
-            out.push_str(&serde_json::to_string(&response_tuple).unwrap());
-        }
-        Err(e) => {
-            return Err(e);
-        }
-    }
-
-    let c_out = CString::new(out).unwrap();
-    let p = c_out.as_ptr();
-    std::mem::forget(c_out);
-    Ok(p)
+) -> Result<Value, Error> {
+    let processed_slate = tx_receive(wallet, keychain_mask, slate_json, None)?;
+    // Keep the outer API uniform with (<slate>, {"slate_msg":""}).
+    let empty_json = r#"{"slate_msg": ""}"#;
+    let response_tuple = (&processed_slate, &empty_json);
+    Ok(serde_json::to_value(&response_tuple)?)
 }
 
 /// Finalize a slate via FFI.
@@ -1114,55 +1739,35 @@ pub unsafe extern "C" fn rust_tx_finalize(
     wallet: *const c_char,
     slate_json: *const c_char,
 ) -> *const c_char {
-    let wallet_str = CStr::from_ptr(wallet).to_str().unwrap();
-    let slate_str = CStr::from_ptr(slate_json).to_str().unwrap();
-
-    let (wlt, sek_key): (i64, Option<SecretKey>) =
-        serde_json::from_str(wallet_str).unwrap();
-
-    ensure_wallet!(wlt, wallet);
-
-    match _tx_finalize(wallet, sek_key, slate_str) {
-        Ok(ptr) => ptr,
-        Err(e) => {
-            let err = CString::new(format!("Error {}", e)).unwrap();
-            let p = err.as_ptr();
-            std::mem::forget(err);
-            p
-        }
-    }
+    guard(|| {
+        let session_id: SessionId = arg_parse(wallet, "wallet")?;
+        let slate_str = arg_str(slate_json, "slate_json")?;
+
+        session::get_session(session_id)?.spend(|| {
+            ensure_wallet!(session_id, wallet, sek_key);
+            Ok(_tx_finalize(wallet, sek_key, slate_str)?)
+        })
+    })
 }
 
 /// Helper for tx_finalize.
-fn _tx_finalize(
+pub(crate) fn _tx_finalize(
     wallet: &Wallet,
     keychain_mask: Option<SecretKey>,
     slate_json: &str,
-) -> Result<*const c_char, Error> {
-    let mut out = String::new();
-
-    match tx_finalize(wallet, keychain_mask, slate_json) {
-        Ok(finalized_slate) => {
-            // Same tuple shape as elsewhere.
-            let empty_json = r#"{"slate_msg": ""}"#;
-            let response_tuple = (&finalized_slate, &empty_json);
-            out.push_str(&serde_json::to_string(&response_tuple).unwrap());
-        }
-        Err(e) => {
-            return Err(e);
-        }
-    }
-
-    let c_out = CString::new(out).unwrap();
-    let p = c_out.as_ptr();
-    std::mem::forget(c_out);
-    Ok(p)
+) -> Result<Value, Error> {
+    let finalized_slate = tx_finalize(wallet, keychain_mask, slate_json)?;
+    // Same tuple shape as elsewhere.
+    let empty_json = r#"{"slate_msg": ""}"#;
+    let response_tuple = (&finalized_slate, &empty_json);
+    Ok(serde_json::to_value(&response_tuple)?)
 }
 
 #[cfg(test)]
 mod mnemonic_tests {
     use super::*;
     use std::collections::HashSet;
+    use crate::mnemonic::{create_seed, _get_mnemonic};
 
     // Test the create_seed function.
     #[test]
@@ -1284,3 +1889,34 @@ mod mnemonic_tests {
         }
     }
 }
+
+#[cfg(test)]
+mod guard_tests {
+    use super::*;
+
+    unsafe fn take_envelope(ptr: *const c_char) -> Value {
+        let raw = CStr::from_ptr(ptr).to_str().unwrap().to_string();
+        rust_string_free(ptr as *mut c_char);
+        serde_json::from_str(&raw).unwrap()
+    }
+
+    #[test]
+    fn test_panic_becomes_error_envelope() {
+        let envelope = unsafe { take_envelope(guard(|| panic!("boom {}", 1))) };
+        assert_eq!(envelope["ok"], false);
+        assert_eq!(envelope["error"]["kind"], "panic");
+        assert_eq!(envelope["error"]["message"], "boom 1");
+    }
+
+    #[test]
+    fn test_arg_helpers_reject_bad_input() {
+        let number = CString::new("42").unwrap();
+        let word = CString::new("forty-two").unwrap();
+        unsafe {
+            assert_eq!(arg_parse::<u64>(number.as_ptr(), "n").unwrap(), 42);
+            assert!(arg_flag(number.as_ptr(), "n").unwrap());
+            assert_eq!(arg_str(ptr::null(), "n").unwrap_err().code, ErrorCode::InvalidArgument);
+            assert_eq!(arg_parse::<u64>(word.as_ptr(), "n").unwrap_err().code, ErrorCode::InvalidArgument);
+        }
+    }
+}
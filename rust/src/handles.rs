@@ -0,0 +1,161 @@
+use std::sync::Arc;
+
+use crate::envelope::{ErrorCode, FfiError};
+
+/// A slot in a [`HandleTable`].
+struct Slot<T> {
+    generation: u32,
+    value: Option<Arc<T>>,
+}
+
+/// Generation-checked table of values addressed by opaque `u64` handles.
+///
+/// The low 32 bits of a handle are a slot index and the high 32 bits are the
+/// slot's generation. Removing a value bumps the generation of its slot, so a
+/// stale handle is rejected instead of resolving to whatever reuses the slot.
+/// `0` is never a valid handle.
+pub struct HandleTable<T> {
+    slots: Vec<Slot<T>>,
+    free: Vec<u32>,
+}
+
+fn pack(index: u32, generation: u32) -> u64 {
+    ((generation as u64) << 32) | index as u64
+}
+
+fn unpack(handle: u64) -> (u32, u32) {
+    (handle as u32, (handle >> 32) as u32)
+}
+
+fn invalid_handle(handle: u64) -> FfiError {
+    FfiError::new(
+        ErrorCode::InvalidHandle,
+        format!("Handle {} is not open", handle),
+    )
+}
+
+impl<T> HandleTable<T> {
+    pub const fn new() -> Self {
+        HandleTable {
+            slots: Vec::new(),
+            free: Vec::new(),
+        }
+    }
+
+    /// Store a value and return its handle.
+    pub fn insert(&mut self, value: T) -> u64 {
+        let value = Some(Arc::new(value));
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.value = value;
+                pack(index, slot.generation)
+            }
+            None => {
+                let index = self.slots.len() as u32;
+                self.slots.push(Slot { generation: 1, value });
+                pack(index, 1)
+            }
+        }
+    }
+
+    /// Look up the value behind a handle.
+    pub fn get(&self, handle: u64) -> Result<Arc<T>, FfiError> {
+        let (index, generation) = unpack(handle);
+        match self.slots.get(index as usize) {
+            Some(slot) if slot.generation == generation => {
+                slot.value.clone().ok_or_else(|| invalid_handle(handle))
+            }
+            _ => Err(invalid_handle(handle)),
+        }
+    }
+
+    /// Remove the value behind a handle, invalidating the handle.
+    pub fn remove(&mut self, handle: u64) -> Result<Arc<T>, FfiError> {
+        let (index, generation) = unpack(handle);
+        let slot = match self.slots.get_mut(index as usize) {
+            Some(slot) if slot.generation == generation => slot,
+            _ => return Err(invalid_handle(handle)),
+        };
+        let value = slot.value.take().ok_or_else(|| invalid_handle(handle))?;
+        // Generation 0 is reserved so that handle 0 never resolves.
+        slot.generation = match slot.generation.wrapping_add(1) {
+            0 => 1,
+            g => g,
+        };
+        self.free.push(index);
+        Ok(value)
+    }
+
+    /// The handle and value of the first live value matching `predicate`.
+    pub fn find(&self, predicate: impl Fn(&T) -> bool) -> Option<(u64, Arc<T>)> {
+        self.slots.iter().enumerate().find_map(|(index, slot)| match &slot.value {
+            Some(value) if predicate(value) => Some((pack(index as u32, slot.generation), value.clone())),
+            _ => None,
+        })
+    }
+
+    /// Number of live values.
+    pub fn len(&self) -> usize {
+        self.slots.len() - self.free.len()
+    }
+}
+
+#[cfg(test)]
+mod handles_tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_get_remove() {
+        let mut table = HandleTable::new();
+        let handle = table.insert(7u32);
+        assert_eq!(*table.get(handle).unwrap(), 7);
+        assert_eq!(table.len(), 1);
+
+        assert_eq!(*table.remove(handle).unwrap(), 7);
+        assert_eq!(table.len(), 0);
+    }
+
+    #[test]
+    fn test_find() {
+        let mut table = HandleTable::new();
+        let first = table.insert(1u32);
+        let second = table.insert(2u32);
+        let (handle, value) = table.find(|value| *value == 2).unwrap();
+        assert_eq!((handle, *value), (second, 2));
+        table.remove(first).unwrap();
+        assert!(table.find(|value| *value == 1).is_none());
+    }
+
+    #[test]
+    fn test_stale_handle_is_rejected() {
+        let mut table = HandleTable::new();
+        let stale = table.insert(1u32);
+        table.remove(stale).unwrap();
+
+        // The slot is reused, but the old handle must not resolve to the new value.
+        let fresh = table.insert(2u32);
+        assert_ne!(stale, fresh);
+        assert_eq!(table.get(stale).unwrap_err().code, ErrorCode::InvalidHandle);
+        assert_eq!(table.remove(stale).unwrap_err().code, ErrorCode::InvalidHandle);
+        assert_eq!(*table.get(fresh).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_unknown_handles_are_rejected() {
+        let mut table = HandleTable::new();
+        table.insert(1u32);
+        assert!(table.get(0).is_err());
+        assert!(table.get(u64::MAX).is_err());
+        assert!(table.get(pack(5, 1)).is_err());
+    }
+
+    #[test]
+    fn test_value_outlives_removal_while_in_use() {
+        let mut table = HandleTable::new();
+        let handle = table.insert(String::from("in use"));
+        let in_use = table.get(handle).unwrap();
+        table.remove(handle).unwrap();
+        assert_eq!(*in_use, "in use");
+    }
+}
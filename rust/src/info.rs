@@ -0,0 +1,50 @@
+use epic_wallet_libwallet::api_impl::foreign;
+use serde_json::{json, Map, Value};
+
+use crate::envelope::{FfiError, ENVELOPE_VERSION};
+
+/// Ways this library can exchange slates with another wallet.
+pub const TRANSPORTS: &[&str] = &["epicbox", "http", "file"];
+
+/// Entries of a comma-separated list set by the build script.
+fn build_list(list: &'static str) -> impl Iterator<Item = &'static str> {
+    list.split(',').filter(|entry| !entry.is_empty())
+}
+
+/// Describe this build of the library: its version, the git revisions of the
+/// pinned epic dependencies, the slate versions it speaks, the cargo features
+/// it was built with and the transports it supports.
+pub fn lib_info() -> Result<Value, FfiError> {
+    let revisions: Map<String, Value> = build_list(env!("EPIC_DEPENDENCY_REVISIONS"))
+        .filter_map(|entry| entry.split_once('='))
+        .map(|(name, revision)| (name.to_string(), Value::from(revision)))
+        .collect();
+    let version_info = serde_json::to_value(foreign::check_version())?;
+
+    Ok(json!({
+        "name": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "envelope_version": ENVELOPE_VERSION,
+        "foreign_api_version": version_info["foreign_api_version"],
+        "supported_slate_versions": version_info["supported_slate_versions"],
+        "dependency_revisions": revisions,
+        "features": build_list(env!("EPIC_CASH_FEATURES")).collect::<Vec<_>>(),
+        "transports": TRANSPORTS,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lib_info() {
+        let info = lib_info().unwrap();
+        assert_eq!(info["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(info["envelope_version"], ENVELOPE_VERSION);
+        assert!(!info["supported_slate_versions"].as_array().unwrap().is_empty());
+        assert!(info["dependency_revisions"]["epic_wallet_libwallet"].is_string(), "{}", info);
+        assert!(info["dependency_revisions"]["epic_core"].is_string(), "{}", info);
+        assert_eq!(info["transports"], json!(["epicbox", "http", "file"]));
+    }
+}
@@ -18,39 +18,50 @@ use crate::ffi::rust_get_wallet_address;
 use crate::ffi::rust_get_tx_fees;
 use crate::ffi::rust_delete_wallet;
 
-use android_logger::FilterBuilder;
 pub mod config;
 pub mod mnemonic;
 pub mod wallet;
 pub mod listener;
+pub mod envelope;
+pub mod handles;
+pub mod session;
+pub mod status;
+pub mod rpc;
+pub mod info;
+pub mod logging;
+pub mod backup;
+pub mod manager;
 
+#[cfg(test)]
+mod test_utils;
+
+/// Look up an open wallet session by id, binding its wallet and keychain mask,
+/// and optionally a sender for its status messages along with a stop token for
+/// the operation. Returns an `invalid_handle` error from the enclosing function
+/// if the id is unknown or stale.
 #[macro_export]
 macro_rules! ensure_wallet (
-    ($wallet_ptr:expr, $wallet:ident) => (
-        if ($wallet_ptr as *mut Wallet).as_mut().is_none() {
-            println!("{}", "WALLET_IS_NOT_OPEN");
-        }
-        let $wallet = ($wallet_ptr as *mut Wallet).as_mut().unwrap();
+    ($session_id:expr, $wallet:ident, $keychain_mask:ident) => (
+        let session = $crate::session::get_session($session_id)?;
+        let $wallet: &$crate::wallet::Wallet = session.wallet();
+        let $keychain_mask = session.keychain_mask();
+    );
+    ($session_id:expr, $wallet:ident, $keychain_mask:ident, $status_send:ident, $stop:ident) => (
+        let session = $crate::session::get_session($session_id)?;
+        let $wallet: &$crate::wallet::Wallet = session.wallet();
+        let $keychain_mask = session.keychain_mask();
+        let $status_send = session.status_sender();
+        let $stop = session.begin_operation();
     )
 );
 
-fn init_logger() {
-    android_logger::init_once(
-        AndroidConfig::default()
-            .with_min_level(Level::Trace)
-            .with_tag("libepiccash")
-            .with_filter(FilterBuilder::new().parse("debug,epic-cash-wallet::crate=super").build()),
-    );
-}
-
 #[macro_use] extern crate log;
 extern crate android_logger;
 extern crate simplelog;
 
-use log::Level;
-use android_logger::Config as AndroidConfig;
-
 pub mod ffi;
+pub mod abi;
+pub mod tasks;
 
 #[cfg(test)]
 mod tests {
@@ -66,6 +77,7 @@ mod tests {
     use crate::wallet::get_wallet_info;
     use crate::wallet::convert_deci_to_nano;
     use crate::wallet::nano_to_deci;
+    use crate::ffi::rust_string_free;
 
     /// Helper to convert a Rust string to a *const c_char.
     unsafe fn str_to_cchar_ptr(s: &str) -> *const c_char {
@@ -105,9 +117,7 @@ mod tests {
 
         // 3. Generate a new mnemonic.
         let c_mnemonic_ptr = unsafe { get_mnemonic() };
-        let c_mnemonic_str = unsafe { CStr::from_ptr(c_mnemonic_ptr) }
-            .to_str()
-            .expect("Invalid mnemonic UTF-8");
+        let c_mnemonic_str = &unsafe { envelope_string(c_mnemonic_ptr) };
         println!("(no-refresh) Generated Mnemonic: {c_mnemonic_str}");
 
         // 4. Create a new wallet.
@@ -115,9 +125,7 @@ mod tests {
         let creation_result_ptr = unsafe {
             wallet_init(config_ptr, mnemonic_ptr, password_ptr, wallet_name_ptr)
         };
-        let creation_result_str = unsafe {
-            CStr::from_ptr(creation_result_ptr).to_string_lossy().into_owned()
-        };
+        let creation_result_str = unsafe { envelope_string(creation_result_ptr) };
         if creation_result_str.is_empty() {
             println!("(no-refresh) Wallet created successfully.");
         } else {
@@ -126,9 +134,7 @@ mod tests {
 
         // 5. Open the wallet.
         let open_result_ptr = unsafe { rust_open_wallet(config_ptr, password_ptr) };
-        let open_result_str = unsafe {
-            CStr::from_ptr(open_result_ptr).to_string_lossy().into_owned()
-        };
+        let open_result_str = unsafe { envelope_string(open_result_ptr) };
         println!("(no-refresh) Result from opening wallet: {open_result_str}");
 
         // 6. Check wallet balances with refresh=0.
@@ -143,9 +149,7 @@ mod tests {
                 min_confirmations_str_ptr,
             )
         };
-        let balances_str = unsafe {
-            CStr::from_ptr(balances_ptr).to_string_lossy().into_owned()
-        };
+        let balances_str = unsafe { envelope_string(balances_ptr) };
         println!("(no-refresh) Wallet Balances: {balances_str}");
 
         // 7. Clean up: delete the wallet directory.
@@ -155,9 +159,7 @@ mod tests {
                 config_ptr,
             )
         };
-        let delete_str = unsafe {
-            CStr::from_ptr(delete_ptr).to_string_lossy().into_owned()
-        };
+        let delete_str = unsafe { envelope_string(delete_ptr) };
         println!("(no-refresh) Delete wallet result: {delete_str}");
 
         // Remove the ephemeral wallet directory.
@@ -204,16 +206,14 @@ mod tests {
 
         // 2. Generate new mnemonic.
         let mnemonic_ptr = unsafe { get_mnemonic() };
-        let mnemonic_str = unsafe { CStr::from_ptr(mnemonic_ptr).to_string_lossy().into_owned() };
+        let mnemonic_str = unsafe { envelope_string(mnemonic_ptr) };
         println!("(init_open_minimal) Generated mnemonic: {mnemonic_str}");
 
         // 3. Create (init) the wallet with that mnemonic.
         let creation_res_ptr = unsafe {
             wallet_init(config_ptr, c_ptr(&mnemonic_str), password_ptr, name_ptr)
         };
-        let creation_res_str = unsafe {
-            CStr::from_ptr(creation_res_ptr).to_string_lossy().into_owned()
-        };
+        let creation_res_str = unsafe { envelope_string(creation_res_ptr) };
         if creation_res_str.is_empty() {
             println!("(init_open_minimal) Wallet created successfully.");
         } else {
@@ -224,9 +224,7 @@ mod tests {
         let open_res_ptr = unsafe {
             rust_open_wallet(config_ptr, password_ptr)
         };
-        let open_res_str = unsafe {
-            CStr::from_ptr(open_res_ptr).to_string_lossy().into_owned()
-        };
+        let open_res_str = unsafe { envelope_string(open_res_ptr) };
         println!("(init_open_minimal) rust_open_wallet returned: {open_res_str}");
 
         // 5. Clean up wallet data
@@ -269,19 +267,17 @@ mod tests {
 
         // 1. Create and open the wallet.
         let mnemonic_ptr = unsafe { get_mnemonic() };
-        let mnemonic_str = unsafe { CStr::from_ptr(mnemonic_ptr).to_string_lossy().into_owned() };
+        let mnemonic_str = unsafe { envelope_string(mnemonic_ptr) };
         println!("(balances_no_refresh) Generated mnemonic: {mnemonic_str}");
 
         let creation_res_ptr = unsafe {
             wallet_init(config_ptr, c_ptr(&mnemonic_str), password_ptr, c_ptr("test_balances_wallet"))
         };
-        let creation_res_str = unsafe {
-            CStr::from_ptr(creation_res_ptr).to_string_lossy().into_owned()
-        };
+        let creation_res_str = unsafe { envelope_string(creation_res_ptr) };
         println!("(balances_no_refresh) wallet_init result: {creation_res_str}");
 
         let open_res_ptr = unsafe { rust_open_wallet(config_ptr, password_ptr) };
-        let open_res_str = unsafe { CStr::from_ptr(open_res_ptr).to_string_lossy().into_owned() };
+        let open_res_str = unsafe { envelope_string(open_res_ptr) };
         println!("(balances_no_refresh) rust_open_wallet: {open_res_str}");
 
         // 2. Check balances, no refresh.
@@ -291,9 +287,7 @@ mod tests {
         let balances_ptr = unsafe {
             rust_wallet_balances(c_ptr(&open_res_str), refresh_ptr, min_conf_ptr)
         };
-        let balances_str = unsafe {
-            CStr::from_ptr(balances_ptr).to_string_lossy().into_owned()
-        };
+        let balances_str = unsafe { envelope_string(balances_ptr) };
         println!("(balances_no_refresh) wallet balances: {balances_str}");
 
         // 3. Clean up.
@@ -312,6 +306,35 @@ mod tests {
         ptr
     }
 
+    /// A helper function to read an FFI result envelope.
+    /// Returns the envelope data as a string, or the error message on failure.
+    /// The returned pointer is released with `rust_string_free`.
+    unsafe fn read_envelope(ptr: *const c_char) -> Result<String, String> {
+        let raw = CStr::from_ptr(ptr).to_str().unwrap().to_string();
+        rust_string_free(ptr as *mut c_char);
+        let envelope: serde_json::Value = serde_json::from_str(&raw)
+            .unwrap_or_else(|e| panic!("FFI returned a non-envelope result '{}': {}", raw, e));
+        assert_eq!(envelope["v"], crate::envelope::ENVELOPE_VERSION);
+        if envelope["ok"] == true {
+            Ok(match &envelope["data"] {
+                serde_json::Value::String(s) => s.clone(),
+                data => data.to_string(),
+            })
+        } else {
+            Err(format!(
+                "{}: {}",
+                envelope["error"]["kind"].as_str().unwrap_or(""),
+                envelope["error"]["message"].as_str().unwrap_or("")
+            ))
+        }
+    }
+
+    /// A helper function to read an FFI result envelope for printing.
+    /// Returns the envelope data, or the error message on failure.
+    unsafe fn envelope_string(ptr: *const c_char) -> String {
+        read_envelope(ptr).unwrap_or_else(|e| format!("error {}", e))
+    }
+
     /// A helper function to setup a test directory.
     fn setup_test_dir(name: &str) -> PathBuf {
         let dir = PathBuf::from(format!("test_wallet_dir_{}", name));
@@ -384,7 +407,7 @@ mod tests {
 
             // Get a mnemonic.
             let mnemonic_ptr = get_mnemonic();
-            let mnemonic = CStr::from_ptr(mnemonic_ptr).to_str().unwrap();
+            let mnemonic = &envelope_string(mnemonic_ptr);
             println!("Input Mnemonic: {}", mnemonic);
 
             // Create wallet.
@@ -394,12 +417,12 @@ mod tests {
                 password_ptr,
                 name_ptr
             );
-            let result = CStr::from_ptr(result_ptr).to_str().unwrap();
+            let result = &envelope_string(result_ptr);
             println!("Wallet Creation Result: {}", result);
 
             // Try to open the wallet.
             let open_result_ptr = rust_open_wallet(config_ptr, password_ptr);
-            let open_result = CStr::from_ptr(open_result_ptr).to_str().unwrap();
+            let open_result = &envelope_string(open_result_ptr);
             println!("Wallet Open Result: {}", open_result);
         }
 
@@ -485,12 +508,13 @@ mod tests {
             let height_ptr = rust_get_chain_height(config_ptr);
 
             // Convert the result back to a Rust string.
-            let height_str = CStr::from_ptr(height_ptr).to_str().unwrap();
+            let height_envelope = read_envelope(height_ptr);
+            let height_str = &height_envelope.clone().unwrap_or_else(|e| e);
 
             println!("Chain height returned from FFI: {}", height_str);
 
             // Verify the result.
-            if height_str.starts_with("Error ") {
+            if height_envelope.is_err() {
                 println!("FFI returned an error: {}", height_str);
             } else {
                 // Try to parse as a number.
@@ -526,7 +550,7 @@ mod tests {
 
             // 1. Generate mnemonic and create wallet.
             let mnemonic_ptr = get_mnemonic();
-            let mnemonic_str = CStr::from_ptr(mnemonic_ptr).to_str().unwrap();
+            let mnemonic_str = &envelope_string(mnemonic_ptr);
             println!("Generated mnemonic for scan test");
 
             let creation_ptr = wallet_init(
@@ -535,12 +559,12 @@ mod tests {
                 password_ptr,
                 name_ptr
             );
-            let creation_result = CStr::from_ptr(creation_ptr).to_str().unwrap();
+            let creation_result = &envelope_string(creation_ptr);
             println!("Wallet creation result: {}", creation_result);
 
             // 2. Open the wallet.
             let open_ptr = rust_open_wallet(config_ptr, password_ptr);
-            let wallet_data = CStr::from_ptr(open_ptr).to_str().unwrap();
+            let wallet_data = &envelope_string(open_ptr);
             println!("Opened wallet: {}", wallet_data);
 
             // 3. Scan outputs from block height 1 for 100 blocks.
@@ -552,12 +576,13 @@ mod tests {
                 start_height_ptr,
                 number_of_blocks_ptr
             );
-            let scan_result = CStr::from_ptr(scan_ptr).to_str().unwrap();
+            let scan_envelope = read_envelope(scan_ptr);
+            let scan_result = &scan_envelope.clone().unwrap_or_else(|e| e);
 
             println!("Scan outputs result: {}", scan_result);
 
             // Verify the result.
-            if scan_result.starts_with("Error ") {
+            if scan_envelope.is_err() {
                 println!("Scan returned error (expected for empty wallet): {}", scan_result);
             } else {
                 // Should return the last scanned height.
@@ -574,7 +599,7 @@ mod tests {
 
             // 4. Clean up.
             let delete_ptr = rust_delete_wallet(str_to_cchar(wallet_data), config_ptr);
-            let delete_result = CStr::from_ptr(delete_ptr).to_str().unwrap();
+            let delete_result = &envelope_string(delete_ptr);
             println!("Delete result: {}", delete_result);
         }
 
@@ -625,7 +650,7 @@ mod tests {
 
                 let address_ptr = str_to_cchar(address);
                 let result_ptr = rust_validate_address(address_ptr);
-                let result_str = CStr::from_ptr(result_ptr).to_str().unwrap();
+                let result_str = &envelope_string(result_ptr);
 
                 println!("FFI returned: {}", result_str);
 
@@ -681,7 +706,7 @@ mod tests {
 
             // 1. Generate mnemonic and create wallet.
             let mnemonic_ptr = get_mnemonic();
-            let mnemonic_str = CStr::from_ptr(mnemonic_ptr).to_str().unwrap();
+            let mnemonic_str = &envelope_string(mnemonic_ptr);
             println!("Generated mnemonic for address test");
 
             let creation_ptr = wallet_init(
@@ -690,12 +715,12 @@ mod tests {
                 password_ptr,
                 name_ptr
             );
-            let creation_result = CStr::from_ptr(creation_ptr).to_str().unwrap();
+            let creation_result = &envelope_string(creation_ptr);
             println!("Wallet creation result: {}", creation_result);
 
             // 2. Open the wallet.
             let open_ptr = rust_open_wallet(config_ptr, password_ptr);
-            let wallet_data = CStr::from_ptr(open_ptr).to_str().unwrap();
+            let wallet_data = &envelope_string(open_ptr);
             println!("Opened wallet");
 
             // 3. Get wallet address at index 0.
@@ -707,7 +732,7 @@ mod tests {
                 index_ptr,
                 epicbox_config_ptr
             );
-            let address = CStr::from_ptr(address_ptr).to_str().unwrap();
+            let address = &envelope_string(address_ptr);
 
             println!("Wallet address at index 0: {}", address);
 
@@ -723,7 +748,7 @@ mod tests {
                 index_ptr_1,
                 epicbox_config_ptr
             );
-            let address_1 = CStr::from_ptr(address_ptr_1).to_str().unwrap();
+            let address_1 = &envelope_string(address_ptr_1);
 
             println!("Wallet address at index 1: {}", address_1);
 
@@ -732,7 +757,7 @@ mod tests {
 
             // 5. Clean up.
             let delete_ptr = rust_delete_wallet(str_to_cchar(wallet_data), config_ptr);
-            let delete_result = CStr::from_ptr(delete_ptr).to_str().unwrap();
+            let delete_result = &envelope_string(delete_ptr);
             println!("Delete result: {}", delete_result);
         }
 
@@ -780,12 +805,12 @@ mod tests {
                 password_ptr,
                 name_ptr
             );
-            let creation_result = CStr::from_ptr(creation_ptr).to_str().unwrap();
+            let creation_result = &envelope_string(creation_ptr);
             println!("Wallet creation result: {}", creation_result);
 
             // 2. Open the wallet.
             let open_ptr = rust_open_wallet(config_ptr, password_ptr);
-            let wallet_data = CStr::from_ptr(open_ptr).to_str().unwrap();
+            let wallet_data = &envelope_string(open_ptr);
             println!("Opened wallet from known mnemonic");
 
             // 3. Get wallet address at index 0.
@@ -797,7 +822,7 @@ mod tests {
                 index_ptr,
                 epicbox_config_ptr
             );
-            let address_index_0 = CStr::from_ptr(address_ptr).to_str().unwrap();
+            let address_index_0 = &envelope_string(address_ptr);
 
             println!("Actual address at index 0: {}", address_index_0);
 
@@ -814,7 +839,7 @@ mod tests {
                 index_ptr_1,
                 epicbox_config_ptr
             );
-            let address_index_1 = CStr::from_ptr(address_ptr_1).to_str().unwrap();
+            let address_index_1 = &envelope_string(address_ptr_1);
 
             println!("Actual address at index 1: {}", address_index_1);
 
@@ -826,7 +851,7 @@ mod tests {
 
             // 5. Create a second wallet with the same mnemonic to verify determinism.
             let delete_ptr = rust_delete_wallet(str_to_cchar(wallet_data), config_ptr);
-            let delete_result = CStr::from_ptr(delete_ptr).to_str().unwrap();
+            let delete_result = &envelope_string(delete_ptr);
             println!("Deleted first wallet: {}", delete_result);
 
             let name_ptr_2 = str_to_cchar("vector_wallet_2");
@@ -836,18 +861,18 @@ mod tests {
                 password_ptr,
                 name_ptr_2
             );
-            let creation_result_2 = CStr::from_ptr(creation_ptr_2).to_str().unwrap();
+            let creation_result_2 = &envelope_string(creation_ptr_2);
             println!("Second wallet creation result: {}", creation_result_2);
 
             let open_ptr_2 = rust_open_wallet(config_ptr, password_ptr);
-            let wallet_data_2 = CStr::from_ptr(open_ptr_2).to_str().unwrap();
+            let wallet_data_2 = &envelope_string(open_ptr_2);
 
             let address_ptr_verify = rust_get_wallet_address(
                 str_to_cchar(wallet_data_2),
                 index_ptr,
                 epicbox_config_ptr
             );
-            let address_verify = CStr::from_ptr(address_ptr_verify).to_str().unwrap();
+            let address_verify = &envelope_string(address_ptr_verify);
 
             println!("Verification address at index 0: {}", address_verify);
 
@@ -859,7 +884,7 @@ mod tests {
 
             // 6. Clean up.
             let delete_ptr_2 = rust_delete_wallet(str_to_cchar(wallet_data_2), config_ptr);
-            let delete_result_2 = CStr::from_ptr(delete_ptr_2).to_str().unwrap();
+            let delete_result_2 = &envelope_string(delete_ptr_2);
             println!("Deleted second wallet: {}", delete_result_2);
         }
 
@@ -883,7 +908,7 @@ mod tests {
 
             // 1. Generate mnemonic and create wallet.
             let mnemonic_ptr = get_mnemonic();
-            let mnemonic_str = CStr::from_ptr(mnemonic_ptr).to_str().unwrap();
+            let mnemonic_str = &envelope_string(mnemonic_ptr);
             println!("Generated mnemonic for fees test");
 
             let creation_ptr = wallet_init(
@@ -892,12 +917,12 @@ mod tests {
                 password_ptr,
                 name_ptr
             );
-            let creation_result = CStr::from_ptr(creation_ptr).to_str().unwrap();
+            let creation_result = &envelope_string(creation_ptr);
             println!("Wallet creation result: {}", creation_result);
 
             // 2. Open the wallet.
             let open_ptr = rust_open_wallet(config_ptr, password_ptr);
-            let wallet_data = CStr::from_ptr(open_ptr).to_str().unwrap();
+            let wallet_data = &envelope_string(open_ptr);
             println!("Opened wallet");
 
             // 3. Test fee calculation for various amounts.
@@ -915,16 +940,17 @@ mod tests {
                     amount_ptr,
                     min_conf_ptr
                 );
-                let fees_result = CStr::from_ptr(fees_ptr).to_str().unwrap();
+                let fees_envelope = read_envelope(fees_ptr);
+                let fees_result = &fees_envelope.clone().unwrap_or_else(|e| e);
 
                 println!("Fees result: {}", fees_result);
 
                 // Verify the result.
-                if fees_result.starts_with("Error ") {
+                if fees_envelope.is_err() {
                     println!("Expected error for empty wallet: {}", fees_result);
                     // Empty wallet should error when trying to calculate fees.
                     assert!(
-                        fees_result.contains("Error"),
+                        !fees_result.is_empty(),
                         "Empty wallet should return error when calculating fees"
                     );
                 } else {
@@ -932,8 +958,16 @@ mod tests {
                     match serde_json::from_str::<serde_json::Value>(fees_result) {
                         Ok(json) => {
                             println!("Successfully parsed fees JSON: {:?}", json);
-                            // Verify it's an array (strategies).
-                            assert!(json.is_array(), "Fees response should be an array");
+                            // Verify there is a quote for every strategy.
+                            let quotes = json.as_array().expect("Fees response should be an array");
+                            assert_eq!(quotes.len(), 2);
+                            assert_eq!(quotes[0]["strategy"], "smallest");
+                            assert_eq!(quotes[1]["strategy"], "all");
+                            for quote in quotes {
+                                // An empty wallet quotes a shortfall instead of failing.
+                                assert!(quote["not_enough_funds"]["needed"].is_u64());
+                                assert!(quote.get("inputs").is_none());
+                            }
                         }
                         Err(e) => {
                             println!("Note: Could not parse fees result as JSON: {}", e);
@@ -944,7 +978,7 @@ mod tests {
 
             // 4. Clean up.
             let delete_ptr = rust_delete_wallet(str_to_cchar(wallet_data), config_ptr);
-            let delete_result = CStr::from_ptr(delete_ptr).to_str().unwrap();
+            let delete_result = &envelope_string(delete_ptr);
             println!("\nDelete result: {}", delete_result);
         }
 
@@ -968,7 +1002,7 @@ mod tests {
 
             // 1. Generate mnemonic and create wallet.
             let mnemonic_ptr = get_mnemonic();
-            let mnemonic_str = CStr::from_ptr(mnemonic_ptr).to_str().unwrap();
+            let mnemonic_str = &envelope_string(mnemonic_ptr);
             println!("Generated mnemonic for txs test");
 
             let creation_ptr = wallet_init(
@@ -977,12 +1011,12 @@ mod tests {
                 password_ptr,
                 name_ptr
             );
-            let creation_result = CStr::from_ptr(creation_ptr).to_str().unwrap();
+            let creation_result = &envelope_string(creation_ptr);
             println!("Wallet creation result: {}", creation_result);
 
             // 2. Open the wallet.
             let open_ptr = rust_open_wallet(config_ptr, password_ptr);
-            let wallet_data = CStr::from_ptr(open_ptr).to_str().unwrap();
+            let wallet_data = &envelope_string(open_ptr);
             println!("Opened wallet");
 
             // 3. Get transactions without refreshing from node.
@@ -993,12 +1027,13 @@ mod tests {
                 str_to_cchar(wallet_data),
                 refresh_ptr_no
             );
-            let txs_result = CStr::from_ptr(txs_ptr).to_str().unwrap();
+            let txs_envelope = read_envelope(txs_ptr);
+            let txs_result = &txs_envelope.clone().unwrap_or_else(|e| e);
 
             println!("Transactions result (no refresh): {}", txs_result);
 
             // Verify the result.
-            if txs_result.starts_with("Error ") {
+            if txs_envelope.is_err() {
                 println!("Error getting transactions: {}", txs_result);
             } else {
                 // Should return valid JSON (likely an empty array for a new wallet).
@@ -1024,7 +1059,7 @@ mod tests {
 
             // 4. Clean up.
             let delete_ptr = rust_delete_wallet(str_to_cchar(wallet_data), config_ptr);
-            let delete_result = CStr::from_ptr(delete_ptr).to_str().unwrap();
+            let delete_result = &envelope_string(delete_ptr);
             println!("\nDelete result: {}", delete_result);
         }
 
@@ -1049,7 +1084,7 @@ mod tests {
 
             // 1. Generate mnemonic and create wallet.
             let mnemonic_ptr = get_mnemonic();
-            let mnemonic_str = CStr::from_ptr(mnemonic_ptr).to_str().unwrap();
+            let mnemonic_str = &envelope_string(mnemonic_ptr);
             println!("Generated mnemonic for cancel test");
 
             let creation_ptr = wallet_init(
@@ -1058,12 +1093,12 @@ mod tests {
                 password_ptr,
                 name_ptr
             );
-            let creation_result = CStr::from_ptr(creation_ptr).to_str().unwrap();
+            let creation_result = &envelope_string(creation_ptr);
             println!("Wallet creation result: {}", creation_result);
 
             // 2. Open the wallet.
             let open_ptr = rust_open_wallet(config_ptr, password_ptr);
-            let wallet_data = CStr::from_ptr(open_ptr).to_str().unwrap();
+            let wallet_data = &envelope_string(open_ptr);
             println!("Opened wallet");
 
             // 3. Attempt to cancel a non-existent transaction.
@@ -1076,16 +1111,17 @@ mod tests {
                 str_to_cchar(wallet_data),
                 tx_id_ptr
             );
-            let cancel_result = CStr::from_ptr(cancel_ptr).to_str().unwrap();
+            let cancel_envelope = read_envelope(cancel_ptr);
+            let cancel_result = &cancel_envelope.clone().unwrap_or_else(|e| e);
 
             println!("Cancel result: {}", cancel_result);
 
             // Verify the result.
             // Since the transaction doesn't exist, we expect an error.
-            if cancel_result.starts_with("Error ") {
+            if cancel_envelope.is_err() {
                 println!("Expected error for non-existent transaction: {}", cancel_result);
                 assert!(
-                    cancel_result.contains("Error"),
+                    !cancel_result.is_empty(),
                     "Cancelling non-existent transaction should return an error"
                 );
             } else if cancel_result.is_empty() {
@@ -1095,13 +1131,16 @@ mod tests {
                 println!("Unexpected cancel result: {}", cancel_result);
             }
 
-            // Note: We don't test invalid UUID formats here because the FFI function
-            // panics on invalid UUIDs (which aborts across FFI boundary).
-            // This is a known limitation - the UUID parsing happens before error handling.
+            // An invalid UUID is rejected with an error rather than a panic.
+            let bad_cancel = read_envelope(rust_tx_cancel(
+                str_to_cchar(wallet_data),
+                str_to_cchar("not-a-uuid")
+            ));
+            assert!(bad_cancel.unwrap_err().starts_with("invalid_argument"));
 
             // 4. Clean up.
             let delete_ptr = rust_delete_wallet(str_to_cchar(wallet_data), config_ptr);
-            let delete_result = CStr::from_ptr(delete_ptr).to_str().unwrap();
+            let delete_result = &envelope_string(delete_ptr);
             println!("\nDelete result: {}", delete_result);
         }
 
@@ -1133,7 +1172,7 @@ mod tests {
 
             // 1. Generate mnemonic and create wallet.
             let mnemonic_ptr = get_mnemonic();
-            let mnemonic_str = CStr::from_ptr(mnemonic_ptr).to_str().unwrap();
+            let mnemonic_str = &envelope_string(mnemonic_ptr);
             println!("Generated mnemonic for tx test");
 
             let creation_ptr = wallet_init(
@@ -1142,12 +1181,12 @@ mod tests {
                 password_ptr,
                 name_ptr
             );
-            let creation_result = CStr::from_ptr(creation_ptr).to_str().unwrap();
+            let creation_result = &envelope_string(creation_ptr);
             println!("Wallet creation result: {}", creation_result);
 
             // 2. Open the wallet.
             let open_ptr = rust_open_wallet(config_ptr, password_ptr);
-            let wallet_data = CStr::from_ptr(open_ptr).to_str().unwrap();
+            let wallet_data = &envelope_string(open_ptr);
             println!("Opened wallet");
 
             // 3. Test rust_create_tx (epicbox transaction).
@@ -1165,16 +1204,18 @@ mod tests {
                 str_to_cchar(secret_key_index),
                 str_to_cchar(&epicbox_config),
                 str_to_cchar(confirmations),
-                str_to_cchar(note)
+                str_to_cchar(note),
+                str_to_cchar("0")
             );
-            let tx_result = CStr::from_ptr(tx_ptr).to_str().unwrap();
+            let tx_envelope = read_envelope(tx_ptr);
+            let tx_result = &tx_envelope.clone().unwrap_or_else(|e| e);
 
             println!("Create tx result: {}", tx_result);
 
             // Should return an error (no funds in wallet).
-            if tx_result.starts_with("Error ") {
+            if tx_envelope.is_err() {
                 println!("Expected error for empty wallet");
-                assert!(tx_result.contains("Error"), "Empty wallet should error when creating tx");
+                assert!(!tx_result.is_empty(), "Empty wallet should error when creating tx");
             } else {
                 println!("Unexpected success creating tx (wallet has no funds)");
             }
@@ -1193,21 +1234,22 @@ mod tests {
                 str_to_cchar(amount),
                 str_to_cchar(http_address)
             );
-            let http_tx_result = CStr::from_ptr(http_tx_ptr).to_str().unwrap();
+            let http_tx_envelope = read_envelope(http_tx_ptr);
+            let http_tx_result = &http_tx_envelope.clone().unwrap_or_else(|e| e);
 
             println!("Send HTTP tx result: {}", http_tx_result);
 
             // Should also return an error (no funds in wallet).
-            if http_tx_result.starts_with("Error ") {
+            if http_tx_envelope.is_err() {
                 println!("Expected error for empty wallet");
-                assert!(http_tx_result.contains("Error"), "Empty wallet should error when sending HTTP tx");
+                assert!(!http_tx_result.is_empty(), "Empty wallet should error when sending HTTP tx");
             } else {
                 println!("Unexpected success sending HTTP tx (wallet has no funds)");
             }
 
             // 5. Clean up.
             let delete_ptr = rust_delete_wallet(str_to_cchar(wallet_data), config_ptr);
-            let delete_result = CStr::from_ptr(delete_ptr).to_str().unwrap();
+            let delete_result = &envelope_string(delete_ptr);
             println!("\nDelete result: {}", delete_result);
         }
 
@@ -1238,7 +1280,7 @@ mod tests {
 
             // 1. Generate mnemonic and create wallet.
             let mnemonic_ptr = get_mnemonic();
-            let mnemonic_str = CStr::from_ptr(mnemonic_ptr).to_str().unwrap();
+            let mnemonic_str = &envelope_string(mnemonic_ptr);
             println!("Generated mnemonic for listener test");
 
             let creation_ptr = wallet_init(
@@ -1247,12 +1289,12 @@ mod tests {
                 password_ptr,
                 name_ptr
             );
-            let creation_result = CStr::from_ptr(creation_ptr).to_str().unwrap();
+            let creation_result = &envelope_string(creation_ptr);
             println!("Wallet creation result: {}", creation_result);
 
             // 2. Open the wallet.
             let open_ptr = rust_open_wallet(config_ptr, password_ptr);
-            let wallet_data = CStr::from_ptr(open_ptr).to_str().unwrap();
+            let wallet_data = &envelope_string(open_ptr);
             println!("Opened wallet");
 
             // 3. Start the epicbox listener.
@@ -1274,12 +1316,12 @@ mod tests {
             // 5. Cancel the listener.
             println!("\nCancelling listener...");
             let cancel_ptr = _listener_cancel(listener_handle);
-            let cancel_result = CStr::from_ptr(cancel_ptr).to_str().unwrap();
+            let cancel_result = &envelope_string(cancel_ptr);
 
             println!("Listener cancel result: {}", cancel_result);
 
             // The result should indicate whether the listener was cancelled.
-            // It returns "true" or "false" as a string.
+            // The envelope data is a boolean.
             if cancel_result == "true" {
                 println!("Listener successfully cancelled");
             } else if cancel_result == "false" {
@@ -1290,11 +1332,1218 @@ mod tests {
 
             // 6. Clean up.
             let delete_ptr = rust_delete_wallet(str_to_cchar(wallet_data), config_ptr);
-            let delete_result = CStr::from_ptr(delete_ptr).to_str().unwrap();
+            let delete_result = &envelope_string(delete_ptr);
             println!("\nDelete result: {}", delete_result);
         }
 
         cleanup_test_dir(&test_dir);
         println!("=== End rust_epicbox_listener FFI test ===");
     }
+
+    /// Test that a session id is rejected once the wallet is closed.
+    #[test]
+    fn test_closed_session_is_rejected() {
+        let test_dir = setup_test_dir("closed_handle");
+        let config_json = create_test_config(&test_dir);
+
+        unsafe {
+            let config = CString::new(config_json).unwrap();
+            let password = CString::new("closed_handle_password").unwrap();
+            let name = CString::new("closed_handle_wallet").unwrap();
+
+            let phrase = CString::new(read_envelope(get_mnemonic()).unwrap()).unwrap();
+            read_envelope(wallet_init(config.as_ptr(), phrase.as_ptr(), password.as_ptr(), name.as_ptr()))
+                .expect("wallet creation failed");
+            let wallet_data = read_envelope(rust_open_wallet(config.as_ptr(), password.as_ptr()))
+                .expect("wallet open failed");
+            // Callers only ever get an opaque session id, never the keychain mask.
+            assert!(wallet_data.parse::<u64>().is_ok(), "expected a bare session id, got {}", wallet_data);
+            let wallet = CString::new(wallet_data).unwrap();
+            let no_refresh = CString::new("0").unwrap();
+            let min_confirmations = CString::new("10").unwrap();
+
+            read_envelope(rust_wallet_balances(wallet.as_ptr(), no_refresh.as_ptr(), min_confirmations.as_ptr()))
+                .expect("balances on an open wallet failed");
+
+            read_envelope(crate::ffi::rust_close_wallet(wallet.as_ptr())).expect("close failed");
+
+            let after_close = read_envelope(
+                rust_wallet_balances(wallet.as_ptr(), no_refresh.as_ptr(), min_confirmations.as_ptr())
+            );
+            assert!(after_close.unwrap_err().starts_with("invalid_handle"));
+
+            let second_close = read_envelope(crate::ffi::rust_close_wallet(wallet.as_ptr()));
+            assert!(second_close.unwrap_err().starts_with("invalid_handle"));
+
+            let _ = read_envelope(rust_delete_wallet(wallet.as_ptr(), config.as_ptr()));
+        }
+
+        cleanup_test_dir(&test_dir);
+    }
+
+    /// Call every envelope-returning export with `arg` in every argument position,
+    /// returning the parsed envelopes by export name.
+    unsafe fn call_every_export(arg: *const c_char) -> Vec<(&'static str, serde_json::Value)> {
+        use crate::ffi::{rust_close_wallet, rust_recover_from_mnemonic, rust_tx_finalize, rust_tx_receive};
+
+        let results = vec![
+            ("wallet_init", wallet_init(arg, arg, arg, arg)),
+            ("rust_open_wallet", rust_open_wallet(arg, arg)),
+            ("rust_wallet_balances", rust_wallet_balances(arg, arg, arg)),
+            ("rust_recover_from_mnemonic", rust_recover_from_mnemonic(arg, arg, arg, arg)),
+            ("rust_wallet_scan_outputs", rust_wallet_scan_outputs(arg, arg, arg)),
+            ("rust_create_tx", rust_create_tx(arg, arg, arg, arg, arg, arg, arg, arg)),
+            ("rust_txs_get", rust_txs_get(arg, arg)),
+            ("rust_tx_cancel", rust_tx_cancel(arg, arg)),
+            ("rust_get_chain_height", rust_get_chain_height(arg)),
+            ("rust_close_wallet", rust_close_wallet(arg)),
+            ("rust_delete_wallet", rust_delete_wallet(arg, arg)),
+            ("rust_tx_send_http", rust_tx_send_http(arg, arg, arg, arg, arg, arg)),
+            ("rust_get_wallet_address", rust_get_wallet_address(arg, arg, arg)),
+            ("rust_validate_address", rust_validate_address(arg)),
+            ("rust_get_tx_fees", rust_get_tx_fees(arg, arg, arg)),
+            ("rust_max_sendable", crate::ffi::rust_max_sendable(arg, arg)),
+            ("rust_tx_receive", rust_tx_receive(arg, arg)),
+            ("rust_tx_finalize", rust_tx_finalize(arg, arg)),
+            ("rust_status_poll", crate::ffi::rust_status_poll(arg)),
+            ("rust_cancel_operation", crate::ffi::rust_cancel_operation(arg)),
+            ("rust_set_idle_timeout", crate::ffi::rust_set_idle_timeout(arg, arg)),
+            ("rust_is_session_locked", crate::ffi::rust_is_session_locked(arg)),
+            ("rust_unlock_wallet", crate::ffi::rust_unlock_wallet(arg, arg)),
+            ("rust_change_password", crate::ffi::rust_change_password(arg, arg, arg)),
+            ("rust_reveal_mnemonic", crate::ffi::rust_reveal_mnemonic(arg, arg)),
+            ("rust_wallet_status", crate::ffi::rust_wallet_status(arg)),
+            ("rust_accounts", crate::ffi::rust_accounts(arg)),
+            ("rust_create_account", crate::ffi::rust_create_account(arg, arg)),
+            ("rust_set_active_account", crate::ffi::rust_set_active_account(arg, arg)),
+            ("rust_backup_wallet", crate::ffi::rust_backup_wallet(arg, arg, arg)),
+            ("rust_restore_wallet_backup", crate::ffi::rust_restore_wallet_backup(arg, arg, arg)),
+            ("rust_manager_list_wallets", crate::ffi::rust_manager_list_wallets(arg)),
+            ("rust_manager_create_wallet", crate::ffi::rust_manager_create_wallet(arg, arg, arg, arg, arg)),
+            ("rust_manager_open_wallet", crate::ffi::rust_manager_open_wallet(arg, arg, arg, arg)),
+            ("rust_manager_rename_wallet", crate::ffi::rust_manager_rename_wallet(arg, arg, arg)),
+            ("rust_manager_delete_wallet", crate::ffi::rust_manager_delete_wallet(arg, arg)),
+            ("rust_owner_rpc", crate::ffi::rust_owner_rpc(arg, arg)),
+            ("rust_foreign_rpc", crate::ffi::rust_foreign_rpc(arg, arg)),
+        ];
+        results
+            .into_iter()
+            .map(|(name, ptr)| {
+                let raw = CStr::from_ptr(ptr).to_str().unwrap().to_string();
+                rust_string_free(ptr as *mut c_char);
+                let envelope: serde_json::Value = serde_json::from_str(&raw)
+                    .unwrap_or_else(|e| panic!("{} returned a non-envelope result '{}': {}", name, raw, e));
+                (name, envelope)
+            })
+            .collect()
+    }
+
+    /// Test that null arguments are rejected by every export.
+    #[test]
+    fn test_exports_reject_null_arguments() {
+        unsafe {
+            for (name, envelope) in call_every_export(std::ptr::null()) {
+                assert_eq!(envelope["ok"], false, "{} accepted null", name);
+                assert_eq!(envelope["error"]["kind"], "invalid_argument", "{}: {}", name, envelope);
+            }
+
+            let listener = rust_epicbox_listener_start(std::ptr::null(), std::ptr::null());
+            assert!(listener.is_null());
+        }
+    }
+
+    /// Test that invalid UTF-8 arguments are rejected by every export.
+    #[test]
+    fn test_exports_reject_invalid_utf8() {
+        let invalid = CStr::from_bytes_with_nul(b"\xff\xfe\xfd\0").unwrap();
+        unsafe {
+            for (name, envelope) in call_every_export(invalid.as_ptr()) {
+                assert_eq!(envelope["ok"], false, "{} accepted invalid UTF-8", name);
+                assert_eq!(envelope["error"]["kind"], "invalid_argument", "{}: {}", name, envelope);
+            }
+
+            let listener = rust_epicbox_listener_start(invalid.as_ptr(), invalid.as_ptr());
+            assert!(listener.is_null());
+        }
+    }
+
+    /// Test that non-numeric strings in numeric positions are rejected.
+    #[test]
+    fn test_exports_reject_non_numeric_arguments() {
+        let not_a_number = CString::new("ten").unwrap();
+        unsafe {
+            for (name, envelope) in call_every_export(not_a_number.as_ptr()) {
+                if name == "rust_validate_address" {
+                    // Any string is a candidate address.
+                    assert_eq!(envelope["ok"], true);
+                    continue;
+                }
+                if name == "rust_manager_list_wallets" {
+                    // Any string is a path, and a missing data directory has no wallets.
+                    assert_eq!(envelope["data"], serde_json::json!([]), "{}", envelope);
+                    continue;
+                }
+                assert_eq!(envelope["ok"], false, "{} accepted a non-numeric argument", name);
+                assert_ne!(envelope["error"]["kind"], "panic", "{}: {}", name, envelope);
+            }
+
+            // Numeric arguments are checked even when the session id is well formed.
+            let session = CString::new("1").unwrap();
+            let ten = CString::new("10").unwrap();
+            let negative = CString::new("-1").unwrap();
+            let cases = [
+                rust_wallet_balances(session.as_ptr(), not_a_number.as_ptr(), ten.as_ptr()),
+                rust_wallet_balances(session.as_ptr(), ten.as_ptr(), not_a_number.as_ptr()),
+                rust_wallet_scan_outputs(session.as_ptr(), not_a_number.as_ptr(), ten.as_ptr()),
+                rust_get_tx_fees(session.as_ptr(), not_a_number.as_ptr(), ten.as_ptr()),
+                rust_get_wallet_address(session.as_ptr(), not_a_number.as_ptr(), ten.as_ptr()),
+                rust_txs_get(session.as_ptr(), not_a_number.as_ptr()),
+                rust_get_wallet_address(session.as_ptr(), negative.as_ptr(), ten.as_ptr()),
+            ];
+            for ptr in cases {
+                assert!(read_envelope(ptr).unwrap_err().starts_with("invalid_argument"));
+            }
+        }
+    }
+
+    /// Test that malformed JSON configs and slates are reported as errors.
+    #[test]
+    fn test_exports_reject_garbage_json() {
+        let test_dir = setup_test_dir("garbage_json");
+        let config_json = create_test_config(&test_dir);
+        let garbage = CString::new("{\"wallet_dir\": [}").unwrap();
+
+        unsafe {
+            for (name, envelope) in call_every_export(garbage.as_ptr()) {
+                // Neither takes JSON: any string is a candidate address, and a
+                // data directory that does not exist has no wallets.
+                if name == "rust_validate_address" || name == "rust_manager_list_wallets" {
+                    continue;
+                }
+                assert_eq!(envelope["ok"], false, "{} accepted garbage JSON", name);
+                assert_ne!(envelope["error"]["kind"], "panic", "{}: {}", name, envelope);
+            }
+
+            let config = CString::new(config_json).unwrap();
+            let password = CString::new("garbage_json_password").unwrap();
+            let name = CString::new("garbage_json_wallet").unwrap();
+
+            let phrase = CString::new(read_envelope(get_mnemonic()).unwrap()).unwrap();
+            read_envelope(wallet_init(config.as_ptr(), phrase.as_ptr(), password.as_ptr(), name.as_ptr()))
+                .expect("wallet creation failed");
+            let wallet = CString::new(
+                read_envelope(rust_open_wallet(config.as_ptr(), password.as_ptr())).expect("wallet open failed")
+            ).unwrap();
+            let index = CString::new("0").unwrap();
+
+            let address = read_envelope(rust_get_wallet_address(wallet.as_ptr(), index.as_ptr(), garbage.as_ptr()));
+            assert!(address.unwrap_err().starts_with("invalid_config"));
+
+            let received = read_envelope(crate::ffi::rust_tx_receive(wallet.as_ptr(), garbage.as_ptr()));
+            assert!(received.is_err());
+            let finalized = read_envelope(crate::ffi::rust_tx_finalize(wallet.as_ptr(), garbage.as_ptr()));
+            assert!(finalized.is_err());
+
+            // The wallet is still usable afterwards.
+            let no_refresh = CString::new("0").unwrap();
+            read_envelope(rust_txs_get(wallet.as_ptr(), no_refresh.as_ptr())).expect("txs_get failed");
+
+            let _ = read_envelope(rust_delete_wallet(wallet.as_ptr(), config.as_ptr()));
+        }
+
+        cleanup_test_dir(&test_dir);
+    }
+
+    /// Test that status messages are polled per session and go away with it.
+    #[test]
+    fn test_status_poll_follows_session() {
+        use crate::ffi::{rust_close_wallet, rust_status_poll};
+
+        let test_dir = setup_test_dir("status_poll");
+        let config_json = create_test_config(&test_dir);
+
+        unsafe {
+            let config = CString::new(config_json).unwrap();
+            let password = CString::new("status_poll_password").unwrap();
+            let name = CString::new("status_poll_wallet").unwrap();
+
+            let phrase = CString::new(read_envelope(get_mnemonic()).unwrap()).unwrap();
+            read_envelope(wallet_init(config.as_ptr(), phrase.as_ptr(), password.as_ptr(), name.as_ptr()))
+                .expect("wallet creation failed");
+            let wallet = CString::new(
+                read_envelope(rust_open_wallet(config.as_ptr(), password.as_ptr())).expect("wallet open failed")
+            ).unwrap();
+
+            let messages = read_envelope(rust_status_poll(wallet.as_ptr())).expect("status poll failed");
+            assert_eq!(messages, "[]");
+
+            read_envelope(rust_close_wallet(wallet.as_ptr())).expect("close failed");
+            let after_close = read_envelope(rust_status_poll(wallet.as_ptr()));
+            assert!(after_close.unwrap_err().starts_with("invalid_handle"));
+
+            let _ = read_envelope(rust_delete_wallet(wallet.as_ptr(), config.as_ptr()));
+        }
+
+        cleanup_test_dir(&test_dir);
+    }
+
+    /// Test that cancelling stops the operations in flight on a session only.
+    #[test]
+    fn test_cancel_operation_stops_in_flight_operations() {
+        use crate::envelope::ErrorCode;
+        use crate::ffi::{rust_cancel_operation, rust_close_wallet};
+        use crate::session::{get_session, SessionId};
+
+        let test_dir = setup_test_dir("cancel_operation");
+        let config_json = create_test_config(&test_dir);
+
+        unsafe {
+            let config = CString::new(config_json).unwrap();
+            let password = CString::new("cancel_operation_password").unwrap();
+            let name = CString::new("cancel_operation_wallet").unwrap();
+
+            let phrase = CString::new(read_envelope(get_mnemonic()).unwrap()).unwrap();
+            read_envelope(wallet_init(config.as_ptr(), phrase.as_ptr(), password.as_ptr(), name.as_ptr()))
+                .expect("wallet creation failed");
+            let session_id = read_envelope(rust_open_wallet(config.as_ptr(), password.as_ptr()))
+                .expect("wallet open failed");
+            let wallet = CString::new(session_id.clone()).unwrap();
+
+            let idle = read_envelope(rust_cancel_operation(wallet.as_ptr())).expect("cancel failed");
+            assert_eq!(idle, "0");
+
+            let session = get_session(session_id.parse::<SessionId>().unwrap()).unwrap();
+            let stop = session.begin_operation();
+            assert!(!stop.is_stopped());
+            let stopped = read_envelope(rust_cancel_operation(wallet.as_ptr())).expect("cancel failed");
+            assert_eq!(stopped, "1");
+            assert!(stop.is_stopped());
+
+            let err = stop
+                .finish::<(), _>(Err(epic_wallet_libwallet::Error::GenericError("stopped".to_string())))
+                .unwrap_err();
+            assert_eq!(err.code, ErrorCode::Cancelled);
+
+            // Finished operations are no longer counted, and new ones start afresh.
+            drop(stop);
+            assert!(!session.begin_operation().is_stopped());
+            let after = read_envelope(rust_cancel_operation(wallet.as_ptr())).expect("cancel failed");
+            assert_eq!(after, "0");
+            drop(session);
+
+            read_envelope(rust_close_wallet(wallet.as_ptr())).expect("close failed");
+            let after_close = read_envelope(rust_cancel_operation(wallet.as_ptr()));
+            assert!(after_close.unwrap_err().starts_with("invalid_handle"));
+
+            let _ = read_envelope(rust_delete_wallet(wallet.as_ptr(), config.as_ptr()));
+        }
+
+        cleanup_test_dir(&test_dir);
+    }
+
+    /// Test that an idle session refuses spends but not reads until unlocked.
+    #[test]
+    fn test_idle_session_locks_spending() {
+        use std::time::Duration;
+        use crate::ffi::{
+            rust_close_wallet, rust_is_session_locked, rust_set_idle_timeout, rust_tx_finalize,
+            rust_unlock_wallet,
+        };
+        use crate::session::{get_session, SessionId};
+
+        let test_dir = setup_test_dir("idle_lock");
+        let config_json = create_test_config(&test_dir);
+
+        unsafe {
+            let config = CString::new(config_json).unwrap();
+            let password = CString::new("idle_lock_password").unwrap();
+            let wrong_password = CString::new("idle_lock_wrong").unwrap();
+            let name = CString::new("idle_lock_wallet").unwrap();
+
+            let phrase = CString::new(read_envelope(get_mnemonic()).unwrap()).unwrap();
+            read_envelope(wallet_init(config.as_ptr(), phrase.as_ptr(), password.as_ptr(), name.as_ptr()))
+                .expect("wallet creation failed");
+            let session_id = read_envelope(rust_open_wallet(config.as_ptr(), password.as_ptr()))
+                .expect("wallet open failed");
+            let wallet = CString::new(session_id.clone()).unwrap();
+            let zero = CString::new("0").unwrap();
+            let hour = CString::new("3600").unwrap();
+            let slate = CString::new("{}").unwrap();
+
+            let locked = read_envelope(rust_is_session_locked(wallet.as_ptr())).expect("lock check failed");
+            assert_eq!(locked, "false");
+            read_envelope(rust_set_idle_timeout(wallet.as_ptr(), hour.as_ptr())).expect("set timeout failed");
+            let locked = read_envelope(rust_is_session_locked(wallet.as_ptr())).expect("lock check failed");
+            assert_eq!(locked, "false");
+
+            // Let the session go idle without waiting for it.
+            get_session(session_id.parse::<SessionId>().unwrap())
+                .unwrap()
+                .set_idle_timeout(Some(Duration::ZERO));
+            let locked = read_envelope(rust_is_session_locked(wallet.as_ptr())).expect("lock check failed");
+            assert_eq!(locked, "true");
+
+            let finalize = read_envelope(rust_tx_finalize(wallet.as_ptr(), slate.as_ptr()));
+            assert!(finalize.unwrap_err().starts_with("session_locked"));
+            read_envelope(rust_wallet_balances(wallet.as_ptr(), zero.as_ptr(), zero.as_ptr()))
+                .expect("cached balances failed while locked");
+            read_envelope(rust_txs_get(wallet.as_ptr(), zero.as_ptr()))
+                .expect("cached history failed while locked");
+
+            // Turning the timeout off does not unlock the session; the password does.
+            read_envelope(rust_set_idle_timeout(wallet.as_ptr(), zero.as_ptr())).expect("set timeout failed");
+            let wrong = read_envelope(rust_unlock_wallet(wallet.as_ptr(), wrong_password.as_ptr()));
+            assert!(wrong.unwrap_err().starts_with("wrong_password"));
+            let locked = read_envelope(rust_is_session_locked(wallet.as_ptr())).expect("lock check failed");
+            assert_eq!(locked, "true");
+            read_envelope(rust_unlock_wallet(wallet.as_ptr(), password.as_ptr())).expect("unlock failed");
+            let locked = read_envelope(rust_is_session_locked(wallet.as_ptr())).expect("lock check failed");
+            assert_eq!(locked, "false");
+
+            let finalize = read_envelope(rust_tx_finalize(wallet.as_ptr(), slate.as_ptr()));
+            assert!(!finalize.unwrap_err().starts_with("session_locked"));
+
+            // A failed spend does not count as activity.
+            get_session(session_id.parse::<SessionId>().unwrap())
+                .unwrap()
+                .set_idle_timeout(Some(Duration::from_secs(1)));
+            std::thread::sleep(Duration::from_millis(600));
+            let finalize = read_envelope(rust_tx_finalize(wallet.as_ptr(), slate.as_ptr()));
+            assert!(!finalize.unwrap_err().starts_with("session_locked"));
+            std::thread::sleep(Duration::from_millis(600));
+            let locked = read_envelope(rust_is_session_locked(wallet.as_ptr())).expect("lock check failed");
+            assert_eq!(locked, "true");
+
+            read_envelope(rust_close_wallet(wallet.as_ptr())).expect("close failed");
+            let _ = read_envelope(rust_delete_wallet(wallet.as_ptr(), config.as_ptr()));
+        }
+
+        cleanup_test_dir(&test_dir);
+    }
+
+    /// Test that a changed password replaces the old one and keeps the seed.
+    #[test]
+    fn test_change_password() {
+        use crate::ffi::{rust_change_password, rust_close_wallet};
+
+        let test_dir = setup_test_dir("change_password");
+        let config_json = create_test_config(&test_dir);
+
+        unsafe {
+            let config = CString::new(config_json).unwrap();
+            let old_password = CString::new("change_password_old").unwrap();
+            let new_password = CString::new("change_password_new").unwrap();
+            let name = CString::new("change_password_wallet").unwrap();
+
+            let phrase = CString::new(read_envelope(get_mnemonic()).unwrap()).unwrap();
+            read_envelope(wallet_init(config.as_ptr(), phrase.as_ptr(), old_password.as_ptr(), name.as_ptr()))
+                .expect("wallet creation failed");
+
+            let wrong = read_envelope(rust_change_password(
+                config.as_ptr(),
+                new_password.as_ptr(),
+                old_password.as_ptr(),
+            ));
+            assert!(wrong.unwrap_err().starts_with("wrong_password"));
+
+            read_envelope(rust_change_password(config.as_ptr(), old_password.as_ptr(), new_password.as_ptr()))
+                .expect("password change failed");
+            assert!(!test_dir.join("wallet_data").join("wallet.seed.password_change.bak").exists());
+
+            let with_old = read_envelope(rust_open_wallet(config.as_ptr(), old_password.as_ptr()));
+            assert!(with_old.unwrap_err().starts_with("wrong_password"));
+            let wallet = CString::new(
+                read_envelope(rust_open_wallet(config.as_ptr(), new_password.as_ptr())).expect("wallet open failed")
+            ).unwrap();
+            read_envelope(rust_close_wallet(wallet.as_ptr())).expect("close failed");
+        }
+
+        cleanup_test_dir(&test_dir);
+    }
+
+    /// Test that the recovery phrase can be revealed with the wallet password only.
+    #[test]
+    fn test_reveal_mnemonic() {
+        use crate::ffi::rust_reveal_mnemonic;
+
+        let test_dir = setup_test_dir("reveal_mnemonic");
+        let config_json = create_test_config(&test_dir);
+
+        unsafe {
+            let config = CString::new(config_json).unwrap();
+            let password = CString::new("reveal_mnemonic_password").unwrap();
+            let wrong_password = CString::new("not_the_password").unwrap();
+            let name = CString::new("reveal_mnemonic_wallet").unwrap();
+
+            let missing = read_envelope(rust_reveal_mnemonic(config.as_ptr(), password.as_ptr()));
+            assert!(missing.unwrap_err().starts_with("wallet_seed_doesnt_exist"));
+
+            let phrase = read_envelope(get_mnemonic()).unwrap();
+            let phrase_c = CString::new(phrase.clone()).unwrap();
+            read_envelope(wallet_init(config.as_ptr(), phrase_c.as_ptr(), password.as_ptr(), name.as_ptr()))
+                .expect("wallet creation failed");
+
+            let revealed = read_envelope(rust_reveal_mnemonic(config.as_ptr(), password.as_ptr()))
+                .expect("reveal failed");
+            assert_eq!(revealed, phrase);
+
+            let wrong = read_envelope(rust_reveal_mnemonic(config.as_ptr(), wrong_password.as_ptr()));
+            assert!(wrong.unwrap_err().starts_with("wrong_password"));
+        }
+
+        cleanup_test_dir(&test_dir);
+    }
+
+    /// Test creating accounts and switching the active one on a session.
+    #[test]
+    fn test_accounts() {
+        use crate::ffi::{rust_accounts, rust_close_wallet, rust_create_account, rust_set_active_account};
+
+        let test_dir = setup_test_dir("accounts");
+        let config_json = create_test_config(&test_dir);
+
+        unsafe {
+            let config = CString::new(config_json).unwrap();
+            let password = CString::new("accounts_password").unwrap();
+            let name = CString::new("accounts_wallet").unwrap();
+            let savings = CString::new("savings").unwrap();
+            let missing = CString::new("missing").unwrap();
+
+            let phrase = CString::new(read_envelope(get_mnemonic()).unwrap()).unwrap();
+            read_envelope(wallet_init(config.as_ptr(), phrase.as_ptr(), password.as_ptr(), name.as_ptr()))
+                .expect("wallet creation failed");
+            let wallet = CString::new(
+                read_envelope(rust_open_wallet(config.as_ptr(), password.as_ptr())).expect("wallet open failed")
+            ).unwrap();
+
+            let path = read_envelope(rust_create_account(wallet.as_ptr(), savings.as_ptr()))
+                .expect("account creation failed");
+            assert_eq!(path, "m/1/0");
+
+            let accounts: serde_json::Value = serde_json::from_str(
+                &read_envelope(rust_accounts(wallet.as_ptr())).expect("accounts failed")
+            ).unwrap();
+            assert_eq!(accounts[0]["label"], "default");
+            assert_eq!(accounts[0]["path"], "m/0/0");
+            assert_eq!(accounts[0]["active"], true);
+            assert_eq!(accounts[1]["label"], "savings");
+            assert_eq!(accounts[1]["active"], false);
+
+            read_envelope(rust_set_active_account(wallet.as_ptr(), savings.as_ptr()))
+                .expect("account switch failed");
+            let accounts: serde_json::Value = serde_json::from_str(
+                &read_envelope(rust_accounts(wallet.as_ptr())).expect("accounts failed")
+            ).unwrap();
+            assert_eq!(accounts[0]["active"], false);
+            assert_eq!(accounts[1]["active"], true);
+
+            assert!(read_envelope(rust_set_active_account(wallet.as_ptr(), missing.as_ptr())).is_err());
+
+            read_envelope(rust_close_wallet(wallet.as_ptr())).expect("close failed");
+        }
+
+        cleanup_test_dir(&test_dir);
+    }
+
+    /// Test that each way opening a wallet can fail has its own error, and that
+    /// a config without an account opens the default one.
+    #[test]
+    fn test_open_wallet_errors() {
+        use crate::ffi::rust_close_wallet;
+
+        let test_dir = setup_test_dir("open_errors");
+        let config_json = create_test_config(&test_dir);
+        let config_for = |account: Option<&str>| {
+            let mut config: serde_json::Value = serde_json::from_str(&config_json).unwrap();
+            match account {
+                Some(account) => config["account"] = json!(account),
+                None => {
+                    config.as_object_mut().unwrap().remove("account");
+                }
+            }
+            CString::new(config.to_string()).unwrap()
+        };
+
+        unsafe {
+            let config = CString::new(config_json.clone()).unwrap();
+            let password = CString::new("open_errors_password").unwrap();
+            let wrong_password = CString::new("not_the_password").unwrap();
+            let name = CString::new("open_errors_wallet").unwrap();
+
+            let missing = read_envelope(rust_open_wallet(config.as_ptr(), password.as_ptr()));
+            assert!(missing.unwrap_err().starts_with("wallet_seed_doesnt_exist"));
+
+            let phrase = CString::new(read_envelope(get_mnemonic()).unwrap()).unwrap();
+            read_envelope(wallet_init(config.as_ptr(), phrase.as_ptr(), password.as_ptr(), name.as_ptr()))
+                .expect("wallet creation failed");
+
+            let wrong = read_envelope(rust_open_wallet(config.as_ptr(), wrong_password.as_ptr()));
+            assert!(wrong.unwrap_err().starts_with("wrong_password"));
+
+            let unknown_account = config_for(Some("missing"));
+            let unknown = read_envelope(rust_open_wallet(unknown_account.as_ptr(), password.as_ptr()));
+            assert!(unknown.unwrap_err().starts_with("unknown_account"));
+
+            let lock_file = fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .open(test_dir.join(".wallet.lock"))
+                .unwrap();
+            lock_file.lock().unwrap();
+            let locked = read_envelope(rust_open_wallet(config.as_ptr(), password.as_ptr()));
+            assert!(locked.unwrap_err().starts_with("wallet_locked"));
+            lock_file.unlock().unwrap();
+
+            let no_account = config_for(None);
+            let wallet = CString::new(
+                read_envelope(rust_open_wallet(no_account.as_ptr(), password.as_ptr())).expect("wallet open failed")
+            ).unwrap();
+            read_envelope(rust_close_wallet(wallet.as_ptr())).expect("close failed");
+        }
+
+        cleanup_test_dir(&test_dir);
+    }
+
+    /// Test that the status of a wallet is reported without its password, both
+    /// while it is closed and while it is open.
+    #[test]
+    fn test_wallet_status() {
+        use crate::ffi::{rust_close_wallet, rust_wallet_status};
+
+        let test_dir = setup_test_dir("wallet_status");
+        let config_json = create_test_config(&test_dir);
+        let status_of = |config: &CString| -> serde_json::Value {
+            let status = unsafe { read_envelope(rust_wallet_status(config.as_ptr())) };
+            serde_json::from_str(&status.expect("status failed")).unwrap()
+        };
+
+        unsafe {
+            let config = CString::new(config_json).unwrap();
+            let password = CString::new("wallet_status_password").unwrap();
+            let name = CString::new("wallet_status_wallet").unwrap();
+
+            let missing = status_of(&config);
+            assert_eq!(missing["seed_exists"], false);
+            assert_eq!(missing["chain"], "floonet");
+            assert!(missing["name"].is_null());
+            assert!(missing["last_confirmed_height"].is_null());
+
+            let phrase = CString::new(read_envelope(get_mnemonic()).unwrap()).unwrap();
+            read_envelope(wallet_init(config.as_ptr(), phrase.as_ptr(), password.as_ptr(), name.as_ptr()))
+                .expect("wallet creation failed");
+            let created = status_of(&config);
+            assert_eq!(created["seed_exists"], true);
+            assert_eq!(created["name"], "wallet_status_wallet");
+            // A wallet that was never closed has no recorded height.
+            assert!(created["last_confirmed_height"].is_null());
+
+            let wallet = CString::new(
+                read_envelope(rust_open_wallet(config.as_ptr(), password.as_ptr())).expect("wallet open failed")
+            ).unwrap();
+            let open = status_of(&config);
+            assert_eq!(open["seed_exists"], true);
+            assert!(open["last_confirmed_height"].is_u64(), "{}", open);
+
+            read_envelope(rust_close_wallet(wallet.as_ptr())).expect("close failed");
+            let closed = status_of(&config);
+            assert_eq!(closed["last_confirmed_height"], open["last_confirmed_height"]);
+            assert_eq!(closed["name"], "wallet_status_wallet");
+
+            // Reading the status does not keep the wallet from being opened.
+            let wallet = CString::new(
+                read_envelope(rust_open_wallet(config.as_ptr(), password.as_ptr())).expect("wallet reopen failed")
+            ).unwrap();
+            read_envelope(rust_close_wallet(wallet.as_ptr())).expect("close failed");
+        }
+
+        cleanup_test_dir(&test_dir);
+    }
+
+    /// Test that a wallet is deleted from its config alone, whether it was never
+    /// opened or is open, and that its session is closed.
+    #[test]
+    fn test_delete_wallet() {
+        let test_dir = setup_test_dir("delete_wallet");
+        let config_json = create_test_config(&test_dir);
+
+        unsafe {
+            let config = CString::new(config_json).unwrap();
+            let password = CString::new("delete_wallet_password").unwrap();
+            let name = CString::new("delete_wallet").unwrap();
+            let no_wallet = CString::new("").unwrap();
+            let phrase = CString::new(read_envelope(get_mnemonic()).unwrap()).unwrap();
+
+            // Never opened in this process.
+            read_envelope(wallet_init(config.as_ptr(), phrase.as_ptr(), password.as_ptr(), name.as_ptr()))
+                .expect("wallet creation failed");
+            let deleted: serde_json::Value = serde_json::from_str(
+                &read_envelope(rust_delete_wallet(no_wallet.as_ptr(), config.as_ptr())).expect("delete failed"),
+            ).unwrap();
+            let removed = deleted["removed"].as_array().unwrap();
+            assert_eq!(removed[0], "wallet_data/wallet.seed");
+            assert!(removed.iter().any(|file| file.as_str().unwrap().starts_with("wallet_data/db/")));
+            assert_eq!(removed.last().unwrap(), ".wallet.lock");
+            assert!(deleted["kept"].as_array().unwrap().is_empty());
+            assert_eq!(deleted["closed_sessions"], 0);
+            assert!(!test_dir.exists());
+
+            // Open.
+            fs::create_dir_all(&test_dir).unwrap();
+            read_envelope(wallet_init(config.as_ptr(), phrase.as_ptr(), password.as_ptr(), name.as_ptr()))
+                .expect("wallet creation failed");
+            let wallet = CString::new(
+                read_envelope(rust_open_wallet(config.as_ptr(), password.as_ptr())).expect("wallet open failed")
+            ).unwrap();
+
+            // The session of another wallet is refused and left open.
+            let other_dir = setup_test_dir("delete_wallet_other");
+            let other_config = CString::new(create_test_config(&other_dir)).unwrap();
+            read_envelope(wallet_init(other_config.as_ptr(), phrase.as_ptr(), password.as_ptr(), name.as_ptr()))
+                .expect("wallet creation failed");
+            let other_wallet = CString::new(
+                read_envelope(rust_open_wallet(other_config.as_ptr(), password.as_ptr())).expect("wallet open failed")
+            ).unwrap();
+            let refused = read_envelope(rust_delete_wallet(other_wallet.as_ptr(), config.as_ptr()));
+            assert!(refused.unwrap_err().starts_with("invalid_argument"));
+            assert!(test_dir.join("wallet_data").exists());
+            read_envelope(crate::ffi::rust_close_wallet(other_wallet.as_ptr())).expect("other wallet was closed");
+            cleanup_test_dir(&other_dir);
+
+            let deleted: serde_json::Value = serde_json::from_str(
+                &read_envelope(rust_delete_wallet(wallet.as_ptr(), config.as_ptr())).expect("delete failed"),
+            ).unwrap();
+            assert_eq!(deleted["closed_sessions"], 1);
+            assert!(!test_dir.exists());
+            let zero = CString::new("0").unwrap();
+            let stale = read_envelope(rust_txs_get(wallet.as_ptr(), zero.as_ptr()));
+            assert!(stale.unwrap_err().starts_with("invalid_handle"));
+
+            // Deleting again finds nothing to remove.
+            let again: serde_json::Value = serde_json::from_str(
+                &read_envelope(rust_delete_wallet(no_wallet.as_ptr(), config.as_ptr())).expect("delete failed"),
+            ).unwrap();
+            assert!(again["removed"].as_array().unwrap().is_empty());
+        }
+
+        cleanup_test_dir(&test_dir);
+    }
+
+    /// Test that wallets on different chains are kept side by side under one
+    /// data directory and managed by name.
+    #[test]
+    fn test_wallet_manager() {
+        use crate::ffi::{
+            rust_close_wallet, rust_manager_create_wallet, rust_manager_delete_wallet,
+            rust_manager_list_wallets, rust_manager_open_wallet, rust_manager_rename_wallet,
+        };
+
+        let root = setup_test_dir("manager");
+        let floonet_json = create_test_config(&root);
+        let mut mainnet_json: serde_json::Value = serde_json::from_str(&floonet_json).unwrap();
+        mainnet_json["chain"] = json!("mainnet");
+
+        unsafe {
+            let root_arg = CString::new(root.to_str().unwrap()).unwrap();
+            let floonet = CString::new(floonet_json).unwrap();
+            let mainnet = CString::new(mainnet_json.to_string()).unwrap();
+            let password = CString::new("manager_password").unwrap();
+            let main_name = CString::new("main").unwrap();
+            let test_name = CString::new("test").unwrap();
+            let renamed_name = CString::new("savings").unwrap();
+
+            let phrase = CString::new(read_envelope(get_mnemonic()).unwrap()).unwrap();
+            for (config, name) in [(&mainnet, &main_name), (&floonet, &test_name)] {
+                read_envelope(rust_manager_create_wallet(
+                    root_arg.as_ptr(),
+                    config.as_ptr(),
+                    name.as_ptr(),
+                    phrase.as_ptr(),
+                    password.as_ptr(),
+                ))
+                .expect("wallet creation failed");
+            }
+            let again = read_envelope(rust_manager_create_wallet(
+                root_arg.as_ptr(),
+                floonet.as_ptr(),
+                main_name.as_ptr(),
+                phrase.as_ptr(),
+                password.as_ptr(),
+            ));
+            assert!(again.unwrap_err().starts_with("wallet_seed_exists"));
+
+            let listed: serde_json::Value = serde_json::from_str(
+                &read_envelope(rust_manager_list_wallets(root_arg.as_ptr())).expect("list failed"),
+            ).unwrap();
+            assert_eq!(listed.as_array().unwrap().len(), 2);
+            assert_eq!(listed[0]["name"], "main");
+            assert_eq!(listed[0]["chain"], "mainnet");
+            assert_eq!(listed[1]["name"], "test");
+            assert_eq!(listed[1]["chain"], "floonet");
+            assert!(listed[1]["created_at"].is_u64());
+
+            // Opened with the floonet config, the mainnet wallet stays on mainnet.
+            let session_id = read_envelope(rust_manager_open_wallet(
+                root_arg.as_ptr(),
+                floonet.as_ptr(),
+                main_name.as_ptr(),
+                password.as_ptr(),
+            ))
+            .expect("wallet open failed");
+            let wallet = CString::new(session_id).unwrap();
+            let open = read_envelope(rust_manager_rename_wallet(
+                root_arg.as_ptr(),
+                main_name.as_ptr(),
+                renamed_name.as_ptr(),
+            ));
+            assert!(open.unwrap_err().starts_with("lifecycle"));
+            read_envelope(rust_close_wallet(wallet.as_ptr())).expect("close failed");
+
+            read_envelope(rust_manager_rename_wallet(
+                root_arg.as_ptr(),
+                main_name.as_ptr(),
+                renamed_name.as_ptr(),
+            ))
+            .expect("rename failed");
+            read_envelope(rust_manager_delete_wallet(root_arg.as_ptr(), test_name.as_ptr()))
+                .expect("delete failed");
+
+            let listed: serde_json::Value = serde_json::from_str(
+                &read_envelope(rust_manager_list_wallets(root_arg.as_ptr())).expect("list failed"),
+            ).unwrap();
+            assert_eq!(listed.as_array().unwrap().len(), 1);
+            assert_eq!(listed[0]["name"], "savings");
+            assert_eq!(listed[0]["chain"], "mainnet");
+        }
+
+        cleanup_test_dir(&root);
+    }
+
+    /// Test that a backup restores into a wallet that opens with its password,
+    /// and that an open wallet is not backed up.
+    #[test]
+    fn test_backup_and_restore_wallet() {
+        use crate::ffi::{rust_accounts, rust_backup_wallet, rust_close_wallet, rust_create_account,
+            rust_restore_wallet_backup};
+
+        let test_dir = setup_test_dir("backup_source");
+        let restore_dir = PathBuf::from("test_wallet_dir_backup_restored");
+        let archive = PathBuf::from("test_wallet_backup.epicbackup");
+        let _ = fs::remove_dir_all(&restore_dir);
+        let config_json = create_test_config(&test_dir);
+        let restored_config_json = create_test_config(&restore_dir);
+
+        unsafe {
+            let config = CString::new(config_json).unwrap();
+            let restored_config = CString::new(restored_config_json).unwrap();
+            let password = CString::new("backup_wallet_password").unwrap();
+            let backup_password = CString::new("backup_archive_password").unwrap();
+            let name = CString::new("backup_wallet").unwrap();
+            let savings = CString::new("savings").unwrap();
+            let archive_c = CString::new(archive.to_str().unwrap()).unwrap();
+            let restore_dir_c = CString::new(restore_dir.to_str().unwrap()).unwrap();
+
+            let phrase = CString::new(read_envelope(get_mnemonic()).unwrap()).unwrap();
+            read_envelope(wallet_init(config.as_ptr(), phrase.as_ptr(), password.as_ptr(), name.as_ptr()))
+                .expect("wallet creation failed");
+            let wallet = CString::new(
+                read_envelope(rust_open_wallet(config.as_ptr(), password.as_ptr())).expect("wallet open failed")
+            ).unwrap();
+            read_envelope(rust_create_account(wallet.as_ptr(), savings.as_ptr())).expect("account creation failed");
+
+            let while_open = read_envelope(rust_backup_wallet(config.as_ptr(), backup_password.as_ptr(), archive_c.as_ptr()));
+            assert!(while_open.unwrap_err().starts_with("lifecycle"));
+            read_envelope(rust_close_wallet(wallet.as_ptr())).expect("close failed");
+
+            read_envelope(rust_backup_wallet(config.as_ptr(), backup_password.as_ptr(), archive_c.as_ptr()))
+                .expect("backup failed");
+
+            let wrong = read_envelope(rust_restore_wallet_backup(archive_c.as_ptr(), password.as_ptr(), restore_dir_c.as_ptr()));
+            assert!(wrong.unwrap_err().starts_with("wrong_password"));
+            assert!(!restore_dir.exists());
+
+            // A staging directory the restore did not create is left alone.
+            let staging = PathBuf::from("test_wallet_dir_backup_restored.restoring");
+            fs::create_dir_all(&staging).unwrap();
+            fs::write(staging.join("unrelated"), b"keep").unwrap();
+            let unmarked = read_envelope(rust_restore_wallet_backup(archive_c.as_ptr(), backup_password.as_ptr(), restore_dir_c.as_ptr()));
+            assert!(unmarked.unwrap_err().starts_with("backend"));
+            assert!(staging.join("unrelated").exists());
+            assert!(!restore_dir.exists());
+            // One left behind by an interrupted restore is replaced.
+            fs::write(staging.join(crate::backup::STAGING_MARKER), b"").unwrap();
+
+            read_envelope(rust_restore_wallet_backup(archive_c.as_ptr(), backup_password.as_ptr(), restore_dir_c.as_ptr()))
+                .expect("restore failed");
+            assert!(!staging.exists());
+            assert!(!restore_dir.join("unrelated").exists());
+            assert!(!restore_dir.join(crate::backup::STAGING_MARKER).exists());
+            let restored = CString::new(
+                read_envelope(rust_open_wallet(restored_config.as_ptr(), password.as_ptr())).expect("restored open failed")
+            ).unwrap();
+            let accounts = read_envelope(rust_accounts(restored.as_ptr())).expect("accounts failed");
+            assert!(accounts.contains("savings"), "{}", accounts);
+            let while_open = read_envelope(rust_restore_wallet_backup(archive_c.as_ptr(), backup_password.as_ptr(), restore_dir_c.as_ptr()));
+            assert!(while_open.unwrap_err().starts_with("lifecycle"));
+            read_envelope(rust_close_wallet(restored.as_ptr())).expect("close failed");
+
+            let not_empty = read_envelope(rust_restore_wallet_backup(archive_c.as_ptr(), backup_password.as_ptr(), restore_dir_c.as_ptr()));
+            assert!(not_empty.unwrap_err().starts_with("wallet_seed_exists"));
+        }
+
+        let _ = fs::remove_file(&archive);
+        cleanup_test_dir(&restore_dir);
+        cleanup_test_dir(&test_dir);
+    }
+
+    /// Test that owner JSON-RPC requests run with the session's keychain mask.
+    #[test]
+    fn test_owner_rpc() {
+        use crate::ffi::rust_owner_rpc;
+
+        let test_dir = setup_test_dir("owner_rpc");
+        let config_json = create_test_config(&test_dir);
+
+        unsafe {
+            let config = CString::new(config_json).unwrap();
+            let password = CString::new("owner_rpc_password").unwrap();
+            let name = CString::new("owner_rpc_wallet").unwrap();
+
+            let phrase = CString::new(read_envelope(get_mnemonic()).unwrap()).unwrap();
+            read_envelope(wallet_init(config.as_ptr(), phrase.as_ptr(), password.as_ptr(), name.as_ptr()))
+                .expect("wallet creation failed");
+            let wallet = CString::new(
+                read_envelope(rust_open_wallet(config.as_ptr(), password.as_ptr())).expect("wallet open failed")
+            ).unwrap();
+
+            let accounts = CString::new(r#"{"jsonrpc": "2.0", "id": 1, "method": "accounts", "params": {}}"#).unwrap();
+            let reply: serde_json::Value = serde_json::from_str(
+                &read_envelope(rust_owner_rpc(wallet.as_ptr(), accounts.as_ptr())).expect("owner rpc failed")
+            ).unwrap();
+            assert_eq!(reply["id"], 1);
+            assert_eq!(reply["result"]["Ok"][0]["label"], "default", "unexpected reply {}", reply);
+
+            let close = CString::new(r#"{"jsonrpc": "2.0", "id": 2, "method": "close_wallet", "params": {}}"#).unwrap();
+            let reply: serde_json::Value = serde_json::from_str(
+                &read_envelope(rust_owner_rpc(wallet.as_ptr(), close.as_ptr())).expect("owner rpc failed")
+            ).unwrap();
+            assert_eq!(reply["error"]["code"], -32601);
+
+            let garbage = CString::new("{\"method\": ").unwrap();
+            let result = read_envelope(rust_owner_rpc(wallet.as_ptr(), garbage.as_ptr()));
+            assert!(result.unwrap_err().starts_with("invalid_argument"));
+
+            let _ = read_envelope(rust_delete_wallet(wallet.as_ptr(), config.as_ptr()));
+        }
+
+        cleanup_test_dir(&test_dir);
+    }
+
+    /// Test that foreign JSON-RPC requests run against the session's wallet.
+    #[test]
+    fn test_foreign_rpc() {
+        use crate::ffi::rust_foreign_rpc;
+
+        let test_dir = setup_test_dir("foreign_rpc");
+        let config_json = create_test_config(&test_dir);
+
+        unsafe {
+            let config = CString::new(config_json).unwrap();
+            let password = CString::new("foreign_rpc_password").unwrap();
+            let name = CString::new("foreign_rpc_wallet").unwrap();
+
+            let phrase = CString::new(read_envelope(get_mnemonic()).unwrap()).unwrap();
+            read_envelope(wallet_init(config.as_ptr(), phrase.as_ptr(), password.as_ptr(), name.as_ptr()))
+                .expect("wallet creation failed");
+            let wallet = CString::new(
+                read_envelope(rust_open_wallet(config.as_ptr(), password.as_ptr())).expect("wallet open failed")
+            ).unwrap();
+
+            let check_version = CString::new(r#"{"jsonrpc": "2.0", "id": 1, "method": "check_version", "params": []}"#).unwrap();
+            let reply: serde_json::Value = serde_json::from_str(
+                &read_envelope(rust_foreign_rpc(wallet.as_ptr(), check_version.as_ptr())).expect("foreign rpc failed")
+            ).unwrap();
+            assert_eq!(reply["id"], 1);
+            assert!(reply["result"]["Ok"]["foreign_api_version"].is_u64(), "unexpected reply {}", reply);
+
+            let unknown = CString::new(r#"{"jsonrpc": "2.0", "id": 2, "method": "no_such_method", "params": []}"#).unwrap();
+            let reply: serde_json::Value = serde_json::from_str(
+                &read_envelope(rust_foreign_rpc(wallet.as_ptr(), unknown.as_ptr())).expect("foreign rpc failed")
+            ).unwrap();
+            assert_eq!(reply["error"]["code"], -32601);
+
+            let _ = read_envelope(rust_delete_wallet(wallet.as_ptr(), config.as_ptr()));
+        }
+
+        cleanup_test_dir(&test_dir);
+    }
+
+    /// Test that configured logging writes redacted lines to the log file and callback.
+    #[test]
+    fn test_logger_writes_redacted_lines() {
+        use crate::ffi::{rust_logger_init, rust_logger_set_callback};
+
+        static LINES: std::sync::Mutex<Vec<String>> = std::sync::Mutex::new(Vec::new());
+        unsafe extern "C" fn collect(_level: u32, line: *const c_char) {
+            LINES.lock().unwrap().push(CStr::from_ptr(line).to_string_lossy().into_owned());
+        }
+
+        let test_dir = setup_test_dir("logger");
+
+        unsafe {
+            let config = CString::new(
+                json!({ "level": "debug", "wallet_dir": test_dir.to_str().unwrap() }).to_string()
+            ).unwrap();
+            let path = read_envelope(rust_logger_init(config.as_ptr())).expect("logger init failed");
+
+            rust_logger_set_callback(Some(collect));
+            info!("logger test password=hunter2");
+            rust_logger_set_callback(None);
+            log::logger().flush();
+
+            let redacted = "logger test password=\"[REDACTED]\"";
+            let written = fs::read_to_string(&path).unwrap();
+            assert!(written.contains(redacted), "log file was '{}'", written);
+            assert!(!written.contains("hunter2"));
+            assert!(LINES.lock().unwrap().iter().any(|line| line.contains(redacted)));
+
+            let bad_level = CString::new(r#"{"level": "loud"}"#).unwrap();
+            assert!(read_envelope(rust_logger_init(bad_level.as_ptr())).unwrap_err().starts_with("invalid_config"));
+
+            // Stop writing to the test directory before removing it.
+            let defaults = CString::new("{}").unwrap();
+            read_envelope(rust_logger_init(defaults.as_ptr())).expect("logger reset failed");
+        }
+
+        cleanup_test_dir(&test_dir);
+    }
+
+    /// Read-only calls on an open wallet, returning its address at index 0.
+    unsafe fn exercise_wallet(wallet: &str, epicbox_config: &str) -> String {
+        let wallet = CString::new(wallet).unwrap();
+        let no_refresh = CString::new("0").unwrap();
+        let min_confirmations = CString::new("10").unwrap();
+        let index = CString::new("0").unwrap();
+        let epicbox_config = CString::new(epicbox_config).unwrap();
+
+        read_envelope(rust_wallet_balances(wallet.as_ptr(), no_refresh.as_ptr(), min_confirmations.as_ptr()))
+            .expect("balances failed");
+        read_envelope(rust_txs_get(wallet.as_ptr(), no_refresh.as_ptr())).expect("txs_get failed");
+        read_envelope(rust_get_wallet_address(wallet.as_ptr(), index.as_ptr(), epicbox_config.as_ptr()))
+            .expect("address failed")
+    }
+
+    /// Test that wallets opened from different directories at once are isolated:
+    /// a busy wallet never blocks another, concurrent use never mixes them up, and
+    /// one wallet directory cannot be opened twice.
+    #[test]
+    fn test_multiple_wallets_are_isolated() {
+        use crate::ffi::rust_close_wallet;
+        use std::sync::mpsc;
+        use std::thread;
+        use std::time::Duration;
+
+        const WALLETS: usize = 3;
+        const ROUNDS: usize = 10;
+        let epicbox_config = json!({
+            "epicbox_domain": "epicbox.epic.tech",
+            "epicbox_port": 443,
+            "epicbox_protocol_unsecure": false,
+            "epicbox_address_index": 0,
+        }).to_string();
+
+        let dirs: Vec<PathBuf> = (0..WALLETS).map(|i| setup_test_dir(&format!("multi_{}", i))).collect();
+        let configs: Vec<CString> = dirs.iter().map(|dir| CString::new(create_test_config(dir)).unwrap()).collect();
+        let password = CString::new("multi_wallet_password").unwrap();
+
+        unsafe {
+            let mut sessions = Vec::new();
+            for (i, config) in configs.iter().enumerate() {
+                let name = CString::new(format!("multi_wallet_{}", i)).unwrap();
+                let phrase = CString::new(read_envelope(get_mnemonic()).unwrap()).unwrap();
+                read_envelope(wallet_init(config.as_ptr(), phrase.as_ptr(), password.as_ptr(), name.as_ptr()))
+                    .expect("wallet creation failed");
+                let session = read_envelope(rust_open_wallet(config.as_ptr(), password.as_ptr()))
+                    .expect("wallet open failed");
+                let address = exercise_wallet(&session, &epicbox_config);
+                sessions.push((session, address));
+            }
+            let addresses: std::collections::HashSet<&String> = sessions.iter().map(|(_, a)| a).collect();
+            assert_eq!(addresses.len(), WALLETS, "wallets share an address");
+
+            // The same wallet directory cannot be opened a second time.
+            let reopened = read_envelope(rust_open_wallet(configs[0].as_ptr(), password.as_ptr()));
+            assert!(reopened.unwrap_err().starts_with("lifecycle"));
+
+            // Hold the first wallet's lock; the others must still make progress.
+            let busy = crate::session::get_session(sessions[0].0.parse().unwrap()).unwrap();
+            let busy_guard = busy.wallet().lock();
+            let (done_tx, done_rx) = mpsc::channel();
+            for (session, address) in sessions.iter().skip(1).cloned() {
+                let done_tx = done_tx.clone();
+                let epicbox_config = epicbox_config.clone();
+                thread::spawn(move || {
+                    for _ in 0..ROUNDS {
+                        assert_eq!(exercise_wallet(&session, &epicbox_config), address);
+                    }
+                    done_tx.send(()).unwrap();
+                });
+            }
+            for _ in 1..WALLETS {
+                done_rx.recv_timeout(Duration::from_secs(120))
+                    .expect("a wallet was blocked by another wallet's lock");
+            }
+            drop(busy_guard);
+
+            // All wallets at once, each still answering for itself.
+            let workers: Vec<_> = sessions.iter().cloned().map(|(session, address)| {
+                let epicbox_config = epicbox_config.clone();
+                thread::spawn(move || {
+                    for _ in 0..ROUNDS {
+                        assert_eq!(exercise_wallet(&session, &epicbox_config), address);
+                    }
+                })
+            }).collect();
+            for worker in workers {
+                worker.join().expect("concurrent wallet use failed");
+            }
+
+            // Closing one wallet leaves the others usable, and frees its directory.
+            let first = CString::new(sessions[0].0.clone()).unwrap();
+            read_envelope(rust_close_wallet(first.as_ptr())).expect("close failed");
+            assert_eq!(exercise_wallet(&sessions[1].0, &epicbox_config), sessions[1].1);
+            let reopened = read_envelope(rust_open_wallet(configs[0].as_ptr(), password.as_ptr()))
+                .expect("reopening a closed wallet failed");
+            assert_eq!(exercise_wallet(&reopened, &epicbox_config), sessions[0].1);
+            sessions[0].0 = reopened;
+
+            for ((session, _), config) in sessions.iter().zip(&configs) {
+                let session = CString::new(session.as_str()).unwrap();
+                let _ = read_envelope(rust_delete_wallet(session.as_ptr(), config.as_ptr()));
+            }
+        }
+
+        for dir in &dirs {
+            cleanup_test_dir(dir);
+        }
+    }
+
+    /// Test that a session id that was never issued is rejected.
+    #[test]
+    fn test_unknown_session_is_rejected() {
+        unsafe {
+            let wallet = CString::new("4294967297").unwrap();
+            let no_refresh = CString::new("0").unwrap();
+            let result = read_envelope(rust_txs_get(wallet.as_ptr(), no_refresh.as_ptr()));
+            assert!(result.unwrap_err().starts_with("invalid_handle"));
+        }
+    }
+
+    /// Test that a wallet task on an unknown session reports the error through its result.
+    #[test]
+    fn test_task_reports_errors_through_result() {
+        use crate::ffi::{rust_refresh_task_start, rust_task_cancel, rust_task_destroy, rust_task_poll, rust_task_wait};
+
+        unsafe {
+            let wallet = CString::new("4294967297").unwrap();
+            let handle = rust_refresh_task_start(wallet.as_ptr());
+            assert!(!handle.is_null());
+
+            let result = read_envelope(rust_task_wait(handle));
+            assert!(result.unwrap_err().starts_with("invalid_handle"));
+            // The result is handed out once, and waiting again does not block.
+            assert!(rust_task_poll(handle).is_null());
+            let again = read_envelope(rust_task_wait(handle));
+            assert!(again.unwrap_err().starts_with("lifecycle"));
+            // The handle stays usable until it is destroyed.
+            assert_eq!(read_envelope(rust_task_cancel(handle)).unwrap(), "true");
+            rust_task_destroy(handle);
+
+            let null_poll = read_envelope(rust_task_poll(std::ptr::null_mut()));
+            assert!(null_poll.unwrap_err().starts_with("invalid_argument"));
+            rust_task_destroy(std::ptr::null_mut());
+        }
+    }
+
+    /// Test that wallet tasks are not started from malformed arguments.
+    #[test]
+    fn test_task_start_rejects_malformed_arguments() {
+        use crate::ffi::{rust_finalize_task_start, rust_scan_outputs_task_start};
+
+        unsafe {
+            let wallet = CString::new("1").unwrap();
+            let not_a_number = CString::new("ten").unwrap();
+            let handle = rust_scan_outputs_task_start(wallet.as_ptr(), not_a_number.as_ptr(), not_a_number.as_ptr());
+            assert!(handle.is_null());
+
+            let handle = rust_finalize_task_start(wallet.as_ptr(), std::ptr::null());
+            assert!(handle.is_null());
+        }
+    }
+
+    /// Test the sweep amount and the max sendable query on an empty wallet.
+    #[test]
+    fn test_sweep_empty_wallet() {
+        use crate::ffi::{rust_close_wallet, rust_max_sendable};
+
+        let test_dir = setup_test_dir("sweep");
+        let config_json = create_test_config(&test_dir);
+
+        unsafe {
+            let config = CString::new(config_json).unwrap();
+            let password = CString::new("sweep_password").unwrap();
+            let name = CString::new("sweep_wallet").unwrap();
+
+            let phrase = CString::new(read_envelope(get_mnemonic()).unwrap()).unwrap();
+            read_envelope(wallet_init(config.as_ptr(), phrase.as_ptr(), password.as_ptr(), name.as_ptr()))
+                .expect("wallet creation failed");
+            let session_id = read_envelope(rust_open_wallet(config.as_ptr(), password.as_ptr()))
+                .expect("wallet open failed");
+            let wallet = CString::new(session_id).unwrap();
+            let confirmations = CString::new("10").unwrap();
+            let message = CString::new("sweep").unwrap();
+            let address = CString::new("http://example.epic.address").unwrap();
+
+            // Without a node the query fails; with one, nothing is spendable.
+            match read_envelope(rust_max_sendable(wallet.as_ptr(), confirmations.as_ptr())) {
+                Ok(max) => {
+                    let max: serde_json::Value = serde_json::from_str(&max).unwrap();
+                    assert_eq!(max["amount"], 0);
+                    assert_eq!(max["inputs"], 0);
+                }
+                Err(err) => assert!(err.starts_with("node_unreachable"), "{}", err),
+            }
+
+            let bad_amount = CString::new("maximum").unwrap();
+            let sent = read_envelope(rust_tx_send_http(
+                wallet.as_ptr(),
+                confirmations.as_ptr(),
+                confirmations.as_ptr(),
+                message.as_ptr(),
+                bad_amount.as_ptr(),
+                address.as_ptr(),
+            ));
+            assert!(sent.unwrap_err().starts_with("invalid_argument"));
+
+            // An empty wallet has nothing to sweep.
+            let max_amount = CString::new("max").unwrap();
+            let sent = read_envelope(rust_tx_send_http(
+                wallet.as_ptr(),
+                confirmations.as_ptr(),
+                confirmations.as_ptr(),
+                message.as_ptr(),
+                max_amount.as_ptr(),
+                address.as_ptr(),
+            ));
+            let err = sent.unwrap_err();
+            assert!(
+                err.starts_with("not_enough_funds") || err.starts_with("node_unreachable"),
+                "{}",
+                err
+            );
+
+            read_envelope(rust_close_wallet(wallet.as_ptr())).expect("close failed");
+        }
+
+        cleanup_test_dir(&test_dir);
+    }
 }
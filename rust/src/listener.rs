@@ -1,19 +1,15 @@
-use std::sync::Arc;
 use ffi_helpers::{export_task, Task};
 use ffi_helpers::task::CancellationToken;
-use epic_util::Mutex;
-use epic_util::secp::SecretKey;
 use epic_wallet_config::{EpicboxConfig, TorConfig};
 use epic_wallet_impls::EpicboxListenChannel;
-use std::sync::atomic::AtomicBool;
-use std::sync::Arc as StdArc;
 
+use crate::session::{get_session, SessionId};
 use crate::wallet::Wallet;
 
 /// Listener task.
 #[derive(Debug, Clone)]
 pub struct Listener {
-    pub wallet_ptr_str: String,
+    pub session_id: SessionId,
     pub epicbox_config: String
 }
 
@@ -22,39 +18,43 @@ impl Task for Listener {
     type Output = usize;
 
     fn run(&self, cancel_tok: &CancellationToken) -> Result<Self::Output, anyhow::Error> {
-        let wallet_data_str = &self.wallet_ptr_str;
-        let tuple_wallet_data: (i64, Option<SecretKey>) = serde_json::from_str(wallet_data_str).unwrap();
-        let wlt = tuple_wallet_data.0;
-        let sek_key = tuple_wallet_data.1;
+        let epicbox_conf = serde_json::from_str::<EpicboxConfig>(&self.epicbox_config.as_str())
+            .map_err(|e| anyhow::Error::msg(format!("Epicbox config error: {}", e)))?;
 
-        unsafe {
-            let epicbox_conf = serde_json::from_str::<EpicboxConfig>(&self.epicbox_config.as_str()).unwrap();
+        // Hold our own reference so the wallet stays alive even if the session is closed
+        // meanwhile. The keychain mask is shared with the session, so closing it locks us out.
+        let session = match get_session(self.session_id) {
+            Ok(session) => session,
+            Err(e) => return Err(anyhow::Error::msg(e.to_string())),
+        };
+        let wallet: Wallet = session.wallet().clone();
+        let keychain_mask = session.shared_keychain_mask();
+        // Cleared when the session is closed or its wallet deleted.
+        let listening = session.listen_flag();
 
-            crate::ensure_wallet!(wlt, wallet);
+        // Only attempt connection if not cancelled
+        if !cancel_tok.cancelled() {
+            let listener = EpicboxListenChannel::new()
+                .map_err(|e| anyhow::Error::msg(format!("Epicbox listener error: {}", e)))?;
 
-            // Only attempt connection if not cancelled
-            if !cancel_tok.cancelled() {
-                let listener = EpicboxListenChannel::new().unwrap();
+            let mut reconnections = 0;
 
-                let mut reconnections = 0;
-
-                match listener.listen(
-                    wallet.clone(),
-                    Arc::new(Mutex::new(sek_key.clone())),
-                    epicbox_conf.clone(),
-                    &mut reconnections,
-                    // IMPORTANT: Must be true for the listener to process messages.
-                    // When false, the epicbox subscriber loop just sleeps and skips message processing.
-                    StdArc::new(AtomicBool::new(true)),
-                    TorConfig::default(),
-                ) {
-                    Ok(_) => {
-                        // Graceful close (server sent Close message or clean shutdown).
-                        // Do not reconnect - the caller can restart the listener if needed.
-                    }
-                    Err(e) => {
-                        return Err(anyhow::Error::msg(format!("Epicbox listener error: {}", e)));
-                    }
+            match listener.listen(
+                wallet.clone(),
+                keychain_mask,
+                epicbox_conf.clone(),
+                &mut reconnections,
+                // IMPORTANT: Must be true for the listener to process messages.
+                // When false, the epicbox subscriber loop just sleeps and skips message processing.
+                listening,
+                TorConfig::default(),
+            ) {
+                Ok(_) => {
+                    // Graceful close (server sent Close message or clean shutdown).
+                    // Do not reconnect - the caller can restart the listener if needed.
+                }
+                Err(e) => {
+                    return Err(anyhow::Error::msg(format!("Epicbox listener error: {}", e)));
                 }
             }
         }
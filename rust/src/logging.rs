@@ -0,0 +1,384 @@
+use std::borrow::Cow;
+use std::ffi::CString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::os::raw::c_char;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, Once, OnceLock};
+
+use android_logger::{AndroidLogger, Config as AndroidConfig};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use regex::Regex;
+use serde::Deserialize;
+
+use epic_keychain::mnemonic;
+
+use crate::envelope::{ErrorCode, FfiError};
+
+/// Name of the current log file; rotated files get `.1`, `.2`, ... appended.
+const LOG_FILE_NAME: &str = "epic-cash-wallet.log";
+
+/// Directory under a wallet directory that holds its log files.
+pub(crate) const LOG_DIR_NAME: &str = "logs";
+
+/// Whether `name` is the name of a log file written by this library, current
+/// or rotated.
+pub(crate) fn is_log_file_name(name: &str) -> bool {
+    match name.strip_prefix(LOG_FILE_NAME) {
+        Some("") => true,
+        Some(rest) => rest
+            .strip_prefix('.')
+            .is_some_and(|index| !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit())),
+        None => false,
+    }
+}
+
+/// Called with the level (1 = error up to 5 = trace) and the redacted, formatted
+/// line for every log record. It may be called from any thread.
+pub type LogCallback = Option<unsafe extern "C" fn(level: u32, line: *const c_char)>;
+
+/// Logger settings, as passed to `rust_logger_init`.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LogConfig {
+    /// One of `off`, `error`, `warn`, `info`, `debug` or `trace`.
+    pub level: String,
+    /// Log files are written to `logs` under this directory. No files are
+    /// written if it is unset.
+    pub wallet_dir: Option<String>,
+    /// Size in bytes at which the log file is rotated.
+    pub max_file_size: u64,
+    /// Number of rotated files kept besides the current one.
+    pub max_files: u32,
+    /// Also write log lines to stderr.
+    pub stderr: bool,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        LogConfig {
+            level: "debug".to_string(),
+            wallet_dir: None,
+            max_file_size: 5 * 1024 * 1024,
+            max_files: 3,
+            stderr: false,
+        }
+    }
+}
+
+/// A log file that is rotated once it reaches a maximum size.
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    max_files: u32,
+}
+
+impl RotatingFile {
+    fn open(dir: &Path, max_size: u64, max_files: u32) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(LOG_FILE_NAME);
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(RotatingFile { path, file, size, max_size, max_files })
+    }
+
+    fn rotated_path(&self, index: u32) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.max_files > 0 {
+            // Renaming onto an existing file fails on Windows, so clear the way first.
+            let _ = fs::remove_file(self.rotated_path(self.max_files));
+            for index in (1..self.max_files).rev() {
+                let _ = fs::rename(self.rotated_path(index), self.rotated_path(index + 1));
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+        self.file = OpenOptions::new().create(true).write(true).truncate(true).open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let len = line.len() as u64 + 1;
+        if self.size > 0 && self.size + len > self.max_size {
+            self.rotate()?;
+        }
+        writeln!(self.file, "{}", line)?;
+        self.size += len;
+        Ok(())
+    }
+}
+
+/// Where log records go.
+struct Sinks {
+    level: LevelFilter,
+    file: Option<RotatingFile>,
+    callback: LogCallback,
+    stderr: bool,
+}
+
+static SINKS: Mutex<Sinks> = Mutex::new(Sinks {
+    level: LevelFilter::Debug,
+    file: None,
+    callback: None,
+    stderr: false,
+});
+
+fn sinks() -> MutexGuard<'static, Sinks> {
+    SINKS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Logcat output on Android; a no-op elsewhere.
+fn android_logger() -> &'static AndroidLogger {
+    static ANDROID: OnceLock<AndroidLogger> = OnceLock::new();
+    ANDROID.get_or_init(|| {
+        AndroidLogger::new(AndroidConfig::default().with_min_level(Level::Trace).with_tag("libepiccash"))
+    })
+}
+
+struct FfiLogger;
+
+static LOGGER: FfiLogger = FfiLogger;
+
+impl Log for FfiLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= sinks().level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let message = format!("{}", record.args());
+        let message = redact(&message);
+        let line = format!(
+            "{} {:<5} {}: {}",
+            chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+            record.level(),
+            record.target(),
+            message
+        );
+
+        android_logger().log(
+            &Record::builder()
+                .args(format_args!("{}", message))
+                .level(record.level())
+                .target(record.target())
+                .module_path(record.module_path())
+                .file(record.file())
+                .line(record.line())
+                .build(),
+        );
+
+        let callback = {
+            let mut sinks = sinks();
+            if let Some(file) = sinks.file.as_mut() {
+                // Nowhere to report a failed log write; drop the line.
+                let _ = file.write_line(&line);
+            }
+            if sinks.stderr {
+                eprintln!("{}", line);
+            }
+            sinks.callback
+        };
+        // Called without the lock held, so the callback may itself log.
+        if let (Some(callback), Ok(line)) = (callback, CString::new(line)) {
+            unsafe { callback(record.level() as u32, line.as_ptr()) };
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = sinks().file.as_mut() {
+            let _ = file.file.flush();
+        }
+    }
+}
+
+/// Install the logger if it is not installed yet. Until `configure` is called
+/// it logs at debug level to logcat only.
+pub fn init() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        if log::set_logger(&LOGGER).is_ok() {
+            log::set_max_level(sinks().level);
+        }
+    });
+}
+
+/// Install the logger if needed and apply `config`. Returns the path of the
+/// log file, if one is written.
+pub fn configure(config: LogConfig) -> Result<Option<PathBuf>, FfiError> {
+    let level: LevelFilter = config.level.parse().map_err(|_| {
+        FfiError::new(ErrorCode::InvalidConfig, format!("Unknown log level '{}'", config.level))
+    })?;
+    let file = match &config.wallet_dir {
+        Some(dir) => Some(
+            RotatingFile::open(&Path::new(dir).join(LOG_DIR_NAME), config.max_file_size, config.max_files)
+                .map_err(|e| FfiError::new(ErrorCode::InvalidConfig, format!("Unable to open log file: {}", e)))?,
+        ),
+        None => None,
+    };
+    let path = file.as_ref().map(|f| f.path.clone());
+
+    init();
+    {
+        let mut sinks = sinks();
+        sinks.level = level;
+        sinks.file = file;
+        sinks.stderr = config.stderr;
+    }
+    log::set_max_level(level);
+    Ok(path)
+}
+
+/// Set or clear the callback that receives every log line.
+pub fn set_callback(callback: LogCallback) {
+    init();
+    sinks().callback = callback;
+}
+
+/// Keys whose values are secret wherever they appear, as `key: value`,
+/// `key=value` or JSON.
+fn secret_value_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| {
+        Regex::new(
+            r#"(?i)("?\b(?:password|passphrase|mnemonic|phrase|seed|keychain_mask|sek_key|sec_key|sec_nonce|secret[a-z_]*|private_key)"?\s*[:=]\s*)("[^"]*"|[^\s,;}\]]+)"#,
+        )
+        .unwrap()
+    })
+}
+
+/// Fewest words of a mnemonic.
+const MNEMONIC_MIN_WORDS: usize = 12;
+
+/// Words that could be in a mnemonic; only those on the BIP-39 wordlist are.
+fn word_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"\b[a-z]{3,8}\b").unwrap())
+}
+
+/// Replace every run of at least `MNEMONIC_MIN_WORDS` BIP-39 words, separated
+/// by single spaces, with `[REDACTED]`.
+fn redact_mnemonics(message: &str) -> Cow<'_, str> {
+    let mut runs = Vec::new();
+    // Start, end and word count of the run of wordlist words being read.
+    let mut run: Option<(usize, usize, usize)> = None;
+    for word in word_pattern().find_iter(message) {
+        let listed = mnemonic::search(word.as_str()).is_ok();
+        run = match run {
+            Some((start, end, count)) if listed && &message[end..word.start()] == " " => {
+                Some((start, word.end(), count + 1))
+            }
+            _ => {
+                runs.extend(run.filter(|&(_, _, count)| count >= MNEMONIC_MIN_WORDS));
+                listed.then(|| (word.start(), word.end(), 1))
+            }
+        };
+    }
+    runs.extend(run.filter(|&(_, _, count)| count >= MNEMONIC_MIN_WORDS));
+    if runs.is_empty() {
+        return Cow::Borrowed(message);
+    }
+
+    let mut redacted = String::with_capacity(message.len());
+    let mut copied = 0;
+    for (start, end, _) in runs {
+        redacted.push_str(&message[copied..start]);
+        redacted.push_str("[REDACTED]");
+        copied = end;
+    }
+    redacted.push_str(&message[copied..]);
+    Cow::Owned(redacted)
+}
+
+/// Debug output of secp secret keys.
+fn secret_key_pattern() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+    PATTERN.get_or_init(|| Regex::new(r"SecretKey\([^)]*\)").unwrap())
+}
+
+/// Remove passwords, mnemonics, keychain masks and other secrets from a log
+/// message.
+pub fn redact(message: &str) -> Cow<'_, str> {
+    let mut message = Cow::Borrowed(message);
+    if let Cow::Owned(redacted) = secret_value_pattern().replace_all(&message, "${1}\"[REDACTED]\"") {
+        message = Cow::Owned(redacted);
+    }
+    if let Cow::Owned(redacted) = redact_mnemonics(&message) {
+        message = Cow::Owned(redacted);
+    }
+    if let Cow::Owned(redacted) = secret_key_pattern().replace_all(&message, "SecretKey([REDACTED])") {
+        message = Cow::Owned(redacted);
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_log_file_name() {
+        for name in ["epic-cash-wallet.log", "epic-cash-wallet.log.1", "epic-cash-wallet.log.12"] {
+            assert!(is_log_file_name(name), "{}", name);
+        }
+        for name in ["epic-cash-wallet.log.", "epic-cash-wallet.log.old", "epic-cash-wallet.logs", "other.log"] {
+            assert!(!is_log_file_name(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_redact_secrets() {
+        assert_eq!(
+            redact(r#"{"wallet_dir": "/tmp/w", "password": "hunter2"}"#),
+            r#"{"wallet_dir": "/tmp/w", "password": "[REDACTED]"}"#
+        );
+        assert_eq!(redact("open with keychain_mask=0a1b2c, ok"), "open with keychain_mask=\"[REDACTED]\", ok");
+        assert_eq!(redact("sek_key: Some(abc)"), "sek_key: \"[REDACTED]\"");
+        assert_eq!(
+            redact("Words: abandon ability able about above absent absorb abstract absurd abuse access accident"),
+            "Words: [REDACTED]"
+        );
+        assert_eq!(
+            redact("seed abandon ability able about above absent absorb abstract absurd abuse access accident, ok"),
+            "seed [REDACTED], ok"
+        );
+        // Short lowercase words that are not all on the wordlist are kept.
+        let prose = "the scan found no new outputs from the node and will retry the whole range later";
+        assert!(matches!(redact(prose), Cow::Borrowed(_)));
+        let eleven = "abandon ability able about above absent absorb abstract absurd abuse access";
+        assert!(matches!(redact(eleven), Cow::Borrowed(_)));
+        assert_eq!(redact("mask SecretKey(#7f1e)"), "mask SecretKey([REDACTED])");
+        assert!(matches!(redact("scanned 1000 blocks"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_rotating_file() {
+        let dir = PathBuf::from("test_log_dir_rotation");
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut file = RotatingFile::open(&dir, 16, 2).unwrap();
+        for line in ["first line", "second line", "third line", "fourth line"] {
+            file.write_line(line).unwrap();
+        }
+        assert_eq!(fs::read_to_string(dir.join(LOG_FILE_NAME)).unwrap(), "fourth line\n");
+        assert_eq!(fs::read_to_string(file.rotated_path(1)).unwrap(), "third line\n");
+        assert_eq!(fs::read_to_string(file.rotated_path(2)).unwrap(), "second line\n");
+        assert!(!file.rotated_path(3).exists());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_configure_rejects_unknown_level() {
+        let config = LogConfig { level: "loud".to_string(), ..LogConfig::default() };
+        assert_eq!(configure(config).unwrap_err().code, ErrorCode::InvalidConfig);
+    }
+}
@@ -0,0 +1,534 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::config::Config;
+use crate::envelope::{ErrorCode, FfiError};
+use crate::logging::{is_log_file_name, LOG_DIR_NAME};
+use crate::session;
+use crate::wallet::{self, read_wallet_metadata, wallet_seed_exists, write_wallet_metadata, WalletMetadata};
+
+/// Longest wallet name the manager accepts, in bytes.
+pub const MAX_WALLET_NAME_LEN: usize = 64;
+
+/// A wallet under a manager root.
+#[derive(Debug, Serialize)]
+pub struct WalletEntry {
+    pub name: String,
+    /// Name of the chain, if it was recorded when the wallet was created.
+    pub chain: Option<String>,
+    /// Unix time in seconds, if it was recorded when the wallet was created.
+    pub created_at: Option<u64>,
+    pub wallet_dir: String,
+}
+
+fn io_error(context: &str, e: impl std::fmt::Display) -> FfiError {
+    FfiError::new(ErrorCode::Backend, format!("{}: {}", context, e))
+}
+
+/// Check that `name` can name a wallet directory.
+///
+/// Names are used as directory names as-is, so they may not contain path
+/// separators, start with a dot or hold characters that some file systems
+/// refuse.
+fn validate_name(name: &str) -> Result<(), FfiError> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_WALLET_NAME_LEN
+        && !name.starts_with('.')
+        && name.trim() == name
+        && name.chars().all(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(FfiError::new(
+            ErrorCode::InvalidArgument,
+            format!(
+                "Wallet name '{}' is invalid: use up to {} letters, digits, spaces, '-', '_' or '.', \
+                 not starting with '.'",
+                name, MAX_WALLET_NAME_LEN
+            ),
+        ))
+    }
+}
+
+/// Directory of the wallet named `name` under `root`.
+pub fn wallet_dir(root: &Path, name: &str) -> Result<PathBuf, FfiError> {
+    validate_name(name)?;
+    Ok(root.join(name))
+}
+
+fn unknown_wallet(name: &str) -> FfiError {
+    FfiError::new(
+        ErrorCode::WalletSeedDoesntExist,
+        format!("There is no wallet named '{}'", name),
+    )
+}
+
+/// Directory of the existing wallet named `name` under `root`.
+fn existing_wallet_dir(root: &Path, name: &str) -> Result<PathBuf, FfiError> {
+    let dir = wallet_dir(root, name)?;
+    if wallet_seed_exists(&dir) {
+        Ok(dir)
+    } else {
+        Err(unknown_wallet(name))
+    }
+}
+
+fn entry(name: &str, dir: &Path) -> WalletEntry {
+    let metadata = read_wallet_metadata(dir);
+    WalletEntry {
+        name: name.to_string(),
+        chain: metadata.as_ref().and_then(|m| m.chain.clone()),
+        created_at: metadata.as_ref().and_then(|m| m.created_at),
+        wallet_dir: dir.to_string_lossy().into_owned(),
+    }
+}
+
+/// The wallets under `root`, sorted by name.
+///
+/// Every directory of `root` holding a wallet seed is a wallet, named after
+/// the directory. A `root` that does not exist holds no wallets.
+pub fn list_wallets(root: &Path) -> Result<Vec<WalletEntry>, FfiError> {
+    let entries = match fs::read_dir(root) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(io_error("Unable to list the wallets", e)),
+    };
+    let mut wallets = Vec::new();
+    for dir_entry in entries {
+        let dir_entry = dir_entry.map_err(|e| io_error("Unable to list the wallets", e))?;
+        let dir = dir_entry.path();
+        let name = match dir_entry.file_name().into_string() {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+        if validate_name(&name).is_ok() && wallet_seed_exists(&dir) {
+            wallets.push(entry(&name, &dir));
+        }
+    }
+    wallets.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(wallets)
+}
+
+/// The config of the wallet named `name` under `root`, based on `base`.
+///
+/// The wallet directory is the wallet's own, and the chain is the one the
+/// wallet was created on if that was recorded, so mainnet and floonet wallets
+/// can be opened with the same base config.
+pub fn wallet_config(root: &Path, base: &Config, name: &str) -> Result<Config, FfiError> {
+    let dir = existing_wallet_dir(root, name)?;
+    let mut config = base.clone();
+    if let Some(chain) = read_wallet_metadata(&dir).and_then(|m| m.chain) {
+        config.chain = chain;
+    }
+    config.wallet_dir = dir.to_string_lossy().into_owned();
+    Ok(config)
+}
+
+/// Create a wallet named `name` under `root` from `mnemonic`, on the chain of
+/// `base`.
+pub fn create_wallet(
+    root: &Path,
+    base: &Config,
+    name: &str,
+    mnemonic: &str,
+    password: &str,
+) -> Result<WalletEntry, FfiError> {
+    let dir = wallet_dir(root, name)?;
+    if wallet_seed_exists(&dir) {
+        return Err(FfiError::new(
+            ErrorCode::WalletSeedExists,
+            format!("A wallet named '{}' already exists", name),
+        ));
+    }
+    let new_dir = !dir.exists();
+    fs::create_dir_all(&dir).map_err(|e| io_error("Unable to create the wallet directory", e))?;
+    let mut claim = match session::claim_wallet_dir(&dir) {
+        Ok(claim) => claim,
+        Err(e) => {
+            if new_dir {
+                let _ = fs::remove_dir(&dir);
+            }
+            return Err(e);
+        }
+    };
+
+    let mut config = base.clone();
+    config.wallet_dir = dir.to_string_lossy().into_owned();
+    let config_json = serde_json::to_string(&config)?;
+    if let Err(e) = wallet::create_wallet(&config_json, mnemonic, password, name) {
+        if new_dir {
+            claim.release_lock();
+            let _ = fs::remove_dir_all(&dir);
+        }
+        return Err(e.into());
+    }
+    Ok(entry(name, &dir))
+}
+
+/// Rename the wallet named `name` under `root` to `new_name`.
+///
+/// The wallet must be closed; this fails with `lifecycle` if a session has it
+/// open and with `wallet_locked` if another process does.
+pub fn rename_wallet(root: &Path, name: &str, new_name: &str) -> Result<WalletEntry, FfiError> {
+    let dir = existing_wallet_dir(root, name)?;
+    let new_dir = wallet_dir(root, new_name)?;
+    if new_dir.exists() {
+        return Err(FfiError::new(
+            ErrorCode::WalletSeedExists,
+            format!("A wallet named '{}' already exists", new_name),
+        ));
+    }
+    let mut claim = session::claim_wallet_dir(&dir)?;
+    let _new_claim = session::claim_wallet_dir(&new_dir)?;
+    claim.release_lock();
+    fs::rename(&dir, &new_dir).map_err(|e| io_error("Unable to rename the wallet", e))?;
+
+    let metadata = match read_wallet_metadata(&new_dir) {
+        Some(metadata) => WalletMetadata { name: new_name.to_string(), ..metadata },
+        None => WalletMetadata {
+            name: new_name.to_string(),
+            chain: None,
+            created_at: None,
+            last_confirmed_height: None,
+        },
+    };
+    write_wallet_metadata(&new_dir, &metadata)?;
+    Ok(entry(new_name, &new_dir))
+}
+
+/// Delete the wallet named `name` under `root`, as [`delete_wallet_dir`]
+/// does.
+pub fn delete_wallet(root: &Path, name: &str) -> Result<DeletedWallet, FfiError> {
+    let dir = existing_wallet_dir(root, name)?;
+    delete_wallet_dir(&dir)
+}
+
+/// Files directly in a wallet directory that belong to the wallet, besides
+/// its data directory, logs and lock file.
+const WALLET_FILES: &[&str] = &[".api_secret"];
+
+/// What deleting a wallet did.
+#[derive(Debug, Default, Serialize)]
+pub struct DeletedWallet {
+    /// Every file removed, relative to the wallet directory with `/`
+    /// separators.
+    pub removed: Vec<String>,
+    /// Files in the wallet directory that do not belong to the wallet and
+    /// were kept, along with the directory itself.
+    pub kept: Vec<String>,
+    /// Number of sessions that had the wallet open and were closed.
+    pub closed_sessions: usize,
+}
+
+/// Delete the wallet in `dir`.
+///
+/// Needs neither the password nor a working wallet database. A session that
+/// has the wallet open is closed first, which stops its listeners and makes
+/// its id invalid. Only the files the wallet owns are removed: its data
+/// directory, with the seed files overwritten first, its API secret, its log
+/// files and, last, its lock file. The directory itself is removed if nothing
+/// else is left in it; anything else is listed in `kept`. The wallet stays
+/// locked until its lock file goes. Fails with `wallet_locked` if another
+/// process has the wallet open, and with `invalid_argument` if `dir` is not a
+/// wallet directory. If removing a file fails, the error details list the
+/// files removed before it.
+pub fn delete_wallet_dir(dir: &Path) -> Result<DeletedWallet, FfiError> {
+    let mut deleted = DeletedWallet::default();
+    if let Some((id, _)) = session::find_session_by_dir(dir) {
+        if let Ok(session) = session::close_session(id) {
+            // A corrupted database may fail to close; it is deleted anyway.
+            let _ = wallet::close_wallet(session.wallet());
+            deleted.closed_sessions += 1;
+        }
+    }
+    if !dir.exists() {
+        return Ok(deleted);
+    }
+    if !wallet::is_wallet_dir(dir) {
+        return Err(FfiError::new(
+            ErrorCode::InvalidArgument,
+            format!("{} does not hold a wallet", dir.display()),
+        ));
+    }
+
+    let mut claim = session::claim_wallet_dir(dir)?;
+    let wiped = remove_wallet(dir, &mut claim, &mut deleted);
+    drop(claim);
+    match wiped {
+        Ok(()) => Ok(deleted),
+        Err(e) => Err(FfiError {
+            details: Some(serde_json::json!({ "removed": deleted.removed })),
+            ..e
+        }),
+    }
+}
+
+/// Remove the files of the claimed wallet in `dir`, its lock file last.
+fn remove_wallet(
+    dir: &Path,
+    claim: &mut session::WalletDirClaim,
+    deleted: &mut DeletedWallet,
+) -> Result<(), FfiError> {
+    wipe_seed_files(dir, &mut deleted.removed)?;
+    remove_wallet_files(dir, &mut deleted.removed)?;
+    let had_lock_file = claim
+        .remove_lock_file()
+        .map_err(|e| io_error("Unable to remove the wallet lock file", e))?;
+    if had_lock_file {
+        deleted.removed.push(session::LOCK_FILE_NAME.to_string());
+    }
+    remove_dir_if_empty(dir, &mut deleted.kept)
+}
+
+/// Remove the data directory, API secret and log files of the wallet in
+/// `dir`, recording each removed file.
+fn remove_wallet_files(dir: &Path, removed: &mut Vec<String>) -> Result<(), FfiError> {
+    let data_dir = dir.join(wallet::WALLET_DATA_DIR);
+    remove_tree(&data_dir, wallet::WALLET_DATA_DIR, removed)?;
+    fs::remove_dir(&data_dir).map_err(|e| io_error("Unable to delete the wallet", e))?;
+
+    for name in WALLET_FILES {
+        remove_file_if_exists(dir, Path::new(name), removed)?;
+    }
+
+    let log_dir = dir.join(LOG_DIR_NAME);
+    if log_dir.is_dir() {
+        let mut names = fs::read_dir(&log_dir)
+            .map_err(|e| io_error("Unable to read the log directory", e))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| is_log_file_name(name))
+            .collect::<Vec<_>>();
+        names.sort();
+        for name in names {
+            remove_file_if_exists(dir, &Path::new(LOG_DIR_NAME).join(name), removed)?;
+        }
+        // Other files in the log directory are not the wallet's to remove.
+        let _ = fs::remove_dir(&log_dir);
+    }
+    Ok(())
+}
+
+fn remove_file_if_exists(dir: &Path, relative: &Path, removed: &mut Vec<String>) -> Result<(), FfiError> {
+    let path = dir.join(relative);
+    match fs::remove_file(&path) {
+        Ok(()) => {
+            removed.push(relative_path(dir, &path));
+            Ok(())
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(io_error("Unable to delete the wallet", e)),
+    }
+}
+
+/// Remove `dir` if it is empty, or list what is left in it in `kept`.
+fn remove_dir_if_empty(dir: &Path, kept: &mut Vec<String>) -> Result<(), FfiError> {
+    let mut left = fs::read_dir(dir)
+        .map_err(|e| io_error("Unable to read the wallet directory", e))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    if left.is_empty() {
+        fs::remove_dir(dir).map_err(|e| io_error("Unable to delete the wallet directory", e))
+    } else {
+        left.sort();
+        *kept = left;
+        Ok(())
+    }
+}
+
+/// Overwrite the seed files of the wallet in `dir` with zeros and remove them.
+fn wipe_seed_files(dir: &Path, removed: &mut Vec<String>) -> Result<(), FfiError> {
+    for path in wallet::seed_files(dir) {
+        let len = match fs::metadata(&path) {
+            Ok(metadata) => metadata.len(),
+            Err(_) => continue,
+        };
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .map_err(|e| io_error("Unable to open the seed file", e))?;
+        io::copy(&mut io::repeat(0).take(len), &mut file)
+            .and_then(|_| file.sync_all())
+            .map_err(|e| io_error("Unable to overwrite the seed file", e))?;
+        drop(file);
+        fs::remove_file(&path).map_err(|e| io_error("Unable to remove the seed file", e))?;
+        removed.push(relative_path(dir, &path));
+    }
+    Ok(())
+}
+
+fn relative_path(dir: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(dir).unwrap_or(path);
+    let parts: Vec<_> = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+    parts.join("/")
+}
+
+/// Remove everything under `dir`, a directory the wallet owns, recording each
+/// removed file. Symbolic links are removed, not followed.
+fn remove_tree(dir: &Path, prefix: &str, removed: &mut Vec<String>) -> Result<(), FfiError> {
+    let mut entries = fs::read_dir(dir)
+        .map_err(|e| io_error("Unable to read the wallet directory", e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| io_error("Unable to read the wallet directory", e))?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let relative = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+        let file_type = entry.file_type().map_err(|e| io_error("Unable to read the wallet directory", e))?;
+        if file_type.is_dir() {
+            remove_tree(&entry.path(), &relative, removed)?;
+            fs::remove_dir(entry.path()).map_err(|e| io_error("Unable to delete the wallet", e))?;
+        } else {
+            fs::remove_file(entry.path()).map_err(|e| io_error("Unable to delete the wallet", e))?;
+            removed.push(relative);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::fake_wallet;
+
+    fn base_config() -> Config {
+        Config {
+            wallet_dir: String::new(),
+            check_node_api_http_addr: "http://localhost:3413".to_string(),
+            chain: "mainnet".to_string(),
+            account: None,
+            api_listen_port: 3415,
+            api_listen_interface: "127.0.0.1".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_create_wallet_leaves_no_directory_when_claimed() {
+        let root = PathBuf::from("test_manager_create_claimed");
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        let dir = fs::canonicalize(&root).unwrap().join("held");
+        let _claim = session::claim_wallet_dir(&dir).unwrap();
+
+        let created = create_wallet(&root, &base_config(), "held", "mnemonic", "password");
+        assert_eq!(created.unwrap_err().code, ErrorCode::Lifecycle);
+        assert!(!dir.exists());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_list_wallets() {
+        let root = PathBuf::from("test_manager_list");
+        let _ = fs::remove_dir_all(&root);
+        assert!(list_wallets(&root).unwrap().is_empty());
+
+        fake_wallet(&root.join("savings"), Some("mainnet"));
+        fake_wallet(&root.join("floonet test"), Some("floonet"));
+        fake_wallet(&root.join("legacy"), None);
+        fs::create_dir_all(root.join("not a wallet")).unwrap();
+
+        let wallets = list_wallets(&root).unwrap();
+        let names: Vec<_> = wallets.iter().map(|w| w.name.as_str()).collect();
+        assert_eq!(names, ["floonet test", "legacy", "savings"]);
+        assert_eq!(wallets[0].chain.as_deref(), Some("floonet"));
+        assert_eq!(wallets[0].created_at, Some(1_700_000_000));
+        assert_eq!(wallets[1].chain, None);
+
+        let config = wallet_config(&root, &base_config(), "floonet test").unwrap();
+        assert_eq!(config.chain, "floonet");
+        assert_eq!(PathBuf::from(config.wallet_dir), root.join("floonet test"));
+        assert_eq!(wallet_config(&root, &base_config(), "legacy").unwrap().chain, "mainnet");
+        assert_eq!(
+            wallet_config(&root, &base_config(), "missing").unwrap_err().code,
+            ErrorCode::WalletSeedDoesntExist
+        );
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_rename_and_delete_wallet() {
+        let root = PathBuf::from("test_manager_rename");
+        let _ = fs::remove_dir_all(&root);
+        fake_wallet(&root.join("old"), Some("mainnet"));
+        fake_wallet(&root.join("taken"), Some("mainnet"));
+
+        assert_eq!(rename_wallet(&root, "old", "taken").unwrap_err().code, ErrorCode::WalletSeedExists);
+        let renamed = rename_wallet(&root, "old", "new").unwrap();
+        assert_eq!(renamed.name, "new");
+        assert_eq!(read_wallet_metadata(&root.join("new")).unwrap().name, "new");
+        assert!(!root.join("old").exists());
+
+        let deleted = delete_wallet(&root, "new").unwrap();
+        assert_eq!(
+            deleted.removed,
+            ["wallet_data/wallet.seed", "wallet_data/wallet_meta.json", ".wallet.lock"]
+        );
+        assert!(deleted.kept.is_empty());
+        assert!(!root.join("new").exists());
+        assert_eq!(delete_wallet(&root, "new").unwrap_err().code, ErrorCode::WalletSeedDoesntExist);
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_delete_wallet_dir_refuses_other_directories() {
+        let dir = PathBuf::from("test_manager_not_a_wallet");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("notes.txt"), b"keep me").unwrap();
+
+        assert_eq!(delete_wallet_dir(&dir).unwrap_err().code, ErrorCode::InvalidArgument);
+        assert!(dir.join("notes.txt").exists());
+        assert!(delete_wallet_dir(&dir.join("missing")).unwrap().removed.is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_delete_wallet_dir_keeps_other_files() {
+        let root = PathBuf::from("test_manager_shared_dir");
+        let _ = fs::remove_dir_all(&root);
+        fake_wallet(&root.join("shared"), None);
+        let dir = root.join("shared");
+        fs::write(dir.join(".api_secret"), b"secret").unwrap();
+        fs::create_dir_all(dir.join("logs")).unwrap();
+        fs::write(dir.join("logs/epic-cash-wallet.log"), b"log line").unwrap();
+        fs::write(dir.join("logs/epic-cash-wallet.log.1"), b"old log line").unwrap();
+        fs::write(dir.join("logs/other.log"), b"not ours").unwrap();
+        fs::write(dir.join("photo.jpg"), b"not a wallet file").unwrap();
+
+        let deleted = delete_wallet_dir(&dir).unwrap();
+        assert_eq!(
+            deleted.removed,
+            [
+                "wallet_data/wallet.seed",
+                ".api_secret",
+                "logs/epic-cash-wallet.log",
+                "logs/epic-cash-wallet.log.1",
+                ".wallet.lock",
+            ]
+        );
+        assert_eq!(deleted.kept, ["logs", "photo.jpg"]);
+        assert!(dir.join("photo.jpg").exists());
+        assert!(dir.join("logs/other.log").exists());
+        assert!(!dir.join("wallet_data").exists());
+
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn test_validate_name() {
+        for name in ["main", "Floonet 2", "my-wallet_1.0", "Épargne"] {
+            assert!(validate_name(name).is_ok(), "{}", name);
+        }
+        for name in ["", ".hidden", "..", "a/b", "a\\b", " padded", "tab\t", &"x".repeat(65)] {
+            assert!(validate_name(name).is_err(), "{}", name);
+        }
+    }
+}
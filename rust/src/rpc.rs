@@ -0,0 +1,226 @@
+use easy_jsonrpc_mw::{Handler, MaybeReply};
+use epic_util::secp::key::SecretKey;
+use epic_wallet_api::{Foreign, ForeignRpc, Owner, OwnerRpcS};
+use serde_json::{json, Map, Value};
+
+use crate::envelope::{ErrorCode, FfiError};
+use crate::session::Session;
+
+/// JSON-RPC error code for a method that does not exist or is not available.
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Owner RPC methods that open, close or re-key the wallet, or reveal its
+/// secrets. Sessions manage all of that themselves, so these are refused rather
+/// than allowed to act behind the session's back.
+const BLOCKED_OWNER_METHODS: &[&str] = &[
+    "init_secure_api",
+    "get_top_level_directory",
+    "set_top_level_directory",
+    "create_config",
+    "create_wallet",
+    "open_wallet",
+    "close_wallet",
+    "get_mnemonic",
+    "change_password",
+    "delete_wallet",
+    "start_updater",
+    "stop_updater",
+];
+
+/// Owner RPC methods that spend from the wallet, which a locked session
+/// refuses.
+const SPENDING_OWNER_METHODS: &[&str] = &[
+    "init_send_tx",
+    "process_invoice_tx",
+    "tx_lock_outputs",
+    "finalize_tx",
+    "post_tx",
+];
+
+/// Owner RPC methods whose signature takes a `token`, which gets the session's
+/// keychain mask. The others, such as `get_updater_messages` and
+/// `set_tor_config`, would reject the extra parameter.
+const TOKEN_OWNER_METHODS: &[&str] = &[
+    "accounts",
+    "create_account_path",
+    "set_active_account",
+    "retrieve_outputs",
+    "retrieve_txs",
+    "retrieve_summary_info",
+    "init_send_tx",
+    "issue_invoice_tx",
+    "process_invoice_tx",
+    "tx_lock_outputs",
+    "finalize_tx",
+    "post_tx",
+    "cancel_tx",
+    "get_stored_tx",
+    "verify_slate_messages",
+    "scan",
+    "node_height",
+    "get_public_proof_address",
+    "retrieve_payment_proof",
+    "verify_payment_proof",
+];
+
+/// Parse a single JSON-RPC 2.0 request object, returning it with its method.
+fn parse_request(request: &str) -> Result<(Map<String, Value>, String), FfiError> {
+    let request: Value = serde_json::from_str(request).map_err(|e| {
+        FfiError::new(ErrorCode::InvalidArgument, format!("Invalid JSON-RPC request: {}", e))
+    })?;
+    let request = match request {
+        Value::Object(request) => request,
+        _ => {
+            return Err(FfiError::new(
+                ErrorCode::InvalidArgument,
+                "Expected a single JSON-RPC request object",
+            ))
+        }
+    };
+    let method = match request.get("method").and_then(Value::as_str) {
+        Some(method) => method.to_string(),
+        None => {
+            return Err(FfiError::new(
+                ErrorCode::InvalidArgument,
+                "JSON-RPC request has no method",
+            ))
+        }
+    };
+    Ok((request, method))
+}
+
+/// A JSON-RPC error reply to `request`.
+fn error_reply(request: &Map<String, Value>, code: i64, message: String) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": request.get("id").cloned().unwrap_or(Value::Null),
+        "error": { "code": code, "message": message },
+    })
+}
+
+/// Set the `token` parameter of an owner request to `keychain_mask`, replacing
+/// any token the caller sent. Positional parameters take the token first.
+fn inject_token(
+    request: &mut Map<String, Value>,
+    keychain_mask: Option<SecretKey>,
+) -> Result<(), FfiError> {
+    let token = json!({ "keychain_mask": keychain_mask.map(|mask| hex::encode(mask.0)) });
+    match request.get_mut("params") {
+        Some(Value::Object(params)) => {
+            params.insert("token".to_string(), token);
+        }
+        Some(Value::Array(params)) => params.insert(0, token),
+        Some(Value::Null) | None => {
+            request.insert("params".to_string(), json!({ "token": token }));
+        }
+        Some(_) => {
+            return Err(FfiError::new(
+                ErrorCode::InvalidArgument,
+                "JSON-RPC params must be an object or an array",
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Run an owner JSON-RPC 2.0 request against a session's wallet.
+///
+/// The session's keychain mask is passed as the `token` of methods that take
+/// one, status messages go to the session's queue and `rust_cancel_operation`
+/// stops the request. Spending methods fail with `session_locked` while the
+/// session is locked. Returns the JSON-RPC response, or null for a
+/// notification.
+pub fn owner_rpc(session: &Session, request: &str) -> Result<Value, FfiError> {
+    let (mut request, method) = parse_request(request)?;
+    if BLOCKED_OWNER_METHODS.contains(&method.as_str()) {
+        return Ok(error_reply(
+            &request,
+            METHOD_NOT_FOUND,
+            format!("Method '{}' is not available through the owner RPC export", method),
+        ));
+    }
+    let spends = SPENDING_OWNER_METHODS.contains(&method.as_str());
+    if spends {
+        session.ensure_unlocked()?;
+    }
+    if TOKEN_OWNER_METHODS.contains(&method.as_str()) {
+        inject_token(&mut request, session.keychain_mask())?;
+    }
+
+    let stop = session.begin_operation();
+    let api = Owner::new(session.wallet().clone(), session.status_sender(), stop.flag());
+    let reply = reply_value((&api as &dyn OwnerRpcS).handle_request(Value::Object(request)));
+    // Owner methods report their own failures as an `Err` result.
+    if spends && reply["result"].get("Ok").is_some() {
+        session.record_activity();
+    }
+    Ok(reply)
+}
+
+/// Run a foreign JSON-RPC 2.0 request against a session's wallet.
+///
+/// Requests and responses have the same shape as the epic-wallet foreign HTTP
+/// API. Returns the JSON-RPC response, or null for a notification.
+pub fn foreign_rpc(session: &Session, request: &str) -> Result<Value, FfiError> {
+    let (request, _) = parse_request(request)?;
+    let api = Foreign::new(session.wallet().clone(), session.keychain_mask(), None);
+    Ok(reply_value((&api as &dyn ForeignRpc).handle_request(Value::Object(request))))
+}
+
+fn reply_value(reply: MaybeReply) -> Value {
+    match reply {
+        MaybeReply::Reply(reply) => reply,
+        MaybeReply::DontReply => Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request_rejects_non_requests() {
+        assert!(parse_request("{\"method\": \"accounts\"").is_err());
+        assert!(parse_request("[{\"method\": \"accounts\"}]").is_err());
+        assert!(parse_request("{\"jsonrpc\": \"2.0\", \"id\": 1}").is_err());
+
+        let (_, method) = parse_request("{\"jsonrpc\": \"2.0\", \"id\": 1, \"method\": \"accounts\"}").unwrap();
+        assert_eq!(method, "accounts");
+    }
+
+    #[test]
+    fn test_inject_token_overrides_caller_token() {
+        let (mut named, _) = parse_request(
+            "{\"method\": \"accounts\", \"params\": {\"token\": {\"keychain_mask\": \"00\"}}}",
+        ).unwrap();
+        inject_token(&mut named, None).unwrap();
+        assert_eq!(named["params"], json!({ "token": { "keychain_mask": null } }));
+
+        let (mut positional, _) = parse_request("{\"method\": \"node_height\", \"params\": []}").unwrap();
+        inject_token(&mut positional, None).unwrap();
+        assert_eq!(positional["params"], json!([{ "keychain_mask": null }]));
+
+        let (mut missing, _) = parse_request("{\"method\": \"accounts\"}").unwrap();
+        inject_token(&mut missing, None).unwrap();
+        assert_eq!(missing["params"], json!({ "token": { "keychain_mask": null } }));
+
+        let (mut scalar, _) = parse_request("{\"method\": \"accounts\", \"params\": 1}").unwrap();
+        assert!(inject_token(&mut scalar, None).is_err());
+    }
+
+    #[test]
+    fn test_token_only_for_methods_taking_one() {
+        assert!(TOKEN_OWNER_METHODS.contains(&"accounts"));
+        assert!(!TOKEN_OWNER_METHODS.contains(&"get_updater_messages"));
+        assert!(!TOKEN_OWNER_METHODS.contains(&"set_tor_config"));
+        assert!(TOKEN_OWNER_METHODS.iter().all(|m| !BLOCKED_OWNER_METHODS.contains(m)));
+    }
+
+    #[test]
+    fn test_blocked_methods_get_error_replies() {
+        let (request, _) = parse_request("{\"jsonrpc\": \"2.0\", \"id\": 7, \"method\": \"open_wallet\"}").unwrap();
+        let reply = error_reply(&request, METHOD_NOT_FOUND, "nope".to_string());
+        assert_eq!(reply["id"], 7);
+        assert_eq!(reply["error"]["code"], METHOD_NOT_FOUND);
+    }
+}
@@ -0,0 +1,421 @@
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use epic_util::Mutex;
+use epic_util::secp::key::SecretKey;
+use epic_wallet_libwallet::StatusMessage;
+use zeroize::Zeroize;
+
+use crate::envelope::{ErrorCode, FfiError};
+use crate::handles::HandleTable;
+use crate::status::StatusQueue;
+use crate::wallet::Wallet;
+
+/// Opaque id of an open wallet session, as held by FFI callers.
+///
+/// This is the only thing callers ever see of an open wallet; the wallet and
+/// its keychain mask stay on this side of the boundary.
+pub type SessionId = u64;
+
+/// An open wallet together with the keychain mask that unlocks it.
+pub struct Session {
+    wallet: Wallet,
+    /// Shared with long-running tasks such as the epicbox listener, so that
+    /// clearing it on close also locks them out.
+    keychain_mask: Arc<Mutex<Option<SecretKey>>>,
+    status: StatusQueue,
+    /// The wallet directory this session holds, if it was opened from one.
+    wallet_dir: Option<PathBuf>,
+    /// The lock file that keeps other processes from opening the wallet.
+    dir_lock: std::sync::Mutex<Option<File>>,
+    /// Stop flags of the operations in flight on this session.
+    in_flight: StopFlags,
+    idle_lock: std::sync::Mutex<IdleLock>,
+    /// Flags that keep the epicbox listeners of this session processing
+    /// messages.
+    listening: std::sync::Mutex<Vec<Arc<AtomicBool>>>,
+}
+
+/// When a session stops allowing spends.
+struct IdleLock {
+    /// How long the session may go without spending before it locks. `None`
+    /// keeps it unlocked until closed.
+    timeout: Option<Duration>,
+    last_active: Instant,
+    locked: bool,
+}
+
+impl Session {
+    pub fn new(wallet: Wallet, keychain_mask: Option<SecretKey>) -> Self {
+        Session {
+            wallet,
+            keychain_mask: Arc::new(Mutex::new(keychain_mask)),
+            status: StatusQueue::new(),
+            wallet_dir: None,
+            dir_lock: std::sync::Mutex::new(None),
+            in_flight: Arc::new(std::sync::Mutex::new(Vec::new())),
+            idle_lock: std::sync::Mutex::new(IdleLock {
+                timeout: None,
+                last_active: Instant::now(),
+                locked: false,
+            }),
+            listening: std::sync::Mutex::new(Vec::new()),
+        }
+    }
+
+    /// The open wallet.
+    pub fn wallet(&self) -> &Wallet {
+        &self.wallet
+    }
+
+    /// The wallet directory this session holds, if it was opened from one.
+    pub fn wallet_dir(&self) -> Option<&Path> {
+        self.wallet_dir.as_deref()
+    }
+
+    /// Whether this session holds the wallet directory `dir`.
+    pub fn holds_wallet_dir(&self, dir: &Path) -> bool {
+        let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+        self.wallet_dir.as_deref() == Some(dir.as_path())
+    }
+
+    /// A copy of the keychain mask, for a single wallet call.
+    pub fn keychain_mask(&self) -> Option<SecretKey> {
+        self.keychain_mask.lock().clone()
+    }
+
+    /// The keychain mask itself, for tasks that outlive a single call.
+    pub fn shared_keychain_mask(&self) -> Arc<Mutex<Option<SecretKey>>> {
+        self.keychain_mask.clone()
+    }
+
+    /// Status messages sent by owner API calls for this session.
+    pub fn status(&self) -> &StatusQueue {
+        &self.status
+    }
+
+    /// A sender for owner API calls to report status messages on.
+    pub fn status_sender(&self) -> Option<Sender<StatusMessage>> {
+        Some(self.status.sender())
+    }
+
+    /// Start an operation that `stop_operations` can stop until the returned
+    /// token is dropped.
+    pub fn begin_operation(&self) -> StopToken {
+        let flag = Arc::new(AtomicBool::new(false));
+        lock_flags(&self.in_flight).push(flag.clone());
+        StopToken {
+            flag,
+            in_flight: self.in_flight.clone(),
+        }
+    }
+
+    /// Ask every operation in flight on this session to stop, returning how
+    /// many were asked.
+    pub fn stop_operations(&self) -> usize {
+        let in_flight = lock_flags(&self.in_flight);
+        for flag in in_flight.iter() {
+            flag.store(true, Ordering::SeqCst);
+        }
+        in_flight.len()
+    }
+
+    /// A flag for an epicbox listener of this session to process messages
+    /// while set. `stop_listeners` clears it.
+    pub fn listen_flag(&self) -> Arc<AtomicBool> {
+        let flag = Arc::new(AtomicBool::new(true));
+        lock_flags(&self.listening).push(flag.clone());
+        flag
+    }
+
+    /// Make every epicbox listener of this session stop processing messages,
+    /// returning how many were stopped.
+    pub fn stop_listeners(&self) -> usize {
+        let mut listening = lock_flags(&self.listening);
+        for flag in listening.iter() {
+            flag.store(false, Ordering::SeqCst);
+        }
+        listening.drain(..).count()
+    }
+
+    /// Lock the session after `timeout` without spending, or never with `None`.
+    /// The idle time counts from now.
+    pub fn set_idle_timeout(&self, timeout: Option<Duration>) {
+        let mut idle = self.lock_idle();
+        idle.timeout = timeout;
+        idle.last_active = Instant::now();
+    }
+
+    /// Whether the session has been idle for longer than its timeout.
+    pub fn is_locked(&self) -> bool {
+        let mut idle = self.lock_idle();
+        if let Some(timeout) = idle.timeout {
+            if idle.last_active.elapsed() >= timeout {
+                idle.locked = true;
+            }
+        }
+        idle.locked
+    }
+
+    /// Check that the session may spend.
+    pub fn ensure_unlocked(&self) -> Result<(), FfiError> {
+        if self.is_locked() {
+            return Err(FfiError::new(
+                ErrorCode::SessionLocked,
+                "The wallet was locked after being idle; unlock it with its password",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Count a spend as activity.
+    ///
+    /// Only successful spends and unlocks count as activity. Failed spends,
+    /// balances, history and refreshes do not, so a wallet that is refreshed in
+    /// the background or fed bad requests still locks when nobody uses it.
+    pub fn record_activity(&self) {
+        self.lock_idle().last_active = Instant::now();
+    }
+
+    /// Run `spend` if the session may spend, counting it as activity once it
+    /// has succeeded.
+    pub fn spend<T, F>(&self, spend: F) -> Result<T, FfiError>
+    where
+        F: FnOnce() -> Result<T, FfiError>,
+    {
+        self.ensure_unlocked()?;
+        let result = spend()?;
+        self.record_activity();
+        Ok(result)
+    }
+
+    /// Allow spends again. The caller checks the password first.
+    pub fn unlock(&self) {
+        let mut idle = self.lock_idle();
+        idle.locked = false;
+        idle.last_active = Instant::now();
+    }
+
+    fn lock_idle(&self) -> std::sync::MutexGuard<'_, IdleLock> {
+        self.idle_lock.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Zeroize and forget the keychain mask.
+    pub fn clear_keychain_mask(&self) {
+        if let Some(mut mask) = self.keychain_mask.lock().take() {
+            mask.0.zeroize();
+        }
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        self.clear_keychain_mask();
+    }
+}
+
+type StopFlags = Arc<std::sync::Mutex<Vec<Arc<AtomicBool>>>>;
+
+fn lock_flags(
+    flags: &std::sync::Mutex<Vec<Arc<AtomicBool>>>,
+) -> std::sync::MutexGuard<'_, Vec<Arc<AtomicBool>>> {
+    flags.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// An operation in flight on a session.
+///
+/// Its flag is handed to the owner API as `is_stopped`, which makes scans,
+/// refreshes and transaction building stop at the next point where the wallet
+/// is consistent.
+pub struct StopToken {
+    flag: Arc<AtomicBool>,
+    in_flight: StopFlags,
+}
+
+impl StopToken {
+    /// The flag to hand to the owner API and the wallet functions.
+    pub fn flag(&self) -> Arc<AtomicBool> {
+        self.flag.clone()
+    }
+
+    /// Whether the operation was asked to stop.
+    pub fn is_stopped(&self) -> bool {
+        self.flag.load(Ordering::SeqCst)
+    }
+
+    /// Report a failure of a stopped operation as `cancelled`. Results of
+    /// operations that finished anyway are passed through.
+    pub fn finish<T, E: Into<FfiError>>(&self, result: Result<T, E>) -> Result<T, FfiError> {
+        result.map_err(|e| {
+            let err = e.into();
+            if self.is_stopped() {
+                FfiError {
+                    code: ErrorCode::Cancelled,
+                    message: format!("Operation was cancelled: {}", err.message),
+                    details: err.details,
+                }
+            } else {
+                err
+            }
+        })
+    }
+}
+
+impl Drop for StopToken {
+    fn drop(&mut self) {
+        lock_flags(&self.in_flight).retain(|flag| !Arc::ptr_eq(flag, &self.flag));
+    }
+}
+
+/// Every wallet session opened through the FFI.
+static SESSIONS: std::sync::Mutex<HandleTable<Session>> =
+    std::sync::Mutex::new(HandleTable::new());
+
+fn sessions() -> std::sync::MutexGuard<'static, HandleTable<Session>> {
+    // A panic while holding the lock cannot leave the table half-updated.
+    SESSIONS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Wallet directories held by an open session or by a wallet being opened.
+static OPEN_WALLET_DIRS: std::sync::Mutex<Vec<PathBuf>> = std::sync::Mutex::new(Vec::new());
+
+fn open_wallet_dirs() -> std::sync::MutexGuard<'static, Vec<PathBuf>> {
+    OPEN_WALLET_DIRS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn release_wallet_dir(dir: &Path) {
+    open_wallet_dirs().retain(|open| open != dir);
+}
+
+/// Name of the file in a wallet directory that is locked while the wallet is
+/// open, so that other processes do not open it too.
+pub(crate) const LOCK_FILE_NAME: &str = ".wallet.lock";
+
+/// Lock the lock file in `dir` for this process. Returns `None` if there is
+/// nothing to lock, such as when the directory does not exist yet.
+fn lock_wallet_dir(dir: &Path) -> Result<Option<File>, FfiError> {
+    if !dir.is_dir() {
+        return Ok(None);
+    }
+    let file = match OpenOptions::new().create(true).write(true).open(dir.join(LOCK_FILE_NAME)) {
+        Ok(file) => file,
+        // A read-only wallet directory cannot hold a lock; other processes
+        // cannot write to the wallet either.
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => return Ok(None),
+        Err(e) => {
+            return Err(FfiError::new(
+                ErrorCode::Backend,
+                format!("Unable to open the wallet lock file: {}", e),
+            ))
+        }
+    };
+    match file.try_lock() {
+        Ok(()) => Ok(Some(file)),
+        Err(TryLockError::WouldBlock) => Err(FfiError::new(
+            ErrorCode::WalletLocked,
+            format!("The wallet in {} is open in another process", dir.display()),
+        )),
+        Err(TryLockError::Error(e)) => Err(FfiError::new(
+            ErrorCode::Backend,
+            format!("Unable to lock the wallet: {}", e),
+        )),
+    }
+}
+
+/// A wallet directory claimed for a wallet that is being opened.
+///
+/// Dropping the claim without opening a session gives the directory up again.
+pub struct WalletDirClaim {
+    dir: Option<PathBuf>,
+    lock: Option<File>,
+}
+
+/// Claim `dir` for a wallet that is about to be opened.
+///
+/// Fails with a `lifecycle` error if the directory is already claimed in this
+/// process, and with `wallet_locked` if another process has it open. Two open
+/// instances of one wallet would each write to its database without the other
+/// knowing, so each wallet directory is held by at most one session.
+pub fn claim_wallet_dir(dir: &Path) -> Result<WalletDirClaim, FfiError> {
+    let dir = fs::canonicalize(dir).unwrap_or_else(|_| dir.to_path_buf());
+    let mut open = open_wallet_dirs();
+    if open.contains(&dir) {
+        return Err(FfiError::new(
+            ErrorCode::Lifecycle,
+            format!("The wallet in {} is already open", dir.display()),
+        ));
+    }
+    let lock = lock_wallet_dir(&dir)?;
+    open.push(dir.clone());
+    Ok(WalletDirClaim { dir: Some(dir), lock })
+}
+
+impl WalletDirClaim {
+    /// Register the opened wallet as a session holding the claimed directory,
+    /// and return its session id.
+    pub fn open_session(mut self, wallet: Wallet, keychain_mask: Option<SecretKey>) -> SessionId {
+        let mut session = Session::new(wallet, keychain_mask);
+        session.wallet_dir = self.dir.take();
+        session.dir_lock = std::sync::Mutex::new(self.lock.take());
+        sessions().insert(session)
+    }
+
+    /// Give up the lock file but keep the directory claimed in this process,
+    /// so that the directory can be moved or removed on platforms that do not
+    /// allow it while the file is open.
+    pub fn release_lock(&mut self) {
+        self.lock.take();
+    }
+
+    /// Give up the lock file and remove it, keeping the directory claimed in
+    /// this process. Returns whether there was a lock file to remove.
+    pub fn remove_lock_file(&mut self) -> io::Result<bool> {
+        self.release_lock();
+        let Some(dir) = &self.dir else { return Ok(false) };
+        match fs::remove_file(dir.join(LOCK_FILE_NAME)) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Drop for WalletDirClaim {
+    fn drop(&mut self) {
+        if let Some(dir) = self.dir.take() {
+            release_wallet_dir(&dir);
+        }
+    }
+}
+
+/// The open session holding the wallet directory `dir` and its id, if there
+/// is one.
+pub fn find_session_by_dir(dir: &Path) -> Option<(SessionId, Arc<Session>)> {
+    sessions().find(|session| session.holds_wallet_dir(dir))
+}
+
+/// Get an open session by id.
+pub fn get_session(id: SessionId) -> Result<Arc<Session>, FfiError> {
+    sessions().get(id)
+}
+
+/// Unregister a session, stop its operations and listeners, zeroize its
+/// keychain mask and give up its wallet directory. The id is invalid from here
+/// on.
+pub fn close_session(id: SessionId) -> Result<Arc<Session>, FfiError> {
+    let session = sessions().remove(id)?;
+    session.stop_operations();
+    session.stop_listeners();
+    session.clear_keychain_mask();
+    if let Some(dir) = &session.wallet_dir {
+        release_wallet_dir(dir);
+    }
+    // Dropping the file releases the lock for other processes.
+    session.dir_lock.lock().unwrap_or_else(|e| e.into_inner()).take();
+    Ok(session)
+}
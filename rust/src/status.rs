@@ -0,0 +1,83 @@
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+
+use epic_wallet_libwallet::StatusMessage;
+use serde_json::{json, Value};
+
+/// Queue of owner API status messages for one wallet session.
+///
+/// Owner API calls made for the session send their `StatusMessage`s here, and
+/// callers drain them with `rust_status_poll`. Messages are kept until they
+/// are polled or the session is closed.
+pub struct StatusQueue {
+    sender: Sender<StatusMessage>,
+    receiver: Mutex<Receiver<StatusMessage>>,
+}
+
+impl StatusQueue {
+    pub fn new() -> Self {
+        let (sender, receiver) = channel();
+        StatusQueue {
+            sender,
+            receiver: Mutex::new(receiver),
+        }
+    }
+
+    /// A sender to hand to `Owner::new` or `scan`.
+    pub fn sender(&self) -> Sender<StatusMessage> {
+        self.sender.clone()
+    }
+
+    /// Take every message queued so far, oldest first, as JSON.
+    pub fn drain(&self) -> Vec<Value> {
+        let receiver = self.receiver.lock().unwrap_or_else(|e| e.into_inner());
+        receiver.try_iter().map(|m| status_to_json(&m)).collect()
+    }
+}
+
+impl Default for StatusQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Convert a status message to the JSON shape returned over the FFI.
+///
+/// Every message has a `kind` and a `message`; `scanning` messages also carry
+/// a `percent` from 0 to 100.
+pub fn status_to_json(message: &StatusMessage) -> Value {
+    match message {
+        StatusMessage::UpdatingOutputs(m) => json!({"kind": "updating_outputs", "message": m}),
+        StatusMessage::UpdatingTransactions(m) => {
+            json!({"kind": "updating_transactions", "message": m})
+        }
+        StatusMessage::FullScanWarn(m) => json!({"kind": "full_scan_warning", "message": m}),
+        StatusMessage::Scanning(m, percent) => {
+            json!({"kind": "scanning", "message": m, "percent": percent})
+        }
+        StatusMessage::ScanningComplete(m) => json!({"kind": "scanning_complete", "message": m}),
+        StatusMessage::UpdateWarning(m) => json!({"kind": "update_warning", "message": m}),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drain_returns_messages_in_order() {
+        let queue = StatusQueue::new();
+        let sender = queue.sender();
+        sender.send(StatusMessage::Scanning("Scanning".to_string(), 40)).unwrap();
+        sender.send(StatusMessage::ScanningComplete("Done".to_string())).unwrap();
+
+        assert_eq!(
+            queue.drain(),
+            vec![
+                json!({"kind": "scanning", "message": "Scanning", "percent": 40}),
+                json!({"kind": "scanning_complete", "message": "Done"}),
+            ]
+        );
+        assert!(queue.drain().is_empty());
+    }
+}
@@ -0,0 +1,255 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::{Mutex, MutexGuard};
+
+use ffi_helpers::{export_task, Task};
+use ffi_helpers::task::{CancellationToken, TaskHandle};
+use serde_json::Value;
+
+use crate::envelope::{self, ErrorCode, FfiError};
+use crate::ffi::{panic_error, _create_tx, _tx_finalize, _tx_send_http, _txs_get, _wallet_scan_outputs};
+use crate::session::{self, SessionId};
+use crate::wallet::SendAmount;
+
+/// A long-running wallet operation.
+#[derive(Debug, Clone)]
+pub enum Operation {
+    /// Scan `number_of_blocks` blocks from `start_height` for outputs.
+    Scan {
+        start_height: u64,
+        number_of_blocks: u64,
+    },
+    /// Refresh outputs and transactions from the node.
+    Refresh,
+    /// Create a transaction and send it over epicbox.
+    Send {
+        amount: SendAmount,
+        address: String,
+        epicbox_config: String,
+        minimum_confirmations: u64,
+        note: String,
+        return_slate: bool,
+    },
+    /// Create a transaction and send it over http.
+    SendHttp {
+        amount: SendAmount,
+        address: String,
+        selection_strategy_is_use_all: bool,
+        minimum_confirmations: u64,
+        message: String,
+    },
+    /// Finalize a slate and post the transaction.
+    Finalize {
+        slate_json: String,
+    },
+}
+
+impl Operation {
+    /// Whether the operation spends from the wallet, which a locked session
+    /// refuses.
+    fn spends(&self) -> bool {
+        matches!(
+            self,
+            Operation::Send { .. } | Operation::SendHttp { .. } | Operation::Finalize { .. }
+        )
+    }
+}
+
+/// Wallet operation task.
+///
+/// The output is always a result envelope; failures of the operation are
+/// reported inside it rather than as a task error.
+#[derive(Debug, Clone)]
+pub struct WalletTask {
+    pub session_id: SessionId,
+    pub operation: Operation,
+    /// Cancels the task. Unlike the token of its task handle, this one stays
+    /// reachable while another thread waits on the handle.
+    cancel: CancellationToken,
+}
+
+impl WalletTask {
+    pub fn new(session_id: SessionId, operation: Operation) -> Self {
+        WalletTask {
+            session_id,
+            operation,
+            cancel: CancellationToken::new(),
+        }
+    }
+}
+
+/// Spawn a wallet operation task.
+impl Task for WalletTask {
+    type Output = String;
+
+    fn run(&self, _: &CancellationToken) -> Result<Self::Output, anyhow::Error> {
+        let result = panic::catch_unwind(AssertUnwindSafe(|| self.execute(&self.cancel)))
+            .unwrap_or_else(|payload| Err(panic_error(payload)));
+        Ok(envelope::to_json(result))
+    }
+}
+
+fn cancelled() -> FfiError {
+    FfiError::new(ErrorCode::Cancelled, "Task was cancelled")
+}
+
+impl WalletTask {
+    fn execute(&self, cancel_tok: &CancellationToken) -> Result<Value, FfiError> {
+        if cancel_tok.cancelled() {
+            return Err(cancelled());
+        }
+
+        if self.operation.spends() {
+            session::get_session(self.session_id)?.spend(|| self.run_operation(cancel_tok))
+        } else {
+            self.run_operation(cancel_tok)
+        }
+    }
+
+    fn run_operation(&self, cancel_tok: &CancellationToken) -> Result<Value, FfiError> {
+        ensure_wallet!(self.session_id, wallet, sek_key, status_send, stop);
+
+        match &self.operation {
+            Operation::Scan { start_height, number_of_blocks } => {
+                // The scan checks between batches for either a cancelled task or
+                // `rust_cancel_operation`, keeping the progress up to the last batch.
+                stop.finish(_wallet_scan_outputs(
+                    wallet,
+                    sek_key,
+                    *start_height,
+                    *number_of_blocks,
+                    status_send,
+                    || cancel_tok.cancelled() || stop.is_stopped(),
+                ))
+            }
+            Operation::Refresh => stop.finish(_txs_get(wallet, sek_key, true, status_send, stop.flag())),
+            Operation::Send {
+                amount,
+                address,
+                epicbox_config,
+                minimum_confirmations,
+                note,
+                return_slate,
+            } => stop.finish(_create_tx(
+                wallet,
+                sek_key,
+                *amount,
+                address,
+                0,
+                epicbox_config,
+                *minimum_confirmations,
+                note,
+                *return_slate,
+                stop.flag(),
+            )),
+            Operation::SendHttp {
+                amount,
+                address,
+                selection_strategy_is_use_all,
+                minimum_confirmations,
+                message,
+            } => stop.finish(_tx_send_http(
+                wallet,
+                sek_key,
+                *selection_strategy_is_use_all,
+                *minimum_confirmations,
+                message,
+                *amount,
+                address,
+                stop.flag(),
+            )),
+            Operation::Finalize { slate_json } => Ok(_tx_finalize(wallet, sek_key, slate_json)?),
+        }
+    }
+}
+
+export_task! {
+    Task: WalletTask;
+    spawn: wallet_task_spawn;
+    wait: wallet_task_wait;
+    poll: wallet_task_poll;
+    cancel: wallet_task_cancel;
+    cancelled: wallet_task_cancelled;
+    handle_destroy: wallet_task_handle_destroy;
+    result_destroy: wallet_task_result_destroy;
+}
+
+/// A spawned wallet task, as handed to the caller.
+///
+/// Its result is handed out once, by either [`poll`](Self::poll) or
+/// [`wait`](Self::wait).
+pub struct WalletTaskHandle {
+    /// The spawned task, or null once its result has been taken.
+    task: Mutex<*mut TaskHandle<String>>,
+    cancel: CancellationToken,
+}
+
+impl WalletTaskHandle {
+    pub fn spawn(task: &WalletTask) -> Self {
+        WalletTaskHandle {
+            task: Mutex::new(unsafe { wallet_task_spawn(task) }),
+            cancel: task.cancel.clone(),
+        }
+    }
+
+    fn task(&self) -> MutexGuard<'_, *mut TaskHandle<String>> {
+        self.task.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The result envelope of the task if it has finished and the result was
+    /// not taken yet.
+    pub fn poll(&self) -> Option<String> {
+        let mut task = self.task();
+        if task.is_null() {
+            return None;
+        }
+        let result = unsafe { wallet_task_poll(*task) };
+        if result.is_null() {
+            return None;
+        }
+        let envelope_json = unsafe { (*result).clone() };
+        unsafe {
+            wallet_task_result_destroy(result);
+            wallet_task_handle_destroy(*task);
+        }
+        *task = ptr::null_mut();
+        Some(envelope_json)
+    }
+
+    /// Block until the task has finished, returning its result envelope.
+    ///
+    /// Fails with a `lifecycle` error if the result was already taken.
+    pub fn wait(&self) -> Result<String, FfiError> {
+        let task = std::mem::replace(&mut *self.task(), ptr::null_mut());
+        if task.is_null() {
+            return Err(FfiError::new(ErrorCode::Lifecycle, "The task result was already taken"));
+        }
+        // Waiting consumes the task handle.
+        let result = unsafe { wallet_task_wait(task) };
+        if result.is_null() {
+            return Err(FfiError::new(ErrorCode::Backend, "The task stopped without a result"));
+        }
+        let envelope_json = unsafe { (*result).clone() };
+        unsafe { wallet_task_result_destroy(result) };
+        Ok(envelope_json)
+    }
+
+    /// Ask the task to stop, also while another thread waits on it.
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    pub fn cancelled(&self) -> bool {
+        self.cancel.cancelled()
+    }
+}
+
+impl Drop for WalletTaskHandle {
+    fn drop(&mut self) {
+        self.cancel();
+        let task = *self.task();
+        if !task.is_null() {
+            unsafe { wallet_task_handle_destroy(task) };
+        }
+    }
+}
@@ -0,0 +1,22 @@
+use std::fs;
+use std::path::Path;
+
+use crate::wallet::{write_wallet_metadata, WalletMetadata};
+
+/// Create a wallet directory at `dir` with a seed file but no wallet database,
+/// replacing anything there. With a `chain`, metadata naming the wallet after
+/// the directory is written too, as for wallets created by this library.
+pub(crate) fn fake_wallet(dir: &Path, chain: Option<&str>) {
+    let _ = fs::remove_dir_all(dir);
+    fs::create_dir_all(dir.join("wallet_data")).unwrap();
+    fs::write(dir.join("wallet_data/wallet.seed"), b"encrypted seed").unwrap();
+    if let Some(chain) = chain {
+        let metadata = WalletMetadata {
+            name: dir.file_name().unwrap().to_string_lossy().into_owned(),
+            chain: Some(chain.to_string()),
+            created_at: Some(1_700_000_000),
+            last_confirmed_height: None,
+        };
+        write_wallet_metadata(dir, &metadata).unwrap();
+    }
+}
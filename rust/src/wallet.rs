@@ -1,4 +1,7 @@
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::sync::mpsc::Sender;
 use std::sync::atomic::AtomicBool;
 use serde_derive::{Deserialize, Serialize};
 use epic_keychain::ExtKeychain;
@@ -8,17 +11,23 @@ use epic_util::secp::{PublicKey, Secp256k1, SecretKey};
 use epic_wallet_api::Owner;
 use epic_wallet_config::{EpicboxConfig, WalletConfig};
 use epic_wallet_impls::{DefaultLCProvider, HTTPNodeClient};
-use epic_wallet_libwallet::{address, scan, wallet_lock, AddressType, EpicboxAddress, Error, InitTxArgs, InitTxSendArgs, WalletInst, Slate};
+use epic_wallet_libwallet::{address, scan, wallet_lock, AddressType, EpicboxAddress, Error, InitTxArgs, InitTxSendArgs, StatusMessage, WalletInst, Slate};
 use epic_wallet_libwallet::api_impl::owner;
 use epic_wallet_libwallet::api_impl::foreign;
 use uuid::Uuid;
 use crate::config::{create_wallet_config, Config};
 use epic_wallet_libwallet::Address;
 use epic_wallet_libwallet::WalletLCProvider;
+use epic_wallet_util::epic_core::core::amount_to_hr_string;
+use epic_wallet_util::epic_core::libtx::tx_fee;
+use epic_wallet_libwallet::WalletBackend;
 use epic_wallet_libwallet::NodeClient;
 use epic_keychain::Keychain;
 use epic_wallet_impls::DefaultWalletImpl;
-use std::cmp::Ordering;
+use std::cmp::{self, Ordering};
+use std::num::ParseIntError;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 /// Wallet type.
 pub type Wallet = Arc<
@@ -47,49 +56,289 @@ pub struct WalletInfoFormatted {
     pub amount_locked: f64,
 }
 
-/// Strategy for transaction.
+/// A wallet account.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AccountInfo {
+    pub label: String,
+    /// BIP32 path of the account's parent key, e.g. `m/1/0`.
+    pub path: String,
+    /// Whether sends, receives, balances and history use this account.
+    pub active: bool,
+}
+
+/// Coin selection strategies quoted by [`tx_strategies`], by name.
+const SELECTION_STRATEGIES: [(&str, bool); 2] = [("smallest", false), ("all", true)];
+
+/// Fee quote for one coin selection strategy.
 #[derive(Serialize, Deserialize)]
 struct Strategy {
+    /// `"smallest"` or `"all"`.
+    strategy: String,
     selection_strategy_is_use_all: bool,
+    /// Amount plus fee, debited from the wallet.
     total: u64,
     fee: u64,
+    /// Number of outputs spent; absent when the amount can't be covered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    inputs: Option<usize>,
+    /// Amount returned as change; absent when the amount can't be covered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    change: Option<u64>,
+    /// Set when the spendable outputs can't cover the amount plus fee.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    not_enough_funds: Option<Shortfall>,
 }
 
-/// Get transaction strategies.
+/// Funds missing from a [`Strategy`].
+#[derive(Serialize, Deserialize)]
+struct Shortfall {
+    available: u64,
+    needed: u64,
+}
+
+/// Get fee quotes for every coin selection strategy.
+///
+/// A strategy that can't cover the amount is still quoted, carrying the
+/// available and needed amounts instead of failing the whole estimate.
 pub fn tx_strategies(
     wallet: &Wallet,
     keychain_mask: Option<SecretKey>,
     amount: u64,
     minimum_confirmations: u64,
 ) -> Result<String, Error> {
+    let spendable = spendable_values(wallet, minimum_confirmations)?;
     let mut result = vec![];
     wallet_lock!(wallet, w);
 
-    let args = InitTxArgs {
-        src_acct_name: None,
-        amount,
-        minimum_confirmations,
-        max_outputs: 500,
-        num_change_outputs: 1,
-        estimate_only: Some(true),
-        message: None,
-        ..Default::default()
-    };
+    for (name, use_all) in SELECTION_STRATEGIES {
+        let args = InitTxArgs {
+            src_acct_name: None,
+            amount,
+            minimum_confirmations,
+            max_outputs: DEFAULT_MAX_OUTPUTS,
+            num_change_outputs: 1,
+            selection_strategy_is_use_all: use_all,
+            estimate_only: Some(true),
+            message: None,
+            ..Default::default()
+        };
 
-    match owner::init_send_tx(&mut **w, keychain_mask.as_ref(), args, true) {
-        Ok(slate) => {
-            result.push(Strategy {
-                selection_strategy_is_use_all: false,
-                total: slate.amount,
+        // An estimate reports the value of the outputs it selected as the
+        // slate amount, but not the outputs themselves. An amount too large
+        // to add the fee to can't be covered either.
+        let quote = owner::init_send_tx(&mut **w, keychain_mask.as_ref(), args, true)
+            .and_then(|slate| match amount.checked_add(slate.fee) {
+                Some(total) => Ok((slate, total)),
+                None => Err(not_enough_funds(slate.amount, u64::MAX)),
+            });
+        let strategy = match quote {
+            Ok((slate, total)) => Strategy {
+                strategy: name.to_string(),
+                selection_strategy_is_use_all: use_all,
+                total,
                 fee: slate.fee,
+                inputs: count_inputs(&spendable, amount, DEFAULT_MAX_OUTPUTS as usize, use_all),
+                change: Some(slate.amount.saturating_sub(total)),
+                not_enough_funds: None,
+            },
+            Err(Error::NotEnoughFunds { available, needed, .. }) => Strategy {
+                strategy: name.to_string(),
+                selection_strategy_is_use_all: use_all,
+                total: needed,
+                fee: needed.saturating_sub(amount),
+                inputs: None,
+                change: None,
+                not_enough_funds: Some(Shortfall { available, needed }),
+            },
+            Err(e) => return Err(e),
+        };
+        result.push(strategy);
+    }
 
-            });
-        }, Err(e) => {
-            return Err(e);
+    Ok(serde_json::to_string(&result).unwrap())
+}
+
+/// A `NotEnoughFunds` error for `needed` nanoepic when `available` can be spent.
+fn not_enough_funds(available: u64, needed: u64) -> Error {
+    Error::NotEnoughFunds {
+        available,
+        available_disp: amount_to_hr_string(available, false),
+        needed,
+        needed_disp: amount_to_hr_string(needed, false),
+    }
+}
+
+/// Values of the active account's outputs spendable with
+/// `minimum_confirmations`, in ascending order.
+fn spendable_values(wallet: &Wallet, minimum_confirmations: u64) -> Result<Vec<u64>, Error> {
+    wallet_lock!(wallet, w);
+    let current_height = w.w2n_client().get_chain_tip()?.0;
+    let parent_key_id = w.parent_key_id();
+    let mut values: Vec<u64> = w
+        .iter()
+        .filter(|out| {
+            out.root_key_id == parent_key_id
+                && out.eligible_to_spend(current_height, minimum_confirmations)
+        })
+        .map(|out| out.value)
+        .collect();
+    values.sort_unstable();
+    Ok(values)
+}
+
+/// Most outputs a transaction spends unless sweeping needs more.
+const DEFAULT_MAX_OUTPUTS: u32 = 500;
+
+fn sum_values(values: &[u64]) -> u64 {
+    values.iter().fold(0, |sum, value| sum.saturating_add(*value))
+}
+
+/// The outputs libwallet picks from `sorted_values` to cover `amount`: the
+/// smallest that do, or all of them with `use_all`, from the first run of
+/// `max_outputs` that can. Without one, the smallest outputs that cover it,
+/// however many; without those, the largest `max_outputs`.
+fn select_coins(sorted_values: &[u64], amount: u64, max_outputs: usize, use_all: bool) -> &[u64] {
+    fn select_from(values: &[u64], amount: u64, use_all: bool) -> Option<&[u64]> {
+        if sum_values(values) < amount {
+            return None;
         }
+        if use_all {
+            return Some(values);
+        }
+        let mut selected = 0u64;
+        let count = values
+            .iter()
+            .take_while(|value| {
+                let more = selected < amount;
+                selected = selected.saturating_add(**value);
+                more
+            })
+            .count();
+        Some(&values[..count])
     }
 
-    Ok(serde_json::to_string(&result).unwrap())
+    if sorted_values.len() > max_outputs {
+        let windowed = sorted_values.windows(max_outputs).find_map(|window| select_from(window, amount, use_all));
+        if let Some(selected) = windowed.or_else(|| select_from(sorted_values, amount, false)) {
+            return selected;
+        }
+    } else if let Some(selected) = select_from(sorted_values, amount, use_all) {
+        return selected;
+    }
+    &sorted_values[sorted_values.len().saturating_sub(max_outputs)..]
+}
+
+/// Count the outputs a send of `amount` with one change output spends, running
+/// coin selection the way libwallet does: selecting again with the fee added
+/// until the selection covers both. `None` if the outputs can't cover it.
+fn count_inputs(sorted_values: &[u64], amount: u64, max_outputs: usize, use_all: bool) -> Option<usize> {
+    let mut coins = select_coins(sorted_values, amount, max_outputs, use_all);
+    let mut total = sum_values(coins);
+    let mut amount_with_fee = amount.checked_add(tx_fee(coins.len(), 1, 1, None))?;
+    if total == 0 || (total < amount_with_fee && coins.len() == sorted_values.len()) {
+        return None;
+    }
+    // Anything but an exact match needs a change output.
+    if total != amount_with_fee {
+        amount_with_fee = amount.checked_add(tx_fee(coins.len(), 2, 1, None))?;
+        while total < amount_with_fee {
+            let previous = coins.len();
+            coins = select_coins(sorted_values, amount_with_fee, max_outputs, use_all);
+            if coins.len() == previous && sum_values(coins) == total {
+                return None;
+            }
+            total = sum_values(coins);
+            amount_with_fee = amount.checked_add(tx_fee(coins.len(), 2, 1, None))?;
+        }
+    }
+    Some(coins.len())
+}
+
+/// The largest amount a sweep can send.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct MaxSendable {
+    /// Spendable balance less the fee; zero if the fee eats the balance.
+    pub amount: u64,
+    /// Fee for spending every spendable output with no change output.
+    pub fee: u64,
+    /// Number of spendable outputs.
+    pub inputs: usize,
+    /// Total value of the spendable outputs.
+    pub spendable: u64,
+}
+
+impl MaxSendable {
+    /// The sweep of the spendable outputs worth `values`: one transaction
+    /// spending all of them with a single output for the recipient and no
+    /// change.
+    fn from_outputs(values: &[u64]) -> Self {
+        let spendable: u64 = values.iter().sum();
+        if values.is_empty() {
+            return MaxSendable { amount: 0, fee: 0, inputs: 0, spendable };
+        }
+        let fee = tx_fee(values.len(), 1, 1, None);
+        MaxSendable {
+            amount: spendable.saturating_sub(fee),
+            fee,
+            inputs: values.len(),
+            spendable,
+        }
+    }
+}
+
+/// Get the largest amount that can be sent with `minimum_confirmations`.
+///
+/// The amount spends every spendable output and leaves no change, so it is
+/// the spendable balance less the fee for that transaction.
+pub fn max_sendable(wallet: &Wallet, minimum_confirmations: u64) -> Result<MaxSendable, Error> {
+    let values = spendable_values(wallet, minimum_confirmations)?;
+    Ok(MaxSendable::from_outputs(&values))
+}
+
+/// Amount to send in a transaction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SendAmount {
+    /// Send exactly this many nanoepic.
+    Exact(u64),
+    /// Send the whole spendable balance less the fee, leaving no change.
+    Sweep,
+}
+
+impl FromStr for SendAmount {
+    type Err = ParseIntError;
+
+    /// Parse an amount in nanoepic, or `max` for a sweep.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "max" => Ok(SendAmount::Sweep),
+            _ => s.parse().map(SendAmount::Exact),
+        }
+    }
+}
+
+/// Resolve the amount, coin selection strategy and output limit of a send.
+///
+/// A sweep spends every spendable output, so it fails with `NotEnoughFunds`
+/// when the fee leaves nothing to send.
+fn resolve_send_amount(
+    wallet: &Wallet,
+    amount: SendAmount,
+    minimum_confirmations: u64,
+    selection_strategy_is_use_all: bool,
+) -> Result<(u64, bool, u32), Error> {
+    match amount {
+        SendAmount::Exact(amount) => {
+            Ok((amount, selection_strategy_is_use_all, DEFAULT_MAX_OUTPUTS))
+        }
+        SendAmount::Sweep => {
+            let max = max_sendable(wallet, minimum_confirmations)?;
+            if max.amount == 0 {
+                return Err(not_enough_funds(max.spendable, max.fee + 1));
+            }
+            let max_outputs = cmp::max(max.inputs as u32, DEFAULT_MAX_OUTPUTS);
+            Ok((max.amount, true, max_outputs))
+        }
+    }
 }
 
 /// Get wallet transactions.
@@ -97,9 +346,10 @@ pub fn txs_get(
     wallet: &Wallet,
     keychain_mask: Option<SecretKey>,
     refresh_from_node: bool,
+    status_send: Option<Sender<StatusMessage>>,
+    is_stopped: Arc<AtomicBool>,
 ) -> Result<String, Error> {
-    let is_stopped = Arc::new(AtomicBool::new(false));
-    let api = Owner::new(wallet.clone(), None, is_stopped.clone());
+    let api = Owner::new(wallet.clone(), status_send, is_stopped);
     let res = match api.retrieve_txs(
         keychain_mask.as_ref(),
         refresh_from_node,
@@ -125,21 +375,30 @@ pub fn txs_get(
 /// When return_slate is false (default), the transaction is sent via Epicbox.
 ///
 /// Step 1 of the 3-part transaction process (if return_slate is set).
+///
+/// A [`SendAmount::Sweep`] spends every spendable output and sends the balance
+/// less the fee, overriding the selection strategy.
 #[allow(clippy::too_many_arguments)]
 pub fn tx_create(
     wallet: &Wallet,
     keychain_mask: Option<SecretKey>,
-    amount: u64,
+    amount: SendAmount,
     minimum_confirmations: u64,
     selection_strategy_is_use_all: bool,
     epicbox_config: &str,
     address: &str,
     note: &str,
     return_slate: Option<bool>,
+    is_stopped: Arc<AtomicBool>,
 ) -> Result<String, Error> {
     let return_slate = return_slate.unwrap_or(false);
+    let (amount, selection_strategy_is_use_all, max_outputs) = resolve_send_amount(
+        wallet,
+        amount,
+        minimum_confirmations,
+        selection_strategy_is_use_all,
+    )?;
 
-    let is_stopped = Arc::new(AtomicBool::new(false));
     let owner_api = Owner::new(wallet.clone(), None, is_stopped.clone());
 
     // Only set epicbox config and send args if we want the wallet to relay via Epicbox.
@@ -158,10 +417,10 @@ pub fn tx_create(
     };
 
     let args = InitTxArgs {
-        src_acct_name: Some("default".into()),
+        src_acct_name: None,
         amount,
         minimum_confirmations,
-        max_outputs: 500,
+        max_outputs,
         num_change_outputs: 1,
         selection_strategy_is_use_all,
         send_args,
@@ -242,7 +501,7 @@ pub fn tx_receive(
         &mut **w,
         keychain_mask.as_ref(),
         &slate,
-        Some("default"),
+        None,
         message.map(|s| s.to_owned()),
         None,
         false,
@@ -291,6 +550,40 @@ pub fn tx_get(wallet: &Wallet, refresh_from_node: bool, tx_slate_id: &str) -> Re
     Ok(serde_json::to_string(&res.txs).unwrap())
 }
 
+/// List the wallet's accounts with their parent key paths.
+pub fn accounts(wallet: &Wallet, keychain_mask: Option<SecretKey>) -> Result<Vec<AccountInfo>, Error> {
+    let is_stopped = Arc::new(AtomicBool::new(false));
+    let api = Owner::new(wallet.clone(), None, is_stopped);
+    let active = {
+        wallet_lock!(wallet, w);
+        w.parent_key_id()
+    };
+    let accounts = api.accounts(keychain_mask.as_ref())?;
+    Ok(accounts
+        .into_iter()
+        .map(|account| AccountInfo {
+            active: account.path == active,
+            path: account.path.to_bip_32_string(),
+            label: account.label,
+        })
+        .collect())
+}
+
+/// Create an account, returning its parent key path.
+pub fn create_account(wallet: &Wallet, keychain_mask: Option<SecretKey>, label: &str) -> Result<String, Error> {
+    let is_stopped = Arc::new(AtomicBool::new(false));
+    let api = Owner::new(wallet.clone(), None, is_stopped);
+    let path = api.create_account_path(keychain_mask.as_ref(), label)?;
+    Ok(path.to_bip_32_string())
+}
+
+/// Make an account the one that sends, receives, balances and history use.
+pub fn set_active_account(wallet: &Wallet, keychain_mask: Option<SecretKey>, label: &str) -> Result<(), Error> {
+    let is_stopped = Arc::new(AtomicBool::new(false));
+    let api = Owner::new(wallet.clone(), None, is_stopped);
+    api.set_active_account(keychain_mask.as_ref(), label)
+}
+
 /// Convert decimal to nano.
 pub fn convert_deci_to_nano(amount: f64) -> u64 {
     let base_nano = 100000000;
@@ -305,7 +598,15 @@ pub fn nano_to_deci(amount: u64) -> f64 {
     decimal
 }
 
+/// Account used when the config does not name one.
+pub const DEFAULT_ACCOUNT: &str = "default";
+
 /// Open a wallet.
+///
+/// Fails with `WalletSeedDoesntExist` if there is no wallet in the configured
+/// directory, `WalletSeedDecryption` if the password is wrong and
+/// `UnknownAccountLabel` if the configured account does not exist. Without a
+/// configured account the `default` account is used.
 pub fn open_wallet(config_json: &str, password: &str) -> Result<(Wallet, Option<SecretKey>), Error> {
     let config = match Config::from_str(&config_json.to_string()) {
         Ok(config) => {
@@ -314,68 +615,27 @@ pub fn open_wallet(config_json: &str, password: &str) -> Result<(Wallet, Option<
             return Err(Error::GenericError("Unable to get wallet config".to_string()))
         }
     };
-    let wallet = match get_wallet(&config) {
-        Ok(wllet) => {
-            wllet
+    let (seed_path, backup_path) = seed_paths(&config);
+    restore_seed_backup(&seed_path, &backup_path)?;
+    let wallet = get_wallet(&config)?;
+    let secret_key = {
+        let mut wallet_lock = wallet.lock();
+        let lc = wallet_lock.lc_provider()?;
+        if !lc.wallet_exists(None)? {
+            return Err(Error::WalletSeedDoesntExist);
         }
-        Err(err) => {
-            return  Err(err);
+        let secret_key = lc.open_wallet(None, ZeroingString::from(password), true, false)?;
+
+        let account = config.account.as_deref().unwrap_or(DEFAULT_ACCOUNT);
+        let selected = lc.wallet_inst().and_then(|w| w.set_parent_key_id_by_name(account));
+        if let Err(err) = selected {
+            // Do not leave the database open behind a failed open.
+            let _ = lc.close_wallet(None);
+            return Err(err);
         }
+        secret_key
     };
-    let mut secret_key = None;
-    let mut opened = false;
-    {
-        let mut wallet_lock = wallet.lock();
-        let lc = match wallet_lock.lc_provider() {
-            Ok(lc_provider) => {
-                lc_provider
-            }
-            Err(err) => {
-                return  Err(err);
-            }
-        };
-        if let Ok(exists_wallet) = lc.wallet_exists(None) {
-            if exists_wallet {
-                let temp = match lc.open_wallet(
-                    None,
-                    ZeroingString::from(password),
-                    true,
-                    false) {
-                    Ok(tmp_key) => {
-                        tmp_key
-                    }
-                    Err(err) => {
-                        return Err(err);
-                    }
-                };
-                secret_key = temp;
-                let wallet_inst = match lc.wallet_inst() {
-                    Ok(wallet_backend) => {
-                        wallet_backend
-                    }
-                    Err(err) => {
-                        return Err(err);
-                    }
-                };
-                if let Some(account) = config.account {
-                    match wallet_inst.set_parent_key_id_by_name(&account) {
-                        Ok(_) => {
-                            ()
-                        }
-                        Err(err) => {
-                            return  Err(err);
-                        }
-                    }
-                    opened = true;
-                }
-            }
-        }
-    }
-    if opened {
-        Ok((wallet, secret_key))
-    } else {
-        Err(Error::WalletSeedDoesntExist)
-    }
+    Ok((wallet, secret_key))
 }
 
 /// Close a wallet.
@@ -408,47 +668,265 @@ pub fn validate_address(str_address: &str) -> bool {
     }
 }
 
-/// Delete a wallet.
-pub fn delete_wallet(config: Config) -> Result<String, Error> {
-    let mut result = String::from("");
-    // get wallet object in order to use class methods
-    let wallet = match get_wallet(&config) {
-        Ok(wllet) => {
-            wllet
+/// Directory under the wallet dir holding the seed file, as laid out by the
+/// LC provider.
+pub const WALLET_DATA_DIR: &str = "wallet_data";
+
+/// Name of the encrypted seed file.
+const SEED_FILE: &str = "wallet.seed";
+
+/// Copy of the seed file kept while its password is changed.
+const SEED_BACKUP_FILE: &str = "wallet.seed.password_change.bak";
+
+/// File next to the seed recording what this library created the wallet with.
+/// The LC provider does not keep the wallet name.
+const WALLET_METADATA_FILE: &str = "wallet_meta.json";
+
+/// What a wallet was created with.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WalletMetadata {
+    pub name: String,
+    /// Name of the chain, as in [`Config::chain`].
+    #[serde(default)]
+    pub chain: Option<String>,
+    /// Unix time in seconds.
+    #[serde(default)]
+    pub created_at: Option<u64>,
+    /// Height up to which outputs were confirmed when the wallet was last
+    /// closed.
+    #[serde(default)]
+    pub last_confirmed_height: Option<u64>,
+}
+
+fn wallet_metadata_path(wallet_dir: &Path) -> PathBuf {
+    wallet_dir.join(WALLET_DATA_DIR).join(WALLET_METADATA_FILE)
+}
+
+/// Write the metadata of the wallet in `wallet_dir`.
+pub fn write_wallet_metadata(wallet_dir: &Path, metadata: &WalletMetadata) -> Result<(), Error> {
+    let json = serde_json::to_vec(metadata)
+        .map_err(|e| Error::GenericError(format!("Unable to encode the wallet metadata: {}", e)))?;
+    fs::write(wallet_metadata_path(wallet_dir), json)
+        .map_err(|e| Error::GenericError(format!("Unable to write the wallet metadata: {}", e)))
+}
+
+/// Record what a wallet that was just created was created with. The wallet
+/// is usable without the record, so failing to write it is only logged.
+fn record_wallet_created(config: &Config, name: &str) {
+    let metadata = WalletMetadata {
+        name: name.to_string(),
+        chain: Some(config.chain_name().to_string()),
+        created_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .ok()
+            .map(|elapsed| elapsed.as_secs()),
+        last_confirmed_height: None,
+    };
+    if let Err(e) = write_wallet_metadata(Path::new(&config.wallet_dir), &metadata) {
+        warn!("{}", e);
+    }
+}
+
+/// Record the last confirmed height of an open wallet in its metadata, so the
+/// status of the closed wallet can report it without reading its database.
+/// Wallets without metadata are left alone, and failing is only logged.
+pub fn record_last_confirmed_height(wallet_dir: &Path, wallet: &Wallet) {
+    let metadata = match read_wallet_metadata(wallet_dir) {
+        Some(metadata) => metadata,
+        None => return,
+    };
+    let recorded = (|| -> Result<(), Error> {
+        let height = {
+            wallet_lock!(wallet, w);
+            w.last_confirmed_height()?
+        };
+        let metadata = WalletMetadata {
+            last_confirmed_height: Some(height),
+            ..metadata
+        };
+        write_wallet_metadata(wallet_dir, &metadata)
+    })();
+    if let Err(e) = recorded {
+        warn!("{}", e);
+    }
+}
+
+/// The recorded metadata of the wallet in `wallet_dir`, if it has any.
+/// Wallets created before it was recorded have none.
+pub fn read_wallet_metadata(wallet_dir: &Path) -> Option<WalletMetadata> {
+    let json = fs::read(wallet_metadata_path(wallet_dir)).ok()?;
+    serde_json::from_slice(&json).ok()
+}
+
+/// Whether `wallet_dir` holds a wallet seed, without instantiating the wallet.
+pub fn wallet_seed_exists(wallet_dir: &Path) -> bool {
+    let data_dir = wallet_dir.join(WALLET_DATA_DIR);
+    data_dir.join(SEED_FILE).is_file() || data_dir.join(SEED_BACKUP_FILE).is_file()
+}
+
+/// Paths of a wallet's seed file and of its password change backup.
+fn seed_paths(config: &Config) -> (PathBuf, PathBuf) {
+    let data_dir = Path::new(&config.wallet_dir).join(WALLET_DATA_DIR);
+    (data_dir.join(SEED_FILE), data_dir.join(SEED_BACKUP_FILE))
+}
+
+/// The files under `wallet_dir` that hold its encrypted seed, whether or not
+/// they exist.
+pub fn seed_files(wallet_dir: &Path) -> [PathBuf; 2] {
+    let data_dir = wallet_dir.join(WALLET_DATA_DIR);
+    [data_dir.join(SEED_FILE), data_dir.join(SEED_BACKUP_FILE)]
+}
+
+/// Whether `wallet_dir` is laid out as a wallet directory, even if its seed
+/// is gone.
+pub fn is_wallet_dir(wallet_dir: &Path) -> bool {
+    wallet_dir.join(WALLET_DATA_DIR).is_dir()
+}
+
+/// Put the seed backup left by an interrupted password change back in place.
+/// The backup is only used if the seed file itself is gone.
+fn restore_seed_backup(seed_path: &Path, backup_path: &Path) -> Result<(), Error> {
+    if backup_path.exists() && !seed_path.exists() {
+        fs::rename(backup_path, seed_path).map_err(|e| {
+            Error::GenericError(format!("Unable to restore the wallet seed backup: {}", e))
+        })?;
+    }
+    Ok(())
+}
+
+/// Re-encrypt a wallet's seed under a new password.
+///
+/// The old password is checked first. The seed file is backed up before the
+/// LC provider rewrites it, and the backup is put back if the rewrite fails or
+/// the new file does not decrypt to the same seed. Open sessions keep working,
+/// as their keychain masks do not depend on the password.
+pub fn change_password(config: &Config, old_password: &str, new_password: &str) -> Result<(), Error> {
+    let (seed_path, backup_path) = seed_paths(config);
+    restore_seed_backup(&seed_path, &backup_path)?;
+
+    let wallet = get_wallet(config)?;
+    let mut wallet_lock = wallet.lock();
+    let lc = wallet_lock.lc_provider()?;
+    if !lc.wallet_exists(None)? {
+        return Err(Error::WalletSeedDoesntExist);
+    }
+    let mnemonic = lc.get_mnemonic(None, ZeroingString::from(old_password))?;
+
+    fs::copy(&seed_path, &backup_path).map_err(|e| {
+        Error::GenericError(format!("Unable to back up the wallet seed: {}", e))
+    })?;
+    let changed = lc
+        .change_password(None, ZeroingString::from(old_password), ZeroingString::from(new_password))
+        .and_then(|_| lc.get_mnemonic(None, ZeroingString::from(new_password)))
+        .and_then(|new_mnemonic| {
+            if *new_mnemonic == *mnemonic {
+                Ok(())
+            } else {
+                Err(Error::GenericError("Re-encrypted wallet seed does not match".to_string()))
+            }
+        });
+    match changed {
+        Ok(()) => {
+            // The new seed is in place; a leftover backup is harmless.
+            let _ = fs::remove_file(&backup_path);
+            Ok(())
         }
         Err(e) => {
-            return  Err(e);
+            // Renaming onto an existing file fails on Windows, so clear the way first.
+            let _ = fs::remove_file(&seed_path);
+            restore_seed_backup(&seed_path, &backup_path)?;
+            Err(e)
         }
-    };
-    //First close the wallet
-    if let Ok(_) = close_wallet(&wallet) {
-        let is_stopped = Arc::new(AtomicBool::new(false));
-        let api = Owner::new(wallet.clone(), None, is_stopped.clone());
-        match api.delete_wallet(None) {
-            Ok(_) => {
-                result.push_str("deleted");
-            }
-            Err(err) => {
-                return  Err(err);
-            }
-        };
-    } else {
-        return Err(Error::GenericError("Error closing wallet".to_string()));
     }
-    Ok(result)
+}
+
+/// Decrypt a wallet's seed with its password and return its recovery phrase.
+///
+/// The phrase is zeroed when dropped. It must not be logged or stored.
+pub fn reveal_mnemonic(config: &Config, password: &str) -> Result<ZeroingString, Error> {
+    let (seed_path, backup_path) = seed_paths(config);
+    restore_seed_backup(&seed_path, &backup_path)?;
+
+    let wallet = get_wallet(config)?;
+    let mut wallet_lock = wallet.lock();
+    let lc = wallet_lock.lc_provider()?;
+    if !lc.wallet_exists(None)? {
+        return Err(Error::WalletSeedDoesntExist);
+    }
+    lc.get_mnemonic(None, ZeroingString::from(password))
+}
+
+/// Check `password` against the seed of an open wallet.
+pub fn verify_password(wallet: &Wallet, password: &str) -> Result<(), Error> {
+    let mut wallet_lock = wallet.lock();
+    let lc = wallet_lock.lc_provider()?;
+    // The phrase is zeroed when dropped.
+    lc.get_mnemonic(None, ZeroingString::from(password))?;
+    Ok(())
+}
+
+/// Whether a wallet exists and what it was created with.
+#[derive(Serialize, Debug, Clone)]
+pub struct WalletStatus {
+    pub seed_exists: bool,
+    /// The chain the config is for.
+    pub chain: String,
+    /// Height up to which outputs were last confirmed: from the database of
+    /// a wallet open in this process, otherwise as recorded when the wallet was
+    /// last closed.
+    pub last_confirmed_height: Option<u64>,
+    /// Name the wallet was created with, if it was recorded.
+    pub name: Option<String>,
+}
+
+/// Report whether the wallet in `config` exists and what it was created with,
+/// without decrypting its seed. Only `open`, the wallet of a session that has it
+/// open, is read; a closed wallet's database is not touched.
+pub fn wallet_status(config: &Config, open: Option<&Wallet>) -> Result<WalletStatus, Error> {
+    let seed_exists = {
+        let wallet = get_wallet(config)?;
+        let mut wallet_lock = wallet.lock();
+        let lc = wallet_lock.lc_provider()?;
+        lc.wallet_exists(None)?
+    };
+
+    let metadata = read_wallet_metadata(Path::new(&config.wallet_dir));
+    let last_confirmed_height = match open {
+        _ if !seed_exists => None,
+        Some(wallet) => {
+            wallet_lock!(wallet, w);
+            Some(w.last_confirmed_height()?)
+        }
+        None => metadata.as_ref().and_then(|m| m.last_confirmed_height),
+    };
+
+    Ok(WalletStatus {
+        seed_exists,
+        chain: config.chain_name().to_string(),
+        last_confirmed_height,
+        name: metadata.map(|m| m.name),
+    })
 }
 
 /// Send a transaction via HTTP.
+///
+/// A [`SendAmount::Sweep`] works as it does for [`tx_create`].
 pub fn tx_send_http(
     wallet: &Wallet,
     keychain_mask: Option<SecretKey>,
     selection_strategy_is_use_all: bool,
     minimum_confirmations: u64,
     message: &str,
-    amount: u64,
+    amount: SendAmount,
     address: &str,
+    is_stopped: Arc<AtomicBool>,
 ) -> Result<String, Error>{
-    let is_stopped = Arc::new(AtomicBool::new(false));
+    let (amount, selection_strategy_is_use_all, max_outputs) = resolve_send_amount(
+        wallet,
+        amount,
+        minimum_confirmations,
+        selection_strategy_is_use_all,
+    )?;
     let api = Owner::new(wallet.clone(), None, is_stopped.clone());
     let init_send_args = InitTxSendArgs {
         method: "http".to_string(),
@@ -459,10 +937,10 @@ pub fn tx_send_http(
     };
 
     let args = InitTxArgs {
-        src_acct_name: Some("default".to_string()),
+        src_acct_name: None,
         amount,
         minimum_confirmations,
-        max_outputs: 500,
+        max_outputs,
         num_change_outputs: 1,
         selection_strategy_is_use_all,
         message: Some(message.to_string()),
@@ -472,7 +950,7 @@ pub fn tx_send_http(
 
     match api.init_send_tx(keychain_mask.as_ref(), args, is_stopped.clone()) {
         Ok(slate) => {
-            println!("{}", "CREATE_TX_SUCCESS");
+            debug!("CREATE_TX_SUCCESS");
             //Get transaction for slate, for UI display
             let txs_result = match api.retrieve_txs(
                 keychain_mask.as_ref(),
@@ -494,7 +972,7 @@ pub fn tx_send_http(
             let str_tx_data = serde_json::to_string(&tx_data).unwrap();
             Ok(str_tx_data)
         } Err(err) => {
-            println!("CREATE_TX_ERROR_IN_HTTP_SEND {}", err.to_string());
+            error!("CREATE_TX_ERROR_IN_HTTP_SEND {}", err);
             return  Err(err);
         }
     }
@@ -543,9 +1021,10 @@ pub fn create_wallet(config: &str, phrase: &str, password: &str, name: &str) ->
             "".to_string()
         },
         Err(e) => {
-            e.to_string()
+            return Err(e);
         },
     };
+    record_wallet_created(&wallet_config, name);
     Ok(result)
 }
 
@@ -595,10 +1074,11 @@ pub fn get_wallet_info(
     wallet: &Wallet,
     keychain_mask: Option<SecretKey>,
     refresh_from_node: bool,
-    min_confirmations: u64
+    min_confirmations: u64,
+    status_send: Option<Sender<StatusMessage>>,
+    is_stopped: Arc<AtomicBool>,
 ) -> Result<WalletInfoFormatted, Error> {
-    let is_stopped = Arc::new(AtomicBool::new(false));
-    let api = Owner::new(wallet.clone(), None, is_stopped.clone());
+    let api = Owner::new(wallet.clone(), status_send, is_stopped);
 
     match api.retrieve_summary_info(keychain_mask.as_ref(), refresh_from_node, min_confirmations) {
         Ok((_, wallet_summary)) => {
@@ -660,6 +1140,7 @@ pub fn recover_from_mnemonic(mnemonic: &str, password: &str, config: &Config, na
                 false,
             ) {
                 Ok(_) => {
+                    record_wallet_created(config, name);
                     Ok(())
                 }
                 Err(e) => {
@@ -759,13 +1240,37 @@ pub fn get_chain_height(config: &str) -> Result<u64, Error> {
     Ok(chain_tip.0)
 }
 
+/// Number of blocks scanned between checks of the stop flag.
+pub const SCAN_BATCH_BLOCKS: u64 = 1000;
+
+/// Where an output scan ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanEnd {
+    /// Scanned the whole range, or up to the chain tip, ending at this height.
+    Finished(u64),
+    /// Stopped between batches after this height, where a new scan can resume.
+    Stopped(u64),
+}
+
+/// The last block of the batch starting at `from`, when scanning up to
+/// `end_height`. Both ends are included in the batch.
+fn scan_batch_end(from: u64, end_height: u64) -> u64 {
+    cmp::min(from.saturating_add(SCAN_BATCH_BLOCKS - 1), end_height)
+}
+
 /// Scan the wallet outputs.
+///
+/// Blocks are scanned in batches of `SCAN_BATCH_BLOCKS`, saving the last
+/// confirmed height after each one. `is_stopped` is checked before every
+/// batch, and once it returns true the scan ends with `ScanEnd::Stopped`.
 pub fn wallet_scan_outputs(
     wallet: &Wallet,
     keychain_mask: Option<SecretKey>,
     start_height: Option<u64>,
-    number_of_blocks_to_scan: Option<u64>
-) -> Result<String, Error> {
+    number_of_blocks_to_scan: Option<u64>,
+    status_send: Option<Sender<StatusMessage>>,
+    is_stopped: impl Fn() -> bool,
+) -> Result<ScanEnd, Error> {
     let tip = {
         wallet_lock!(wallet, w);
         match w.w2n_client().get_chain_tip() {
@@ -805,54 +1310,105 @@ pub fn wallet_scan_outputs(
         }
     };
 
-    match scan(
-        wallet.clone(),
-        keychain_mask.as_ref(),
-        false,
-        start_height,
-        end_height,
-        &None,
-    ) {
-        Ok(info) => {
-            let parent_key_id = {
-                wallet_lock!(wallet, w);
-                w.parent_key_id().clone()
-            };
+    let parent_key_id = {
+        wallet_lock!(wallet, w);
+        w.parent_key_id().clone()
+    };
 
-            {
-                wallet_lock!(wallet, w);
-                let mut batch = match w.batch(keychain_mask.as_ref()) {
-                    Ok(wallet_output_batch) => {
-                        wallet_output_batch
-                    }
-                    Err(err) => {
-                        return Err(err);
-                    }
-                };
-                match batch.save_last_confirmed_height(&parent_key_id, info.clone().height) {
-                    Ok(_) => {
-                        ()
-                    }
-                    Err(err) => {
-                        return  Err(err);
-                    }
-                };
-                match batch.commit() {
-                    Ok(_) => {
-                        ()
-                    }
-                    Err(err) => {
-                        return  Err(err);
-                    }
-                }
-            };
+    let mut from = start_height;
+    let mut last_scanned = start_height;
+    loop {
+        if is_stopped() {
+            return Ok(ScanEnd::Stopped(last_scanned));
+        }
+        let to = scan_batch_end(from, end_height);
+        let info = scan(
+            wallet.clone(),
+            keychain_mask.as_ref(),
+            false,
+            from,
+            to,
+            &status_send,
+        )?;
+
+        // Save progress after every batch so a stopped scan leaves the
+        // wallet consistent up to the last batch.
+        {
+            wallet_lock!(wallet, w);
+            let mut batch = w.batch(keychain_mask.as_ref())?;
+            batch.save_last_confirmed_height(&parent_key_id, info.height)?;
+            batch.commit()?;
+        }
 
-            // Return the last scanned block height for tracking progress.
-            let result = info.height;
-            let json_result = serde_json::to_string(&result).unwrap();
-            Ok(json_result)
-        }, Err(e) => {
-            return  Err(e);
+        last_scanned = info.height;
+        if to >= end_height {
+            break;
         }
+        // The scanned range includes `to`, so the next batch starts after it.
+        from = to + 1;
+    }
+
+    // Return the last scanned block height for tracking progress.
+    Ok(ScanEnd::Finished(last_scanned))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_batches_do_not_overlap() {
+        assert_eq!(scan_batch_end(1, 2500), SCAN_BATCH_BLOCKS);
+        assert_eq!(scan_batch_end(SCAN_BATCH_BLOCKS + 1, 2500), 2 * SCAN_BATCH_BLOCKS);
+        assert_eq!(scan_batch_end(2 * SCAN_BATCH_BLOCKS + 1, 2500), 2500);
+        assert_eq!(scan_batch_end(10, 10), 10);
+        assert_eq!(scan_batch_end(u64::MAX, u64::MAX), u64::MAX);
+    }
+
+    #[test]
+    fn test_max_sendable_from_funded_outputs() {
+        let values = [250_000_000, 1_000_000_000, 5_000_000_000];
+        let max = MaxSendable::from_outputs(&values);
+        assert_eq!(max.inputs, 3);
+        assert_eq!(max.spendable, 6_250_000_000);
+        // Three inputs, the recipient's output and no change output.
+        assert_eq!(max.fee, tx_fee(3, 1, 1, None));
+        assert!(max.fee > 0);
+        assert_eq!(max.amount + max.fee, max.spendable);
+
+        // Dust that does not cover its own fee leaves nothing to send.
+        let dust = MaxSendable::from_outputs(&[1, 1]);
+        assert_eq!(dust.amount, 0);
+        assert_eq!(dust.fee, tx_fee(2, 1, 1, None));
+
+        let empty = MaxSendable::from_outputs(&[]);
+        assert_eq!((empty.amount, empty.fee, empty.inputs, empty.spendable), (0, 0, 0, 0));
+    }
+
+    #[test]
+    fn test_count_inputs_of_funded_quotes() {
+        const EPIC: u64 = 100_000_000;
+        let values = [EPIC, 2 * EPIC, 5 * EPIC];
+        // Smallest first: enough of the smallest outputs for amount and fee.
+        assert_eq!(count_inputs(&values, EPIC / 2, 500, false), Some(1));
+        assert_eq!(count_inputs(&values, 2 * EPIC, 500, false), Some(2));
+        // Use all: every output.
+        assert_eq!(count_inputs(&values, EPIC / 2, 500, true), Some(3));
+        // The fee pushes a selection that only covers the amount to one more output.
+        let fee = tx_fee(2, 2, 1, None);
+        assert_eq!(count_inputs(&values, 3 * EPIC - fee + 1, 500, false), Some(3));
+        // A run of outputs further along when the smallest can't cover it.
+        assert_eq!(count_inputs(&[1, 1, EPIC, 5 * EPIC], 4 * EPIC, 2, false), Some(2));
+        // Not enough funds.
+        assert_eq!(count_inputs(&values, 8 * EPIC, 500, false), None);
+        assert_eq!(count_inputs(&[], EPIC, 500, false), None);
+    }
+
+    #[test]
+    fn test_parse_send_amount() {
+        assert_eq!("max".parse(), Ok(SendAmount::Sweep));
+        assert_eq!("100000000".parse(), Ok(SendAmount::Exact(100_000_000)));
+        assert!("MAX".parse::<SendAmount>().is_err());
+        assert!("-1".parse::<SendAmount>().is_err());
     }
 }
@@ -0,0 +1,160 @@
+//! Checks that strings handed out over FFI are given back in full.
+//!
+//! These tests count allocations with their own global allocator, so they run
+//! in a test binary of their own rather than the library's unit tests.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::ffi::{CStr, CString};
+use std::fs;
+use std::os::raw::c_char;
+use std::path::PathBuf;
+
+use epic_cash_wallet::envelope::ENVELOPE_VERSION;
+use epic_cash_wallet::ffi::{
+    get_mnemonic, rust_delete_wallet, rust_open_wallet, rust_string_free, rust_txs_get, rust_validate_address,
+    rust_wallet_balances, wallet_init,
+};
+
+/// Allocator that counts bytes allocated and freed on the current thread,
+/// so tests can check that FFI calls give back everything they hand out.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATED: Cell<usize> = const { Cell::new(0) };
+    static FREED: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATED.try_with(|a| a.set(a.get() + layout.size()));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let _ = FREED.try_with(|f| f.set(f.get() + layout.size()));
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Bytes allocated and not yet freed by the current thread.
+fn live_bytes() -> isize {
+    ALLOCATED.with(|a| a.get()) as isize - FREED.with(|f| f.get()) as isize
+}
+
+/// Read and free an FFI result envelope, returning its data as a string, or
+/// "kind: message" on failure.
+unsafe fn read_envelope(ptr: *const c_char) -> Result<String, String> {
+    let raw = CStr::from_ptr(ptr).to_str().unwrap().to_string();
+    rust_string_free(ptr as *mut c_char);
+    let envelope: serde_json::Value = serde_json::from_str(&raw)
+        .unwrap_or_else(|e| panic!("FFI returned a non-envelope result '{}': {}", raw, e));
+    assert_eq!(envelope["v"], ENVELOPE_VERSION);
+    if envelope["ok"] == true {
+        Ok(match &envelope["data"] {
+            serde_json::Value::String(s) => s.clone(),
+            data => data.to_string(),
+        })
+    } else {
+        Err(format!(
+            "{}: {}",
+            envelope["error"]["kind"].as_str().unwrap_or(""),
+            envelope["error"]["message"].as_str().unwrap_or("")
+        ))
+    }
+}
+
+/// Test that strings returned over FFI are fully released by rust_string_free.
+#[test]
+fn test_rust_string_free_releases_results() {
+    unsafe {
+        // Null is a no-op.
+        rust_string_free(std::ptr::null_mut());
+
+        let address = CString::new("invalid_address").unwrap();
+
+        // Warm up any lazily initialised state before measuring.
+        rust_string_free(rust_validate_address(address.as_ptr()) as *mut c_char);
+
+        let before = live_bytes();
+        for _ in 0..100 {
+            let ptr = rust_validate_address(address.as_ptr());
+            rust_string_free(ptr as *mut c_char);
+        }
+        let growth = live_bytes() - before;
+        assert_eq!(growth, 0, "rust_validate_address leaked {} bytes over 100 calls", growth);
+    }
+}
+
+/// Test that polling balances and transactions repeatedly does not grow memory.
+#[test]
+fn test_repeated_balances_and_txs_do_not_grow_memory() {
+    const ITERATIONS: usize = 50;
+
+    let test_dir = PathBuf::from("test_wallet_dir_repeated_polls");
+    let _ = fs::create_dir_all(&test_dir);
+    let config_json = serde_json::json!({
+        "wallet_dir": test_dir.to_str().unwrap(),
+        "check_node_api_http_addr": "http://epiccash.stackwallet.com:3413",
+        "chain": "floonet",
+        "account": "default",
+        "api_listen_port": 3415,
+        "api_listen_interface": "epiccash.stackwallet.com",
+    })
+    .to_string();
+
+    unsafe {
+        let config = CString::new(config_json).unwrap();
+        let password = CString::new("leak_test_password").unwrap();
+        let name = CString::new("leak_test_wallet").unwrap();
+
+        let phrase = CString::new(read_envelope(get_mnemonic()).unwrap()).unwrap();
+        read_envelope(wallet_init(config.as_ptr(), phrase.as_ptr(), password.as_ptr(), name.as_ptr()))
+            .expect("wallet creation failed");
+        let wallet_data = read_envelope(rust_open_wallet(config.as_ptr(), password.as_ptr()))
+            .expect("wallet open failed");
+
+        let wallet = CString::new(wallet_data).unwrap();
+        let no_refresh = CString::new("0").unwrap();
+        let min_confirmations = CString::new("10").unwrap();
+
+        let poll = || {
+            let balances = rust_wallet_balances(wallet.as_ptr(), no_refresh.as_ptr(), min_confirmations.as_ptr());
+            let balances_len = CStr::from_ptr(balances).to_bytes().len();
+            rust_string_free(balances as *mut c_char);
+
+            let txs = rust_txs_get(wallet.as_ptr(), no_refresh.as_ptr());
+            let txs_len = CStr::from_ptr(txs).to_bytes().len();
+            rust_string_free(txs as *mut c_char);
+
+            balances_len + txs_len
+        };
+
+        // Warm up caches and lazily initialised state before measuring.
+        let response_len = poll();
+        poll();
+
+        let before = live_bytes();
+        for _ in 0..ITERATIONS {
+            poll();
+        }
+        let growth = live_bytes() - before;
+
+        // Leaking every response would grow by ITERATIONS * response_len bytes.
+        let leaked_if_not_freed = (ITERATIONS * response_len) as isize;
+        println!("Memory growth over {} polls: {} bytes", ITERATIONS, growth);
+        assert!(
+            growth < leaked_if_not_freed / 10,
+            "Polling grew memory by {} bytes over {} iterations",
+            growth,
+            ITERATIONS
+        );
+
+        let _ = read_envelope(rust_delete_wallet(wallet.as_ptr(), config.as_ptr()));
+    }
+
+    let _ = fs::remove_dir_all(&test_dir);
+}
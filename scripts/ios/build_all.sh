@@ -17,17 +17,17 @@ cd build/rust
 rustup target add aarch64-apple-ios
 
 # Build for iOS device only.
-cp target/epic_cash_wallet.h libepic_cash_wallet.h
+cp include/epic_cash_wallet.h libepic_cash_wallet.h
 
 export IPHONEOS_DEPLOYMENT_TARGET=15.0
 export RUSTFLAGS="-C link-arg=-mios-version-min=15.0"
 cargo build --release --target aarch64-apple-ios
 
 # Generate the C header file using cbindgen.
-cbindgen --config cbindgen.toml --crate epic-cash-wallet --output target/epic_cash_wallet.h
+cbindgen --config cbindgen.toml --crate epic-cash-wallet --output include/epic_cash_wallet.h
 
 # Copy the generated header file.
-cp target/epic_cash_wallet.h libepic_cash_wallet.h
+cp include/epic_cash_wallet.h libepic_cash_wallet.h
 
 # Find and merge librandomx.a with libepic_cash_wallet.a.
 RANDOMX_LIB=$(find target/aarch64-apple-ios/release/build -name "librandomx.a" | head -n 1)
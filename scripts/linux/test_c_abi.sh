@@ -0,0 +1,18 @@
+#!/usr/bin/env bash
+# Build the library, then compile and run the C ABI test against the
+# checked-in header. Run from anywhere.
+set -e
+
+RUST_DIR="$(cd "$(dirname "$0")/../../rust" && pwd)"
+cd "$RUST_DIR"
+
+cargo build --lib
+
+mkdir -p target/ctest
+cc -std=c11 -Wall -Wextra -Werror \
+    -I include \
+    ctest/abi_test.c \
+    -L target/debug -lepic_cash_wallet \
+    -o target/ctest/abi_test
+
+LD_LIBRARY_PATH="target/debug${LD_LIBRARY_PATH:+:$LD_LIBRARY_PATH}" target/ctest/abi_test
@@ -15,18 +15,18 @@ cp -r ../../rust build/rust
 cd build/rust
 
 # building
-cp target/epic_cash_wallet.h libepic_cash_wallet.h
+cp include/epic_cash_wallet.h libepic_cash_wallet.h
 cargo lipo --release --targets aarch64-apple-darwin
 
 # Generate the C header file using cbindgen.
-cbindgen --config cbindgen.toml --crate epic-cash-wallet --output target/epic_cash_wallet.h
+cbindgen --config cbindgen.toml --crate epic-cash-wallet --output include/epic_cash_wallet.h
 
 # Create Headers directory for XCFramework.
 mkdir -p Headers
-cp target/epic_cash_wallet.h Headers/libepic_cash_wallet.h
+cp include/epic_cash_wallet.h Headers/libepic_cash_wallet.h
 
 # Copy autogenerated header to macos/Classes for use by the plugin.
-cp target/epic_cash_wallet.h ../../../../macos/Classes/FlutterLibepiccashPlugin.h
+cp include/epic_cash_wallet.h ../../../../macos/Classes/FlutterLibepiccashPlugin.h
 
 # Find and merge librandomx.a with libepic_cash_wallet.a
 RANDOMX_LIB=$(find target/aarch64-apple-darwin/release/build -name "librandomx.a" | head -n 1)